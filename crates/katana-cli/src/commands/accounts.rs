@@ -0,0 +1,209 @@
+use anyhow::{anyhow, bail, Result};
+use clap::{Args, ValueEnum};
+use katana_models::AccountsResponse;
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+
+use crate::{config::OutputFormat, format};
+use katana_client::Client;
+
+#[derive(Args, Debug)]
+pub struct AccountsArgs {
+    /// Instance name
+    pub name: String,
+    /// Write an accounts file for another tool instead of listing the accounts
+    #[arg(long, value_enum)]
+    pub export: Option<ExportFormat>,
+    /// Account to export for starkli, which takes one account per file
+    #[arg(long, default_value_t = 0)]
+    pub index: usize,
+    /// File to write the export to (defaults to stdout)
+    #[arg(long, short = 'o', requires = "export")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// starkli account file (`--account`), private key passed separately
+    Starkli,
+    /// sncast accounts file (`--accounts-file`) with every account
+    Sncast,
+}
+
+pub async fn execute(
+    client: &Client,
+    args: AccountsArgs,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let response = client.get_accounts(&args.name).await?;
+
+    if let Some(export) = args.export {
+        let file = match export {
+            ExportFormat::Starkli => starkli_account(&response, args.index)?,
+            ExportFormat::Sncast => sncast_accounts(&response),
+        };
+        let contents = serde_json::to_string_pretty(&file)?;
+
+        match &args.output {
+            Some(path) => {
+                std::fs::write(path, contents + "\n")?;
+                eprintln!("Wrote {}", path.display());
+            }
+            None => println!("{}", contents),
+        }
+
+        if export == ExportFormat::Starkli {
+            if let Some(key) = &response.accounts[args.index].private_key {
+                eprintln!("Use with: --rpc {} --private-key {}", response.rpc_url, key);
+            }
+        }
+        return Ok(());
+    }
+
+    match output_format {
+        OutputFormat::Json => {
+            let json_value = serde_json::to_value(&response)?;
+            format::print_json(&json_value);
+        }
+        OutputFormat::Table => {
+            println!(
+                "Prefunded accounts of '{}' (from {})",
+                response.instance_name, response.source
+            );
+            println!("RPC: {}", response.rpc_url);
+            if let Some(chain_id) = &response.chain_id {
+                match &response.chain_name {
+                    Some(name) => println!("Chain ID: {} ({})", name, chain_id),
+                    None => println!("Chain ID: {}", chain_id),
+                }
+            }
+
+            let unknown = || "-".to_string();
+            for (index, account) in response.accounts.iter().enumerate() {
+                println!();
+                println!("Account {}", index);
+                println!("  Address:     {}", account.address);
+                println!(
+                    "  Private key: {}",
+                    account.private_key.clone().unwrap_or_else(unknown)
+                );
+                println!(
+                    "  Public key:  {}",
+                    account.public_key.clone().unwrap_or_else(unknown)
+                );
+                if let Some(balance) = account.balance.as_deref().and_then(format::format_balance) {
+                    println!("  Balance:     {}", balance);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// starkli account file for one account. starkli reads the private key from
+/// `--private-key` or a keystore, so it is not part of the file.
+fn starkli_account(response: &AccountsResponse, index: usize) -> Result<Value> {
+    let account = response.accounts.get(index).ok_or_else(|| {
+        anyhow!(
+            "Instance '{}' has {} accounts, no account {}",
+            response.instance_name,
+            response.accounts.len(),
+            index
+        )
+    })?;
+
+    let Some(public_key) = &account.public_key else {
+        bail!("The public key of account {} is unknown", index);
+    };
+    let Some(class_hash) = &account.class_hash else {
+        bail!(
+            "The class hash of account {} is unknown; starkli needs the instance running",
+            index
+        );
+    };
+
+    Ok(json!({
+        "version": 1,
+        "variant": {
+            "type": "open_zeppelin",
+            "version": 1,
+            "public_key": public_key,
+            "legacy": false,
+        },
+        "deployment": {
+            "status": "deployed",
+            "class_hash": class_hash,
+            "address": account.address,
+        },
+    }))
+}
+
+/// sncast accounts file with every account, keyed by network then account
+/// name (`<instance>-<index>`)
+fn sncast_accounts(response: &AccountsResponse) -> Value {
+    let mut accounts = Map::new();
+    for (index, account) in response.accounts.iter().enumerate() {
+        let mut entry = json!({
+            "address": account.address,
+            "private_key": account.private_key,
+            "public_key": account.public_key,
+            "deployed": true,
+            "legacy": false,
+            "type": "open_zeppelin",
+        });
+        if let Some(class_hash) = &account.class_hash {
+            entry["class_hash"] = json!(class_hash);
+        }
+        accounts.insert(format!("{}-{}", response.instance_name, index), entry);
+    }
+
+    let network = response
+        .chain_name
+        .clone()
+        .unwrap_or_else(|| response.instance_name.clone());
+    json!({ network: accounts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use katana_models::PrefundedAccountInfo;
+
+    fn response(class_hash: Option<&str>) -> AccountsResponse {
+        AccountsResponse {
+            instance_name: "dev".to_string(),
+            rpc_url: "http://localhost:5050".to_string(),
+            chain_id: Some("0x4b4154414e41".to_string()),
+            chain_name: Some("KATANA".to_string()),
+            source: "rpc".to_string(),
+            accounts: vec![PrefundedAccountInfo {
+                address: "0x127fd".to_string(),
+                private_key: Some("0xc5b2f".to_string()),
+                public_key: Some("0x33246".to_string()),
+                balance: None,
+                class_hash: class_hash.map(str::to_string),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_starkli_account() {
+        let file = starkli_account(&response(Some("0x7dc7")), 0).unwrap();
+        assert_eq!(file["variant"]["public_key"], "0x33246");
+        assert_eq!(file["deployment"]["class_hash"], "0x7dc7");
+        assert_eq!(file["deployment"]["address"], "0x127fd");
+
+        assert!(starkli_account(&response(None), 0).is_err());
+        assert!(starkli_account(&response(Some("0x7dc7")), 1).is_err());
+    }
+
+    #[test]
+    fn test_sncast_accounts() {
+        let file = sncast_accounts(&response(None));
+        let account = &file["KATANA"]["dev-0"];
+        assert_eq!(account["private_key"], "0xc5b2f");
+        assert_eq!(account["deployed"], true);
+        assert!(account.get("class_hash").is_none());
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+
+use crate::{
+    config::OutputFormat,
+    format,
+    manifest::{self, ActionKind, InstanceSpec, PlannedAction, METADATA_FIELDS},
+};
+use katana_client::Client;
+use katana_models::{InstanceResponse, UpdateLabelsRequest};
+use std::collections::BTreeMap;
+
+/// How far `apply` may go beyond creating and updating instances
+#[derive(Debug, Clone, Default)]
+pub struct ApplyOptions {
+    /// Delete instances that are no longer in the manifest
+    pub prune: bool,
+    /// Prune instances matching this selector instead of those labelled
+    /// with the manifest
+    pub selector: Option<String>,
+    /// Confirm deletions
+    pub yes: bool,
+    /// Allow recreating instances, which loses their chain data
+    pub allow_replace: bool,
+}
+
+pub async fn execute(
+    client: &Client,
+    file: PathBuf,
+    options: ApplyOptions,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let manifest = manifest::Manifest::load(&file)?;
+    let current = client.list_instances().await?.instances;
+
+    let prunable = prune_candidates(
+        client,
+        &manifest,
+        &current,
+        options.prune,
+        options.selector.as_deref(),
+    )
+    .await?;
+    let actions = manifest::plan(&manifest, &current, &prunable);
+
+    if matches!(output_format, OutputFormat::Table) {
+        manifest::print_plan(&actions);
+    }
+
+    let count = |kind| actions.iter().filter(|a| a.action == kind).count();
+    let deletes = count(ActionKind::Delete);
+    if deletes > 0 && !options.yes {
+        bail!(
+            "{} instance(s) would be deleted; rerun with --yes to confirm",
+            deletes
+        );
+    }
+    let replaces = count(ActionKind::Replace);
+    if replaces > 0 && !options.allow_replace {
+        bail!(
+            "{} instance(s) would be recreated, losing their chain data; \
+             rerun with --allow-replace to confirm",
+            replaces
+        );
+    }
+
+    for action in &actions {
+        let spec = manifest.instances.iter().find(|s| s.name == action.name);
+        let instance = current.iter().find(|i| i.name == action.name);
+
+        let result = match (action.action, spec, instance) {
+            (ActionKind::Create, Some(spec), _) => create(client, spec).await,
+            (ActionKind::Update, Some(spec), Some(instance)) => {
+                update(client, action, spec, instance).await
+            }
+            (ActionKind::Replace, Some(spec), Some(instance)) => {
+                match remove(client, instance).await {
+                    Ok(()) => create(client, spec).await,
+                    Err(e) => Err(e),
+                }
+            }
+            (ActionKind::Delete, _, Some(instance)) => remove(client, instance).await,
+            _ => continue,
+        };
+
+        result.with_context(|| format!("Failed to apply changes to '{}'", action.name))?;
+
+        if matches!(output_format, OutputFormat::Table) {
+            println!("✓ {} ({:?})", action.name, action.action);
+        }
+    }
+
+    if let OutputFormat::Json = output_format {
+        let json_value = serde_json::to_value(&actions)?;
+        format::print_json(&json_value);
+    }
+
+    Ok(())
+}
+
+/// Names of the instances `--prune` may delete: those matching `selector`, or else the
+/// ones labelled as created from this manifest
+pub(crate) async fn prune_candidates(
+    client: &Client,
+    manifest: &manifest::Manifest,
+    current: &[InstanceResponse],
+    prune: bool,
+    selector: Option<&str>,
+) -> Result<Vec<String>> {
+    if !prune {
+        return Ok(vec![]);
+    }
+
+    match selector {
+        Some(selector) => Ok(super::select_instances(client, selector)
+            .await?
+            .into_iter()
+            .map(|instance| instance.name)
+            .collect()),
+        None => Ok(manifest.managed(current)),
+    }
+}
+
+async fn create(client: &Client, spec: &InstanceSpec) -> Result<()> {
+    let response = client.create_instance(spec.create_request()).await?;
+    client.start_instance(&response.name).await?;
+    Ok(())
+}
+
+/// Apply settings to an instance.
+///
+/// Labels and annotations are updated in place; other settings need the
+/// instance stopped, so it is restarted afterwards if it was running.
+async fn update(
+    client: &Client,
+    action: &PlannedAction,
+    spec: &InstanceSpec,
+    instance: &InstanceResponse,
+) -> Result<()> {
+    let metadata_changed = action
+        .changes
+        .iter()
+        .any(|c| METADATA_FIELDS.contains(&c.field));
+    if metadata_changed {
+        let request = UpdateLabelsRequest {
+            labels: metadata_changes(&instance.labels, &spec.labels),
+            annotations: metadata_changes(&instance.annotations, &spec.annotations),
+        };
+        client.update_labels(&instance.name, request).await?;
+    }
+
+    if action
+        .changes
+        .iter()
+        .all(|c| METADATA_FIELDS.contains(&c.field))
+    {
+        return Ok(());
+    }
+
+    let was_running = instance.status == "Running";
+
+    // Stopping would lose a paused or suspended state that start can't restore
+    if needs_stop(instance) && !was_running {
+        bail!(
+            "'{}' is {}; resume or stop it before applying changes",
+            instance.name,
+            instance.status.to_lowercase()
+        );
+    }
+
+    if was_running {
+        client.stop_instance(&instance.name, None).await?;
+    }
+
+    let updated = client
+        .update_instance(&instance.name, spec.update_request())
+        .await;
+    if !was_running {
+        return updated.map(|_| ());
+    }
+
+    // Restart even when the update was rejected, so it isn't left stopped
+    let started = client.start_instance(&instance.name).await;
+    match (updated, started) {
+        (Ok(_), started) => started.map(|_| ()),
+        (Err(e), Ok(_)) => Err(e),
+        (Err(e), Err(start_err)) => Err(e.context(format!(
+            "'{}' was stopped for the update and failed to restart: {}",
+            instance.name, start_err
+        ))),
+    }
+}
+
+async fn remove(client: &Client, instance: &InstanceResponse) -> Result<()> {
+    if needs_stop(instance) {
+        client.stop_instance(&instance.name, None).await?;
+    }
+
+    client.delete_instance(&instance.name, false).await
+}
+
+/// Changes turning `current` into `desired`, with `None` removing a key
+fn metadata_changes(
+    current: &BTreeMap<String, String>,
+    desired: &BTreeMap<String, String>,
+) -> BTreeMap<String, Option<String>> {
+    let removed = current
+        .keys()
+        .filter(|key| !desired.contains_key(*key))
+        .map(|key| (key.clone(), None));
+    let set = desired
+        .iter()
+        .map(|(key, value)| (key.clone(), Some(value.clone())));
+
+    removed.chain(set).collect()
+}
+
+fn needs_stop(instance: &InstanceResponse) -> bool {
+    !matches!(instance.status.as_str(), "Created" | "Stopped" | "Failed")
+}
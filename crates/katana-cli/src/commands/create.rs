@@ -1,38 +1,272 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use clap::{ArgAction, Args};
+use std::path::PathBuf;
 
-use crate::{config::OutputFormat, format};
+use crate::{commands::label::parse_key_value, config::OutputFormat, format};
 use katana_client::Client;
-use katana_models::CreateInstanceRequest;
+use katana_models::{CreateInstanceRequest, MessagingSettings, SetSecretRequest};
+
+/// Instance settings shared by `create` and `template create`.
+///
+/// Unset settings fall back to the template, then to the daemon defaults.
+#[derive(Args, Debug)]
+pub struct SettingsArgs {
+    /// Number of vCPUs [default: 2]
+    #[arg(long)]
+    pub vcpus: Option<u32>,
+    /// Memory size (e.g., "4G", "2048M") [default: 2G]
+    #[arg(long)]
+    pub memory: Option<String>,
+    /// Storage size (e.g., "10G", "5120M") [default: 10G]
+    #[arg(long)]
+    pub storage: Option<String>,
+    /// Enable development mode (use --dev=false to disable) [default: true]
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub dev: Option<bool>,
+    /// Enable TEE mode
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub tee: Option<bool>,
+    /// vCPU model (defaults to "host", or "EPYC-v4" with --tee)
+    #[arg(long)]
+    pub vcpu_type: Option<String>,
+    /// Hardware acceleration (kvm, tcg or auto) [default: auto]
+    #[arg(long, value_parser = ["kvm", "tcg", "auto"])]
+    pub accelerator: Option<String>,
+    /// Chain ID
+    #[arg(long)]
+    pub chain_id: Option<String>,
+    /// Block time in milliseconds (instant mining if not specified)
+    #[arg(long)]
+    pub block_time: Option<u64>,
+    /// Number of pre-funded dev accounts
+    #[arg(long)]
+    pub accounts: Option<u16>,
+    /// Disable transaction fees
+    #[arg(
+        long,
+        action = ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub disable_fee: Option<bool>,
+    /// Extra argument passed to Katana (repeatable), e.g. --katana-arg=--http.cors_origins=*
+    #[arg(long = "katana-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub katana_args: Vec<String>,
+    /// Delete the instance after this long (e.g., "2h", "1d")
+    #[arg(long)]
+    pub ttl: Option<String>,
+    /// Stop the instance after this long without RPC connections (e.g., "30m")
+    #[arg(long)]
+    pub idle_timeout: Option<String>,
+}
+
+/// L1 messaging flags of `create`
+#[derive(Args, Debug)]
+pub struct MessagingArgs {
+    /// Settlement contract address on the L1, enables messaging
+    #[arg(long, value_name = "ADDRESS", requires = "messaging_sender")]
+    pub messaging_contract: Option<String>,
+    /// Account sending messages to the L1
+    #[arg(long, value_name = "ADDRESS", requires = "messaging_contract")]
+    pub messaging_sender: Option<String>,
+    /// L1 RPC URL (localhost means the host, e.g. a local Anvil); may
+    /// reference a secret as ${NAME}
+    #[arg(long, value_name = "URL", requires = "messaging_contract")]
+    pub messaging_rpc: Option<String>,
+    /// Instance serving the L1, reached through its RPC port
+    #[arg(
+        long = "messaging-l1-instance",
+        value_name = "NAME",
+        requires = "messaging_contract",
+        conflicts_with = "messaging_rpc"
+    )]
+    pub messaging_l1_instance: Option<String>,
+    /// Kind of L1 [default: ethereum]
+    #[arg(long, value_parser = ["ethereum", "starknet"], requires = "messaging_contract")]
+    pub messaging_chain: Option<String>,
+    /// Seconds between polls of the L1 [default: 2]
+    #[arg(long, value_name = "SECS", requires = "messaging_contract")]
+    pub messaging_interval: Option<u64>,
+    /// L1 block to start reading messages from [default: 0]
+    #[arg(long, value_name = "BLOCK", requires = "messaging_contract")]
+    pub messaging_from_block: Option<u64>,
+    /// Secret holding the sender's private key [default: MESSAGING_KEY]
+    #[arg(long, value_name = "NAME", requires = "messaging_contract")]
+    pub messaging_key_secret: Option<String>,
+    /// File with the sender's private key, stored as the key secret
+    #[arg(long, value_name = "FILE", requires = "messaging_contract")]
+    pub messaging_key_file: Option<PathBuf>,
+}
+
+impl MessagingArgs {
+    fn settings(&self) -> Option<MessagingSettings> {
+        let contract_address = self.messaging_contract.clone()?;
+
+        Some(MessagingSettings {
+            chain: self.messaging_chain.clone(),
+            rpc_url: self.messaging_rpc.clone(),
+            l1_instance: self.messaging_l1_instance.clone(),
+            contract_address,
+            sender_address: self.messaging_sender.clone().unwrap_or_default(),
+            interval_secs: self.messaging_interval,
+            from_block: self.messaging_from_block,
+            key_secret: self.messaging_key_secret.clone(),
+        })
+    }
+
+    /// Key secret to set before the first start, as (name, value)
+    fn key_secret(&self) -> Result<Option<(String, String)>> {
+        let Some(path) = &self.messaging_key_file else {
+            return Ok(None);
+        };
+
+        let key = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let name = self
+            .messaging_key_secret
+            .clone()
+            .unwrap_or_else(|| "MESSAGING_KEY".to_string());
+
+        Ok(Some((name, key.trim().to_string())))
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct CreateArgs {
+    /// Instance name
+    pub name: String,
+    /// Template to take unset settings from
+    #[arg(long)]
+    pub template: Option<String>,
+    /// RPC port (auto-allocated if not specified)
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Label as key=value (repeatable)
+    #[arg(long = "label", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub labels: Vec<(String, String)>,
+    /// Annotation as key=value (repeatable)
+    #[arg(long = "annotation", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub annotations: Vec<(String, String)>,
+    /// Delete the instance at this time (RFC 3339, e.g., "2026-01-31T18:00:00Z")
+    #[arg(long, conflicts_with = "ttl")]
+    pub expires_at: Option<String>,
+    /// Starknet RPC URL to fork state from (localhost means the host); may
+    /// reference a secret as ${NAME}, e.g. an API key
+    #[arg(long)]
+    pub fork_provider: Option<String>,
+    /// Block number to fork at [default: the provider's latest]
+    #[arg(long, requires = "fork_provider")]
+    pub fork_block: Option<u64>,
+    /// Katana genesis JSON file, put on the instance's config drive
+    #[arg(long, value_name = "FILE")]
+    pub genesis: Option<PathBuf>,
+    /// Katana chain spec (chain.toml), put on the instance's config drive
+    #[arg(long, value_name = "FILE", conflicts_with = "genesis")]
+    pub chain_spec: Option<PathBuf>,
+    /// Environment variable for Katana as key=value (repeatable)
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub env: Vec<(String, String)>,
+    /// Pass Katana's args on the config drive instead of the kernel cmdline
+    #[arg(long)]
+    pub config_drive_args: bool,
+    /// Attach a memory balloon device, so stats report the guest's balloon
+    /// size (ignored with --tee)
+    #[arg(long)]
+    pub balloon: bool,
+    /// PEM certificate chain to put on the config drive
+    #[arg(long, value_name = "FILE", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key to put on the config drive
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+    #[command(flatten)]
+    pub messaging: MessagingArgs,
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+}
+
+impl CreateArgs {
+    /// Build the request, reading the files to put on the config drive
+    fn into_request(self) -> Result<CreateInstanceRequest> {
+        let read = |path: Option<PathBuf>| -> Result<Option<String>> {
+            path.map(|path| {
+                std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))
+            })
+            .transpose()
+        };
+        let genesis = read(self.genesis)?;
+        let chain_spec = read(self.chain_spec)?;
+        let tls_cert = read(self.tls_cert)?;
+        let tls_key = read(self.tls_key)?;
+        let settings = self.settings;
+
+        Ok(CreateInstanceRequest {
+            name: self.name,
+            template: self.template,
+            vcpus: settings.vcpus,
+            memory: settings.memory,
+            storage: settings.storage,
+            port: self.port,
+            dev: settings.dev,
+            tee: settings.tee,
+            vcpu_type: settings.vcpu_type,
+            chain_id: settings.chain_id,
+            block_time: settings.block_time,
+            accounts: settings.accounts,
+            disable_fee: settings.disable_fee,
+            extra_args: settings.katana_args,
+            fork_provider: self.fork_provider,
+            fork_block: self.fork_block,
+            messaging: self.messaging.settings(),
+            genesis,
+            chain_spec,
+            env: self.env.into_iter().collect(),
+            config_drive_args: self.config_drive_args.then_some(true),
+            tls_cert,
+            tls_key,
+            accelerator: settings.accelerator,
+            balloon: self.balloon.then_some(true),
+            sev_snp: None,
+            labels: self.labels.into_iter().collect(),
+            annotations: self.annotations.into_iter().collect(),
+            ttl: settings.ttl,
+            expires_at: self.expires_at,
+            idle_timeout: settings.idle_timeout,
+        })
+    }
+}
 
 pub async fn execute(
     client: &Client,
-    name: String,
-    vcpus: u32,
-    memory: String,
-    storage: String,
-    port: Option<u16>,
-    dev: bool,
-    tee: bool,
+    args: CreateArgs,
     output_format: &OutputFormat,
 ) -> Result<()> {
-    let request = CreateInstanceRequest {
-        name,
-        vcpus,
-        memory,
-        storage,
-        port,
-        dev,
-        tee,
-        vcpu_type: "host".to_string(),
-        chain_id: None,
-        block_time: None,
-        accounts: None,
-        disable_fee: false,
-        extra_args: vec![],
-    };
+    let key_secret = args.messaging.key_secret()?;
+    let request = args.into_request()?;
 
     let response = client.create_instance(request).await?;
 
+    // The messaging key must be in place before Katana starts
+    if let Some((secret, value)) = key_secret {
+        client
+            .set_secret(&response.name, &secret, SetSecretRequest { value })
+            .await?;
+    }
+
     // Automatically start the instance after creation
     let response = client.start_instance(&response.name).await?;
 
@@ -49,3 +283,189 @@ pub async fn execute(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        args: CreateArgs,
+    }
+
+    fn parse(args: &[&str]) -> CreateArgs {
+        TestCli::try_parse_from(std::iter::once("create").chain(args.iter().copied()))
+            .unwrap()
+            .args
+    }
+
+    #[test]
+    fn test_dev_flag() {
+        assert_eq!(parse(&["node"]).settings.dev, None);
+        assert_eq!(parse(&["node", "--dev"]).settings.dev, Some(true));
+        assert_eq!(parse(&["node", "--dev=true"]).settings.dev, Some(true));
+        assert_eq!(parse(&["node", "--dev=false"]).settings.dev, Some(false));
+        assert_eq!(parse(&["--dev", "node"]).settings.dev, Some(true));
+    }
+
+    #[test]
+    fn test_katana_flags() {
+        let request = parse(&[
+            "node",
+            "--chain-id",
+            "KATANA_TEST",
+            "--block-time",
+            "1000",
+            "--accounts",
+            "5",
+            "--disable-fee",
+            "--katana-arg",
+            "--http.cors_origins=*",
+            "--katana-arg=--invoke-max-steps=1000000",
+        ])
+        .into_request()
+        .unwrap();
+
+        assert_eq!(request.chain_id.as_deref(), Some("KATANA_TEST"));
+        assert_eq!(request.block_time, Some(1000));
+        assert_eq!(request.accounts, Some(5));
+        assert_eq!(request.disable_fee, Some(true));
+        assert_eq!(
+            request.extra_args,
+            vec!["--http.cors_origins=*", "--invoke-max-steps=1000000"]
+        );
+        // Left to the daemon, which picks the model based on TEE mode
+        assert_eq!(request.vcpu_type, None);
+    }
+
+    #[test]
+    fn test_template_leaves_settings_unset() {
+        let request = parse(&["--template", "perf", "node", "--vcpus", "16"])
+            .into_request()
+            .unwrap();
+
+        assert_eq!(request.template.as_deref(), Some("perf"));
+        assert_eq!(request.vcpus, Some(16));
+        assert_eq!(request.memory, None);
+        assert_eq!(request.tee, None);
+        assert_eq!(request.accelerator, None);
+    }
+
+    #[test]
+    fn test_labels() {
+        let request = parse(&[
+            "node",
+            "--label",
+            "team=bridge",
+            "--label=env=ci",
+            "--annotation",
+            "owner=Bridge team",
+        ])
+        .into_request()
+        .unwrap();
+
+        assert_eq!(
+            request.labels.get("team").map(String::as_str),
+            Some("bridge")
+        );
+        assert_eq!(request.labels.get("env").map(String::as_str), Some("ci"));
+        assert_eq!(
+            request.annotations.get("owner").map(String::as_str),
+            Some("Bridge team")
+        );
+    }
+
+    #[test]
+    fn test_lifecycle_flags() {
+        let request = parse(&["node", "--ttl", "2h", "--idle-timeout", "30m"])
+            .into_request()
+            .unwrap();
+        assert_eq!(request.ttl.as_deref(), Some("2h"));
+        assert_eq!(request.idle_timeout.as_deref(), Some("30m"));
+        assert_eq!(request.expires_at, None);
+
+        let result = TestCli::try_parse_from([
+            "create",
+            "node",
+            "--ttl",
+            "2h",
+            "--expires-at",
+            "2030-01-01T00:00:00Z",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_config_drive_flags() {
+        let request = parse(&["node", "--env", "RUST_LOG=debug", "--config-drive-args"])
+            .into_request()
+            .unwrap();
+        assert_eq!(
+            request.env.get("RUST_LOG").map(String::as_str),
+            Some("debug")
+        );
+        assert_eq!(request.config_drive_args, Some(true));
+
+        assert!(TestCli::try_parse_from(["create", "node", "--tls-cert", "cert.pem"]).is_err());
+    }
+
+    #[test]
+    fn test_genesis_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let genesis = dir.path().join("genesis.json");
+        std::fs::write(&genesis, r#"{"number": 0}"#).unwrap();
+
+        let request = parse(&["node", "--genesis", genesis.to_str().unwrap()])
+            .into_request()
+            .unwrap();
+        assert_eq!(request.genesis.as_deref(), Some(r#"{"number": 0}"#));
+        assert_eq!(request.chain_spec, None);
+        assert_eq!(request.tls_cert, None);
+
+        let missing = dir.path().join("missing.json");
+        assert!(parse(&["node", "--genesis", missing.to_str().unwrap()])
+            .into_request()
+            .is_err());
+    }
+
+    #[test]
+    fn test_messaging_flags() {
+        let request = parse(&[
+            "l3",
+            "--messaging-contract",
+            "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+            "--messaging-sender",
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266",
+            "--messaging-rpc",
+            "http://localhost:8545",
+            "--messaging-interval",
+            "5",
+        ])
+        .into_request()
+        .unwrap();
+
+        let messaging = request.messaging.unwrap();
+        assert_eq!(messaging.rpc_url.as_deref(), Some("http://localhost:8545"));
+        assert_eq!(messaging.interval_secs, Some(5));
+        assert_eq!(messaging.key_secret, None);
+        assert!(parse(&["l3"]).into_request().unwrap().messaging.is_none());
+
+        // Messaging flags need a contract, and only one L1
+        assert!(TestCli::try_parse_from(["create", "l3", "--messaging-rpc", "http://l1"]).is_err());
+        assert!(TestCli::try_parse_from([
+            "create",
+            "l3",
+            "--messaging-contract",
+            "0x1",
+            "--messaging-sender",
+            "0x2",
+            "--messaging-rpc",
+            "http://l1",
+            "--messaging-l1-instance",
+            "l2",
+        ])
+        .is_err());
+    }
+}
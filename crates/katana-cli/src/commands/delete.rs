@@ -1,11 +1,32 @@
 use anyhow::Result;
 
+use crate::{
+    commands::{report_bulk, select_instances, BulkResult},
+    config::OutputFormat,
+};
 use katana_client::Client;
 
-pub async fn execute(client: &Client, name: String) -> Result<()> {
-    client.delete_instance(&name).await?;
+pub async fn execute(client: &Client, name: String, force: bool) -> Result<()> {
+    client.delete_instance(&name, force).await?;
 
     println!("✓ Instance '{}' deleted successfully!", name);
 
     Ok(())
 }
+
+/// Delete every instance matching a label selector
+pub async fn execute_selected(
+    client: &Client,
+    selector: &str,
+    force: bool,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let mut results = Vec::new();
+
+    for instance in select_instances(client, selector).await? {
+        let result = client.delete_instance(&instance.name, force).await;
+        results.push(BulkResult::new(instance.name, result));
+    }
+
+    report_bulk(&results, "deleted", output_format)
+}
@@ -0,0 +1,31 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::{config::OutputFormat, format, manifest};
+use katana_client::Client;
+
+pub async fn execute(
+    client: &Client,
+    file: PathBuf,
+    prune: bool,
+    selector: Option<String>,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let manifest = manifest::Manifest::load(&file)?;
+    let current = client.list_instances().await?.instances;
+
+    let prunable =
+        super::apply::prune_candidates(client, &manifest, &current, prune, selector.as_deref())
+            .await?;
+    let actions = manifest::plan(&manifest, &current, &prunable);
+
+    match output_format {
+        OutputFormat::Json => {
+            let json_value = serde_json::to_value(&actions)?;
+            format::print_json(&json_value);
+        }
+        OutputFormat::Table => manifest::print_plan(&actions),
+    }
+
+    Ok(())
+}
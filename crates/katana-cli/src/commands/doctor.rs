@@ -0,0 +1,142 @@
+use anyhow::{bail, Result};
+use byte_unit::{Byte, UnitType};
+
+use crate::{config::OutputFormat, format};
+use katana_client::Client;
+use katana_models::{HostInfoResponse, ToolInfo};
+
+/// Result of a single host check
+enum Check {
+    Pass(String),
+    Fail(String, &'static str),
+    /// Failed check for an optional feature
+    Skip(String),
+}
+
+pub async fn execute(client: &Client, output_format: &OutputFormat) -> Result<()> {
+    let host = client.get_host_info().await?;
+
+    if let OutputFormat::Json = output_format {
+        let json_value = serde_json::to_value(&host)?;
+        format::print_json(&json_value);
+        return Ok(());
+    }
+
+    let checks = host_checks(&host);
+    let mut failed = 0;
+
+    println!("Host checks:");
+    for (name, check) in &checks {
+        match check {
+            Check::Pass(detail) => println!("  ✓ {:<12} {}", name, detail),
+            Check::Fail(detail, hint) => {
+                failed += 1;
+                println!("  ✗ {:<12} {}", name, detail);
+                println!("    {:<12} → {}", "", hint);
+            }
+            Check::Skip(detail) => println!("  - {:<12} {}", name, detail),
+        }
+    }
+
+    println!();
+    println!("Resources:");
+    println!("  CPUs:        {}", host.cpu_count);
+    println!(
+        "  Memory:      {} total, {} available",
+        format_bytes(host.memory_total_bytes),
+        format_bytes(host.memory_available_bytes)
+    );
+
+    if failed > 0 {
+        println!();
+        bail!("{} host check(s) failed", failed);
+    }
+
+    Ok(())
+}
+
+fn host_checks(host: &HostInfoResponse) -> Vec<(&'static str, Check)> {
+    let kvm = if host.kvm.accessible {
+        Check::Pass("/dev/kvm is accessible".to_string())
+    } else if host.kvm.present {
+        Check::Fail(
+            format!(
+                "/dev/kvm is not accessible ({})",
+                host.kvm.error.as_deref().unwrap_or("unknown error")
+            ),
+            "add the daemon user to the kvm group: sudo usermod -aG kvm $USER",
+        )
+    } else {
+        Check::Fail(
+            "/dev/kvm does not exist".to_string(),
+            "enable virtualization in the BIOS and load kvm_intel or kvm_amd",
+        )
+    };
+
+    let qemu = match (&host.qemu.binary.version, host.qemu.q35_supported) {
+        (Some(version), true) => Check::Pass(format!("qemu-system-x86_64 {} (q35)", version)),
+        (Some(version), false) => Check::Fail(
+            format!("qemu-system-x86_64 {} does not support q35", version),
+            "install a QEMU build with the q35 machine type",
+        ),
+        (None, _) => Check::Fail(
+            "qemu-system-x86_64 not found".to_string(),
+            "install QEMU: sudo apt install qemu-system-x86",
+        ),
+    };
+
+    let nbd = if host.nbd.module_loaded {
+        Check::Pass(format!("module loaded, {} devices", host.nbd.devices))
+    } else {
+        Check::Fail(
+            "nbd module not loaded".to_string(),
+            "load it: sudo modprobe nbd max_part=8",
+        )
+    };
+
+    let sev = if host.sev.snp_supported {
+        Check::Pass("SEV-SNP supported".to_string())
+    } else if !host.sev.device {
+        Check::Skip("not supported, /dev/sev not found (needed for --tee)".to_string())
+    } else {
+        Check::Skip("not supported, kvm_amd sev_snp is disabled (needed for --tee)".to_string())
+    };
+
+    let vsock = if host.vhost_vsock {
+        Check::Pass("/dev/vhost-vsock is present".to_string())
+    } else {
+        Check::Skip("/dev/vhost-vsock not found (needed for the guest agent)".to_string())
+    };
+
+    vec![
+        ("KVM", kvm),
+        ("QEMU", qemu),
+        (
+            "qemu-img",
+            tool_check(&host.qemu_img, "qemu-img", "sudo apt install qemu-utils"),
+        ),
+        (
+            "qemu-nbd",
+            tool_check(&host.qemu_nbd, "qemu-nbd", "sudo apt install qemu-utils"),
+        ),
+        ("NBD", nbd),
+        ("SEV-SNP", sev),
+        ("vsock", vsock),
+    ]
+}
+
+fn tool_check(tool: &ToolInfo, name: &str, hint: &'static str) -> Check {
+    if tool.available {
+        Check::Pass(match &tool.version {
+            Some(version) => format!("{} {}", name, version),
+            None => name.to_string(),
+        })
+    } else {
+        Check::Fail(format!("{} not found", name), hint)
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    let byte = Byte::from_u64(bytes);
+    format!("{:.1}", byte.get_appropriate_unit(UnitType::Binary))
+}
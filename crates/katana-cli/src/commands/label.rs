@@ -0,0 +1,103 @@
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+
+use crate::{config::OutputFormat, format};
+use katana_client::Client;
+use katana_models::UpdateLabelsRequest;
+
+/// Parse a `key=value` pair, used by `create --label` and `--annotation`
+pub fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected key=value, got '{}'", s)),
+    }
+}
+
+/// Parse `key=value` to set a key or `key-` to remove it
+fn parse_change(s: &str) -> Result<(String, Option<String>)> {
+    if let Some((key, value)) = s.split_once('=') {
+        if !key.is_empty() {
+            return Ok((key.to_string(), Some(value.to_string())));
+        }
+    } else if let Some(key) = s.strip_suffix('-') {
+        if !key.is_empty() {
+            return Ok((key.to_string(), None));
+        }
+    }
+
+    bail!("Invalid change '{}': expected key=value or key-", s)
+}
+
+/// Set or remove labels (or annotations) on an instance
+pub async fn execute(
+    client: &Client,
+    name: String,
+    changes: Vec<String>,
+    annotations: bool,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let changes = changes
+        .iter()
+        .map(|change| parse_change(change))
+        .collect::<Result<BTreeMap<_, _>>>()?;
+
+    let request = if annotations {
+        UpdateLabelsRequest {
+            annotations: changes,
+            ..Default::default()
+        }
+    } else {
+        UpdateLabelsRequest {
+            labels: changes,
+            ..Default::default()
+        }
+    };
+
+    let response = client.update_labels(&name, request).await?;
+
+    match output_format {
+        OutputFormat::Json => {
+            let json_value = serde_json::to_value(&response)?;
+            format::print_json(&json_value);
+        }
+        OutputFormat::Table => {
+            format::print_instance_details(&response);
+            println!(
+                "\n✓ {} updated successfully!",
+                if annotations { "Annotations" } else { "Labels" }
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_change() {
+        assert_eq!(
+            parse_change("team=bridge").unwrap(),
+            ("team".to_string(), Some("bridge".to_string()))
+        );
+        assert_eq!(
+            parse_change("note=a=b").unwrap(),
+            ("note".to_string(), Some("a=b".to_string()))
+        );
+        assert_eq!(parse_change("team-").unwrap(), ("team".to_string(), None));
+        assert!(parse_change("team").is_err());
+        assert!(parse_change("-").is_err());
+        assert!(parse_change("=x").is_err());
+    }
+
+    #[test]
+    fn test_parse_key_value() {
+        assert_eq!(
+            parse_key_value("env=ci").unwrap(),
+            ("env".to_string(), "ci".to_string())
+        );
+        assert!(parse_key_value("env").is_err());
+    }
+}
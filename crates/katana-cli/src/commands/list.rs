@@ -3,8 +3,15 @@ use anyhow::Result;
 use crate::{config::OutputFormat, format};
 use katana_client::Client;
 
-pub async fn execute(client: &Client, output_format: &OutputFormat) -> Result<()> {
-    let response = client.list_instances().await?;
+pub async fn execute(
+    client: &Client,
+    selector: Option<String>,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let response = match selector {
+        Some(selector) => client.list_instances_by_selector(&selector).await?,
+        None => client.list_instances().await?,
+    };
 
     match output_format {
         OutputFormat::Json => {
@@ -1,28 +1,79 @@
 use anyhow::Result;
+use clap::Args;
 use serde_json::Value;
 
-use katana_client::Client;
+use crate::{config::OutputFormat, format};
+use katana_client::{Client, LogsQuery};
 
-pub async fn execute(
-    client: &Client,
-    name: String,
-    tail: Option<usize>,
-    follow: bool,
-) -> Result<()> {
-    if follow {
+#[derive(Args, Debug)]
+pub struct LogsArgs {
+    /// Instance name
+    pub name: String,
+    /// Number of lines to show
+    #[arg(long, short = 'n')]
+    pub tail: Option<usize>,
+    /// Stream logs in real-time (like tail -f)
+    #[arg(long, short = 'f')]
+    pub follow: bool,
+    /// Log to show: kernel (serial console), katana or all
+    #[arg(long, value_parser = ["kernel", "katana", "all"])]
+    pub source: Option<String>,
+    /// Only lines since this time (RFC 3339, or a duration such as "10m" ago)
+    #[arg(long)]
+    pub since: Option<String>,
+    /// Only lines until this time (RFC 3339, or a duration ago)
+    #[arg(long)]
+    pub until: Option<String>,
+    /// Only lines matching this regex
+    #[arg(long)]
+    pub grep: Option<String>,
+    /// Only lines at this level or more severe (trace, debug, info, warn, error)
+    #[arg(long)]
+    pub level: Option<String>,
+    /// Only lines before this byte offset, to page back through older logs
+    #[arg(long, conflicts_with = "follow")]
+    pub before: Option<u64>,
+}
+
+impl LogsArgs {
+    fn query(&self, structured: bool) -> LogsQuery {
+        LogsQuery {
+            tail: self.tail,
+            structured,
+            source: self.source.clone(),
+            since: self.since.clone(),
+            until: self.until.clone(),
+            grep: self.grep.clone(),
+            level: self.level.clone(),
+            before: self.before,
+            offset: None,
+        }
+    }
+}
+
+pub async fn execute(client: &Client, args: LogsArgs, output_format: &OutputFormat) -> Result<()> {
+    let structured = matches!(output_format, OutputFormat::Json);
+    let query = args.query(structured);
+    let name = args.name;
+
+    if args.follow {
         // Stream mode
-        println!("Streaming logs for '{}' (Ctrl+C to exit)...\n", name);
+        if !structured {
+            println!("Streaming logs for '{}' (Ctrl+C to exit)...\n", name);
+        }
 
         client
-            .stream_logs(&name, tail, |event_type, data| {
+            .stream_logs(&name, &query, |event_type, data| {
                 match event_type.as_str() {
                     "init" => {
                         // Initial connection message (optional to display)
                     }
                     "log" => {
-                        // Parse JSON and print log line
+                        // Parse JSON and print the record (one per line) or the log line
                         if let Ok(json) = serde_json::from_str::<Value>(&data) {
-                            if let Some(line) = json["line"].as_str() {
+                            if structured {
+                                println!("{}", json["record"]);
+                            } else if let Some(line) = json["line"].as_str() {
                                 println!("{}", line);
                             }
                         }
@@ -34,32 +85,71 @@ pub async fn execute(
                 }
             })
             .await?;
+    } else if structured {
+        let response = client.get_logs(&name, &query).await?;
+        let json_value = serde_json::to_value(&response)?;
+        format::print_json(&json_value);
     } else {
-        // Non-streaming mode (existing behavior)
         // Fetch logs from daemon
-        let response = client.get_logs(&name, tail).await?;
+        let response = client.get_logs(&name, &query).await?;
 
         // Display logs
         if response.lines.is_empty() {
-            println!("No logs available for instance '{}'", name);
-            println!("(Total lines in log file: {})", response.total_lines);
+            println!("No matching logs for instance '{}'", name);
+            println!("(Lines examined: {})", response.lines_scanned);
         } else {
             // Print logs
             for line in &response.lines {
                 println!("{}", line);
             }
+        }
 
-            // Print summary if we didn't show all lines
-            if response.total_lines > response.lines.len() {
-                println!();
-                println!(
-                    "Showing last {} of {} total lines",
-                    response.lines.len(),
-                    response.total_lines
-                );
+        // Tell the user how to page back if there is more
+        if response.has_more {
+            println!();
+            match response.start_offset {
+                Some(offset) => println!(
+                    "Earlier logs available, add --before {} to see them",
+                    offset
+                ),
+                None => println!(
+                    "Earlier logs available, add --source kernel or --source katana to page back"
+                ),
             }
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        args: LogsArgs,
+    }
+
+    #[test]
+    fn test_filter_flags() {
+        let cli = TestCli::try_parse_from([
+            "logs", "node", "--since", "10m", "--grep", "ERROR", "--level", "warn", "--before",
+            "4096", "--source", "katana",
+        ])
+        .unwrap();
+        let query = cli.args.query(false);
+
+        assert_eq!(query.since.as_deref(), Some("10m"));
+        assert_eq!(query.grep.as_deref(), Some("ERROR"));
+        assert_eq!(query.level.as_deref(), Some("warn"));
+        assert_eq!(query.before, Some(4096));
+        assert_eq!(query.source.as_deref(), Some("katana"));
+        assert!(!query.structured);
+
+        assert!(TestCli::try_parse_from(["logs", "node", "-f", "--before", "1"]).is_err());
+        assert!(TestCli::try_parse_from(["logs", "node", "--source", "qemu"]).is_err());
+    }
+}
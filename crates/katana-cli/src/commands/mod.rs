@@ -1,8 +1,125 @@
+pub mod accounts;
+pub mod apply;
 pub mod create;
-pub mod start;
-pub mod stop;
 pub mod delete;
+pub mod diff;
+pub mod doctor;
+pub mod label;
 pub mod list;
-pub mod show;
 pub mod logs;
+pub mod pause;
+pub mod reset;
+pub mod resume;
+pub mod secret;
+pub mod show;
+pub mod start;
 pub mod stats;
+pub mod stop;
+pub mod suspend;
+pub mod template;
+pub mod top;
+pub mod wake;
+
+use anyhow::{bail, Result};
+use clap::Args;
+use serde::Serialize;
+
+use crate::{config::OutputFormat, format};
+use katana_client::Client;
+use katana_models::InstanceResponse;
+
+/// A single instance by name, or every instance matching a label selector
+#[derive(Args, Debug)]
+pub struct Target {
+    /// Instance name
+    #[arg(required_unless_present = "selector")]
+    pub name: Option<String>,
+    /// Label selector, e.g. "team=bridge,env=ci"
+    #[arg(long, short = 'l', conflicts_with = "name")]
+    pub selector: Option<String>,
+}
+
+/// Outcome of a bulk operation on one instance
+#[derive(Debug, Serialize)]
+pub struct BulkResult {
+    pub name: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BulkResult {
+    pub fn new<T>(name: String, result: Result<T>) -> Self {
+        match result {
+            Ok(_) => Self {
+                name,
+                ok: true,
+                error: None,
+            },
+            Err(e) => Self {
+                name,
+                ok: false,
+                error: Some(format!("{:#}", e)),
+            },
+        }
+    }
+}
+
+/// Fetch the instances matching a label selector, for a bulk operation.
+///
+/// An empty selector matches every instance, which `list` and `top` want but
+/// a stray `-l ""` on `stop` or `delete` does not, so it is rejected here.
+pub async fn select_instances(client: &Client, selector: &str) -> Result<Vec<InstanceResponse>> {
+    check_selector(selector)?;
+    Ok(client.list_instances_by_selector(selector).await?.instances)
+}
+
+/// Reject a selector without requirements
+pub fn check_selector(selector: &str) -> Result<()> {
+    if selector.split(',').all(|term| term.trim().is_empty()) {
+        bail!("Empty label selector; it would match every instance");
+    }
+    Ok(())
+}
+
+/// Print the outcome of a bulk operation, failing if any instance failed
+pub fn report_bulk(results: &[BulkResult], done: &str, output_format: &OutputFormat) -> Result<()> {
+    match output_format {
+        OutputFormat::Json => {
+            let json_value = serde_json::to_value(results)?;
+            format::print_json(&json_value);
+        }
+        OutputFormat::Table => {
+            if results.is_empty() {
+                println!("No matching instances to process.");
+            }
+            for result in results {
+                match &result.error {
+                    None => println!("✓ Instance '{}' {}", result.name, done),
+                    Some(error) => println!("✗ Instance '{}': {}", result.name, error),
+                }
+            }
+        }
+    }
+
+    let failed = results.iter().filter(|r| !r.ok).count();
+    if failed > 0 {
+        bail!("{} of {} instance(s) failed", failed, results.len());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_selector() {
+        assert!(check_selector("team=bridge").is_ok());
+        assert!(check_selector("!tmp").is_ok());
+        assert!(check_selector("").is_err());
+        assert!(check_selector(" ").is_err());
+        assert!(check_selector(" , ,").is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::{bail, Context, Result};
+use clap::{Args, Subcommand};
+use std::io::Read;
+use std::path::PathBuf;
+
+use crate::{config::OutputFormat, format};
+use katana_client::Client;
+use katana_models::SetSecretRequest;
+
+#[derive(Subcommand, Debug)]
+pub enum SecretCommand {
+    /// Create or replace a secret, read from stdin unless a source is given
+    Set(SecretSetArgs),
+    /// List the names of an instance's secrets
+    List {
+        /// Instance name
+        name: String,
+    },
+    /// Delete a secret
+    Delete {
+        /// Instance name
+        name: String,
+        /// Secret name
+        secret: String,
+    },
+}
+
+/// The value is never taken as an argument, where `ps` and shell history
+/// would see it
+#[derive(Args, Debug)]
+pub struct SecretSetArgs {
+    /// Instance name
+    pub name: String,
+    /// Secret name, exported to Katana as an environment variable
+    pub secret: String,
+    /// Read the value from a file
+    #[arg(long, conflicts_with = "from_env")]
+    pub from_file: Option<PathBuf>,
+    /// Read the value from an environment variable of this shell
+    #[arg(long)]
+    pub from_env: Option<String>,
+}
+
+impl SecretSetArgs {
+    /// Read the value from its source, dropping a trailing newline
+    fn read_value(&self) -> Result<String> {
+        let value = if let Some(path) = &self.from_file {
+            std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?
+        } else if let Some(var) = &self.from_env {
+            std::env::var(var)
+                .with_context(|| format!("Environment variable {} is not set", var))?
+        } else {
+            let mut value = String::new();
+            std::io::stdin()
+                .read_to_string(&mut value)
+                .context("Failed to read the secret from stdin")?;
+            value
+        };
+
+        let value = value.trim_end_matches(['\n', '\r']).to_string();
+        if value.is_empty() {
+            bail!("Secret '{}' is empty", self.secret);
+        }
+
+        Ok(value)
+    }
+}
+
+pub async fn execute(
+    client: &Client,
+    command: SecretCommand,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    match command {
+        SecretCommand::Set(args) => {
+            let request = SetSecretRequest {
+                value: args.read_value()?,
+            };
+            let response = client.set_secret(&args.name, &args.secret, request).await?;
+
+            match output_format {
+                OutputFormat::Json => {
+                    let json_value = serde_json::to_value(&response)?;
+                    format::print_json(&json_value);
+                }
+                OutputFormat::Table => {
+                    println!(
+                        "✓ Secret '{}' set on instance '{}'. It applies from the next start.",
+                        response.name, args.name
+                    );
+                }
+            }
+        }
+        SecretCommand::List { name } => {
+            let response = client.list_secrets(&name).await?;
+
+            match output_format {
+                OutputFormat::Json => {
+                    let json_value = serde_json::to_value(&response)?;
+                    format::print_json(&json_value);
+                }
+                OutputFormat::Table => {
+                    if response.secrets.is_empty() {
+                        println!("Instance '{}' has no secrets.", name);
+                    } else {
+                        println!("{:<32} {:<26}", "NAME", "UPDATED");
+                        for secret in &response.secrets {
+                            println!("{:<32} {:<26}", secret.name, secret.updated_at);
+                        }
+                    }
+                }
+            }
+        }
+        SecretCommand::Delete { name, secret } => {
+            client.delete_secret(&name, &secret).await?;
+            println!("✓ Secret '{}' deleted from instance '{}'", secret, name);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[command(subcommand)]
+        command: SecretCommand,
+    }
+
+    fn set_args(args: &[&str]) -> SecretSetArgs {
+        let cli =
+            TestCli::try_parse_from([&["secret", "set", "dev", "FORK_API_KEY"], args].concat())
+                .unwrap();
+        let SecretCommand::Set(args) = cli.command else {
+            panic!("expected set");
+        };
+        args
+    }
+
+    #[test]
+    fn test_read_value() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key");
+        std::fs::write(&path, "alchemy-key\n").unwrap();
+
+        let args = set_args(&["--from-file", path.to_str().unwrap()]);
+        assert_eq!(args.read_value().unwrap(), "alchemy-key");
+
+        std::fs::write(&path, "\n").unwrap();
+        assert!(args.read_value().is_err());
+
+        // The value itself is not an accepted argument
+        assert!(TestCli::try_parse_from(["secret", "set", "dev", "KEY", "value"]).is_err());
+        assert!(TestCli::try_parse_from([
+            "secret",
+            "set",
+            "dev",
+            "KEY",
+            "--from-file",
+            "f",
+            "--from-env",
+            "V"
+        ])
+        .is_err());
+    }
+}
@@ -1,6 +1,10 @@
 use anyhow::Result;
 
-use crate::{config::OutputFormat, format};
+use crate::{
+    commands::{report_bulk, select_instances, BulkResult},
+    config::OutputFormat,
+    format,
+};
 use katana_client::Client;
 
 pub async fn execute(client: &Client, name: String, output_format: &OutputFormat) -> Result<()> {
@@ -19,3 +23,23 @@ pub async fn execute(client: &Client, name: String, output_format: &OutputFormat
 
     Ok(())
 }
+
+/// Start every stopped instance matching a label selector
+pub async fn execute_selected(
+    client: &Client,
+    selector: &str,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let mut results = Vec::new();
+
+    for instance in select_instances(client, selector).await? {
+        if !matches!(instance.status.as_str(), "Created" | "Stopped" | "Failed") {
+            continue;
+        }
+
+        let result = client.start_instance(&instance.name).await;
+        results.push(BulkResult::new(instance.name, result));
+    }
+
+    report_bulk(&results, "started", output_format)
+}
@@ -1,4 +1,6 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use katana_models::ChainInfo;
 
 use crate::{config::OutputFormat, format};
 use katana_client::Client;
@@ -46,12 +48,88 @@ pub async fn execute(client: &Client, name: String, output_format: &OutputFormat
                 println!("    CPU {}:     Thread ID {}", cpu.cpu_index, cpu.thread_id);
             }
             println!("  Memory:      {} MB", response.resources.memory_mb);
+            if let Some(balloon) = response.resources.balloon_bytes {
+                println!("  Balloon:     {}", format::format_bytes(Some(balloon)));
+            }
+            for device in &response.resources.block_devices {
+                println!(
+                    "  Disk {}:  read {} ({} ops), written {} ({} ops)",
+                    device.device,
+                    format::format_bytes(Some(device.read_bytes)),
+                    device.read_operations,
+                    format::format_bytes(Some(device.write_bytes)),
+                    device.write_operations
+                );
+            }
+            if let Some(network_io) = &response.resources.network_io {
+                println!(
+                    "  Network:     received {}, sent {}",
+                    format::format_bytes(Some(network_io.rx_bytes)),
+                    format::format_bytes(Some(network_io.tx_bytes))
+                );
+            }
             println!();
             println!("Network:");
             println!("  RPC:         {}", response.network.rpc_url);
             println!("  Health:      {}", response.network.health_url);
             println!();
+            println!("Chain:");
+            match (&response.chain, &response.chain_error) {
+                (Some(chain), _) => print_chain(chain),
+                (None, Some(error)) => println!("  Unreachable: {}", error),
+                (None, None) => println!("  Not reported by the daemon"),
+            }
+            println!();
+        }
+    }
+
+    Ok(())
+}
+
+fn print_chain(chain: &ChainInfo) {
+    println!("  Block:       {}", chain.block_number);
+    if let Some(timestamp) = &chain.latest_block_timestamp {
+        match DateTime::parse_from_rfc3339(timestamp) {
+            Ok(at) => {
+                let age = (Utc::now() - at.with_timezone(&Utc)).num_seconds().max(0);
+                println!("  Latest:      {} ({}s ago)", timestamp, age);
+            }
+            Err(_) => println!("  Latest:      {}", timestamp),
+        }
+    }
+    match (&chain.chain_name, &chain.chain_id) {
+        (Some(name), Some(id)) => println!("  Chain ID:    {} ({})", name, id),
+        (None, Some(id)) => println!("  Chain ID:    {}", id),
+        _ => {}
+    }
+    if let Some(pending) = chain.pending_transactions {
+        println!("  Pending txs: {}", pending);
+    }
+    if !chain.accounts.is_empty() {
+        println!("  Accounts:    {}", chain.accounts.len());
+        for account in &chain.accounts {
+            match account.balance.as_deref().and_then(format::format_balance) {
+                Some(balance) => println!("    {}  {}", account.address, balance),
+                None => println!("    {}", account.address),
+            }
+        }
+    }
+}
+
+pub async fn execute_history(
+    client: &Client,
+    name: String,
+    since: Option<String>,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let response = client.get_stats_history(&name, since.as_deref()).await?;
+
+    match output_format {
+        OutputFormat::Json => {
+            let json_value = serde_json::to_value(&response)?;
+            format::print_json(&json_value);
         }
+        OutputFormat::Table => format::print_stats_history(&response),
     }
 
     Ok(())
@@ -1,10 +1,19 @@
 use anyhow::Result;
 
-use crate::{config::OutputFormat, format};
+use crate::{
+    commands::{report_bulk, select_instances, BulkResult},
+    config::OutputFormat,
+    format,
+};
 use katana_client::Client;
 
-pub async fn execute(client: &Client, name: String, output_format: &OutputFormat) -> Result<()> {
-    let response = client.stop_instance(&name).await?;
+pub async fn execute(
+    client: &Client,
+    name: String,
+    grace_period: Option<u64>,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let response = client.stop_instance(&name, grace_period).await?;
 
     match output_format {
         OutputFormat::Json => {
@@ -19,3 +28,24 @@ pub async fn execute(client: &Client, name: String, output_format: &OutputFormat
 
     Ok(())
 }
+
+/// Stop every running instance matching a label selector
+pub async fn execute_selected(
+    client: &Client,
+    selector: &str,
+    grace_period: Option<u64>,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    let mut results = Vec::new();
+
+    for instance in select_instances(client, selector).await? {
+        if matches!(instance.status.as_str(), "Created" | "Stopped" | "Failed") {
+            continue;
+        }
+
+        let result = client.stop_instance(&instance.name, grace_period).await;
+        results.push(BulkResult::new(instance.name, result));
+    }
+
+    report_bulk(&results, "stopped", output_format)
+}
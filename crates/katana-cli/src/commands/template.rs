@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
+
+use crate::{commands::create::SettingsArgs, config::OutputFormat, format};
+use katana_client::Client;
+use katana_models::{CreateTemplateRequest, SevSnpRequest};
+
+#[derive(Subcommand, Debug)]
+pub enum TemplateCommand {
+    /// Create a template
+    Create(Box<TemplateCreateArgs>),
+    /// List all templates
+    List,
+    /// Show template details
+    Show {
+        /// Template name
+        name: String,
+    },
+    /// Delete a template
+    Delete {
+        /// Template name
+        name: String,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct TemplateCreateArgs {
+    /// Template name
+    pub name: String,
+    /// Short description
+    #[arg(long)]
+    pub description: Option<String>,
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+    /// JSON file with SEV-SNP launch settings (policy, host_data, id_block,
+    /// id_auth, kernel_hashes, ...) for TEE instances
+    #[arg(long, value_name = "FILE")]
+    pub sev_snp_config: Option<PathBuf>,
+}
+
+impl TemplateCreateArgs {
+    fn into_request(self) -> Result<CreateTemplateRequest> {
+        let sev_snp = self
+            .sev_snp_config
+            .as_deref()
+            .map(|path| -> Result<SevSnpRequest> {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                serde_json::from_str(&contents)
+                    .with_context(|| format!("Invalid SEV-SNP settings in {}", path.display()))
+            })
+            .transpose()?;
+        let settings = self.settings;
+
+        Ok(CreateTemplateRequest {
+            name: self.name,
+            description: self.description,
+            vcpus: settings.vcpus,
+            memory: settings.memory,
+            storage: settings.storage,
+            dev: settings.dev,
+            tee: settings.tee,
+            vcpu_type: settings.vcpu_type,
+            sev_snp,
+            accelerator: settings.accelerator,
+            chain_id: settings.chain_id,
+            block_time: settings.block_time,
+            accounts: settings.accounts,
+            disable_fee: settings.disable_fee,
+            extra_args: settings.katana_args,
+            ttl: settings.ttl,
+            idle_timeout: settings.idle_timeout,
+        })
+    }
+}
+
+pub async fn execute(
+    client: &Client,
+    command: TemplateCommand,
+    output_format: &OutputFormat,
+) -> Result<()> {
+    match command {
+        TemplateCommand::Create(args) => {
+            let response = client.create_template(args.into_request()?).await?;
+
+            match output_format {
+                OutputFormat::Json => {
+                    let json_value = serde_json::to_value(&response)?;
+                    format::print_json(&json_value);
+                }
+                OutputFormat::Table => {
+                    format::print_template_details(&response);
+                    println!("\n✓ Template created successfully!");
+                }
+            }
+        }
+        TemplateCommand::List => {
+            let response = client.list_templates().await?;
+
+            match output_format {
+                OutputFormat::Json => {
+                    let json_value = serde_json::to_value(&response)?;
+                    format::print_json(&json_value);
+                }
+                OutputFormat::Table => format::print_template_list(&response.templates),
+            }
+        }
+        TemplateCommand::Show { name } => {
+            let response = client.get_template(&name).await?;
+
+            match output_format {
+                OutputFormat::Json => {
+                    let json_value = serde_json::to_value(&response)?;
+                    format::print_json(&json_value);
+                }
+                OutputFormat::Table => format::print_template_details(&response),
+            }
+        }
+        TemplateCommand::Delete { name } => {
+            client.delete_template(&name).await?;
+            println!("✓ Template '{}' deleted successfully!", name);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[command(subcommand)]
+        command: TemplateCommand,
+    }
+
+    #[test]
+    fn test_create_request() {
+        let cli = TestCli::try_parse_from([
+            "template",
+            "create",
+            "tee-prod",
+            "--description",
+            "SEV-SNP production",
+            "--tee",
+            "--memory",
+            "8G",
+            "--accelerator",
+            "kvm",
+        ])
+        .unwrap();
+
+        let TemplateCommand::Create(args) = cli.command else {
+            panic!("expected create");
+        };
+        let request = args.into_request().unwrap();
+
+        assert_eq!(request.name, "tee-prod");
+        assert_eq!(request.tee, Some(true));
+        assert_eq!(request.memory.as_deref(), Some("8G"));
+        assert_eq!(request.accelerator.as_deref(), Some("kvm"));
+        assert_eq!(request.vcpus, None);
+        assert_eq!(request.dev, None);
+        assert!(request.sev_snp.is_none());
+    }
+
+    #[test]
+    fn test_create_request_sev_snp() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sev-snp.json");
+        std::fs::write(&path, r#"{"policy": 196608, "kernel_hashes": true}"#).unwrap();
+
+        let cli = TestCli::try_parse_from([
+            "template",
+            "create",
+            "tee-prod",
+            "--tee",
+            "--sev-snp-config",
+            path.to_str().unwrap(),
+        ])
+        .unwrap();
+        let TemplateCommand::Create(args) = cli.command else {
+            panic!("expected create");
+        };
+
+        let sev_snp = args.into_request().unwrap().sev_snp.unwrap();
+        assert_eq!(sev_snp.policy, Some(0x30000));
+        assert_eq!(sev_snp.kernel_hashes, Some(true));
+    }
+}
@@ -0,0 +1,252 @@
+use anyhow::Result;
+use chrono::{DateTime, FixedOffset};
+use clap::{Args, ValueEnum};
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
+use crossterm::{cursor, execute, terminal};
+use katana_models::{InstanceUsage, StatsSnapshot};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::stdout;
+
+use crate::{config::OutputFormat, format};
+use katana_client::Client;
+
+#[derive(Args, Debug)]
+pub struct TopArgs {
+    /// Label selector, e.g. "team=bridge,env=ci"
+    #[arg(long, short = 'l')]
+    pub selector: Option<String>,
+    /// Time between refreshes, e.g. "5s" (defaults to the daemon's sampling interval)
+    #[arg(long)]
+    pub interval: Option<String>,
+    /// Column to sort by, busiest first
+    #[arg(long, value_enum, default_value_t = SortKey::Cpu)]
+    pub sort: SortKey,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Cpu,
+    Memory,
+    Disk,
+    Net,
+    Name,
+}
+
+/// Network throughput of each instance between its last two samples
+#[derive(Debug, Default)]
+struct NetRates {
+    /// Time and rx/tx totals of the last sample seen per instance
+    last: HashMap<String, (DateTime<FixedOffset>, u64, u64)>,
+    /// Received and sent bytes per second
+    rates: HashMap<String, (f64, f64)>,
+}
+
+impl NetRates {
+    fn update(&mut self, usage: &InstanceUsage) {
+        let sample = usage.sample.as_ref();
+        let totals = sample.and_then(|s| Some((s.net_rx_bytes?, s.net_tx_bytes?)));
+        let at = sample.and_then(|s| DateTime::parse_from_rfc3339(&s.timestamp).ok());
+
+        let (Some((rx, tx)), Some(at)) = (totals, at) else {
+            self.last.remove(&usage.name);
+            self.rates.remove(&usage.name);
+            return;
+        };
+
+        if let Some(&(last_at, last_rx, last_tx)) = self.last.get(&usage.name) {
+            let secs = (at - last_at).num_milliseconds() as f64 / 1000.0;
+            if secs <= 0.0 {
+                // Same sample as last refresh
+                return;
+            }
+            // Counters start over when the guest reboots
+            if rx >= last_rx && tx >= last_tx {
+                let rates = ((rx - last_rx) as f64 / secs, (tx - last_tx) as f64 / secs);
+                self.rates.insert(usage.name.clone(), rates);
+            }
+        }
+        self.last.insert(usage.name.clone(), (at, rx, tx));
+    }
+
+    fn total(&self, name: &str) -> Option<f64> {
+        self.rates.get(name).map(|(rx, tx)| rx + tx)
+    }
+}
+
+pub async fn execute(client: &Client, args: TopArgs, output_format: &OutputFormat) -> Result<()> {
+    let interactive = matches!(output_format, OutputFormat::Table);
+    let mut rates = NetRates::default();
+
+    if interactive {
+        execute!(stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+    }
+
+    let stream = client.stream_stats(
+        args.interval.as_deref(),
+        args.selector.as_deref(),
+        |event_type, data| match event_type.as_str() {
+            "stats" if !interactive => {
+                // One snapshot per line
+                println!("{}", data);
+            }
+            "stats" => {
+                if let Ok(mut snapshot) = serde_json::from_str::<StatsSnapshot>(&data) {
+                    let _ = render(&mut snapshot, &mut rates, args.sort);
+                }
+            }
+            "error" => eprintln!("Error: {}", data),
+            _ => {}
+        },
+    );
+
+    let result = tokio::select! {
+        result = stream => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    };
+
+    if interactive {
+        execute!(stdout(), cursor::Show, terminal::LeaveAlternateScreen)?;
+    }
+
+    result
+}
+
+fn render(snapshot: &mut StatsSnapshot, rates: &mut NetRates, sort: SortKey) -> Result<()> {
+    for usage in &snapshot.instances {
+        rates.update(usage);
+    }
+    sort_instances(&mut snapshot.instances, rates, sort);
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            "NAME", "STATUS", "HEALTH", "CPU", "MEMORY", "DISK", "NET RX/s", "NET TX/s",
+        ]);
+
+    for usage in &snapshot.instances {
+        let sample = usage.sample.as_ref();
+        let rate = rates.rates.get(&usage.name);
+
+        table.add_row(vec![
+            usage.name.clone(),
+            usage.status.clone(),
+            usage.health.clone(),
+            sample
+                .and_then(|s| s.cpu_percent)
+                .map(|cpu| format!("{:.1}%", cpu))
+                .unwrap_or_else(|| "-".to_string()),
+            format!(
+                "{} / {} MB",
+                format::format_bytes(sample.and_then(|s| s.rss_bytes)),
+                usage.memory_mb
+            ),
+            format::format_bytes(sample.and_then(|s| s.disk_bytes)),
+            format::format_bytes(rate.map(|(rx, _)| *rx as u64)),
+            format::format_bytes(rate.map(|(_, tx)| *tx as u64)),
+        ]);
+    }
+
+    execute!(
+        stdout(),
+        cursor::MoveTo(0, 0),
+        terminal::Clear(terminal::ClearType::All)
+    )?;
+    println!(
+        "katana top - {} instances - {} (Ctrl+C to quit)",
+        snapshot.instances.len(),
+        snapshot.timestamp
+    );
+    println!("{table}");
+
+    Ok(())
+}
+
+/// Sort busiest first for `sort`, instances without data last, then by name
+fn sort_instances(instances: &mut [InstanceUsage], rates: &NetRates, sort: SortKey) {
+    let key = |usage: &InstanceUsage| -> Option<f64> {
+        let sample = usage.sample.as_ref();
+        match sort {
+            SortKey::Cpu => sample?.cpu_percent,
+            SortKey::Memory => sample?.rss_bytes.map(|bytes| bytes as f64),
+            SortKey::Disk => sample?.disk_bytes.map(|bytes| bytes as f64),
+            SortKey::Net => rates.total(&usage.name),
+            SortKey::Name => None,
+        }
+    };
+
+    instances.sort_by(|a, b| {
+        let by_key = match (key(a), key(b)) {
+            (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        by_key.then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use katana_models::StatsSampleInfo;
+
+    fn usage(name: &str, second: u32, cpu: Option<f64>, net: Option<(u64, u64)>) -> InstanceUsage {
+        InstanceUsage {
+            name: name.to_string(),
+            status: "Running".to_string(),
+            vcpus: 2,
+            memory_mb: 2048,
+            rpc_port: 5050,
+            health: "healthy".to_string(),
+            sample: Some(StatsSampleInfo {
+                timestamp: format!("2025-01-10T12:00:{:02}+00:00", second),
+                cpu_percent: cpu,
+                rss_bytes: None,
+                disk_bytes: None,
+                net_rx_bytes: net.map(|(rx, _)| rx),
+                net_tx_bytes: net.map(|(_, tx)| tx),
+                block_read_bytes: None,
+                block_write_bytes: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_net_rates() {
+        let mut rates = NetRates::default();
+
+        rates.update(&usage("a", 0, None, Some((1000, 100))));
+        assert_eq!(rates.total("a"), None);
+
+        rates.update(&usage("a", 10, None, Some((11000, 600))));
+        assert_eq!(rates.rates["a"], (1000.0, 50.0));
+
+        // A repeated sample keeps the last rate
+        rates.update(&usage("a", 10, None, Some((11000, 600))));
+        assert_eq!(rates.rates["a"], (1000.0, 50.0));
+
+        // Counters reset by a reboot don't produce a rate
+        rates.update(&usage("a", 20, None, Some((500, 50))));
+        assert_eq!(rates.rates["a"], (1000.0, 50.0));
+    }
+
+    #[test]
+    fn test_sort_by_cpu() {
+        let mut instances = vec![
+            usage("idle", 0, Some(1.0), None),
+            usage("unsampled", 0, None, None),
+            usage("hot", 0, Some(180.0), None),
+            usage("busy", 0, Some(40.0), None),
+        ];
+        sort_instances(&mut instances, &NetRates::default(), SortKey::Cpu);
+
+        let names: Vec<_> = instances.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, vec!["hot", "busy", "idle", "unsampled"]);
+
+        sort_instances(&mut instances, &NetRates::default(), SortKey::Name);
+        assert_eq!(instances[0].name, "busy");
+    }
+}
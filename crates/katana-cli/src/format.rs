@@ -1,7 +1,8 @@
 use byte_unit::{Byte, UnitType};
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
-use katana_models::InstanceResponse;
+use katana_models::{InstanceResponse, SevSnpRequest, StatsHistoryResponse, TemplateResponse};
 use serde_json::Value;
+use std::collections::BTreeMap;
 
 pub fn print_json(value: &Value) {
     println!("{}", serde_json::to_string_pretty(value).unwrap());
@@ -14,6 +15,27 @@ fn format_storage(bytes: u64) -> String {
     adjusted.to_string()
 }
 
+/// Format optional byte counts, "-" when unknown
+pub fn format_bytes(bytes: Option<u64>) -> String {
+    bytes.map(format_storage).unwrap_or_else(|| "-".to_string())
+}
+
+/// Hex felt balance in whole fee tokens (18 decimals)
+pub fn format_balance(balance: &str) -> Option<String> {
+    let wei = u128::from_str_radix(balance.strip_prefix("0x").unwrap_or(balance), 16).ok()?;
+    let unit = 10u128.pow(18);
+    Some(format!("{}.{:04}", wei / unit, wei % unit / 10u128.pow(14)))
+}
+
+/// Format labels as "key=value,key=value"
+fn format_labels(labels: &BTreeMap<String, String>) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 pub fn print_instance_list(instances: &[InstanceResponse]) {
     if instances.is_empty() {
         println!("No instances found.");
@@ -24,7 +46,9 @@ pub fn print_instance_list(instances: &[InstanceResponse]) {
     table
         .load_preset(UTF8_FULL)
         .apply_modifier(UTF8_ROUND_CORNERS)
-        .set_header(vec!["NAME", "STATUS", "VCPUS", "MEMORY", "RPC PORT"]);
+        .set_header(vec![
+            "NAME", "STATUS", "VCPUS", "MEMORY", "RPC PORT", "LABELS",
+        ]);
 
     for instance in instances {
         table.add_row(vec![
@@ -33,6 +57,7 @@ pub fn print_instance_list(instances: &[InstanceResponse]) {
             instance.config.vcpus.to_string(),
             format!("{} MB", instance.config.memory_mb),
             instance.config.rpc_port.to_string(),
+            format_labels(&instance.labels),
         ]);
     }
 
@@ -49,6 +74,9 @@ pub fn print_instance_details(instance: &InstanceResponse) {
     println!("  Memory:     {} MB", instance.config.memory_mb);
     println!("  Storage:    {}", storage_display);
     println!("  RPC Port:   {}", instance.config.rpc_port);
+    if let Some(cid) = instance.config.vsock_cid {
+        println!("  vsock CID:  {}", cid);
+    }
     println!(
         "  TEE Mode:   {}",
         if instance.config.tee_mode {
@@ -57,6 +85,66 @@ pub fn print_instance_details(instance: &InstanceResponse) {
             "disabled"
         }
     );
+    match &instance.accelerator {
+        Some(active) if *active != instance.config.accelerator => println!(
+            "  Accelerator: {} (configured: {})",
+            active, instance.config.accelerator
+        ),
+        Some(active) => println!("  Accelerator: {}", active),
+        None => println!("  Accelerator: {}", instance.config.accelerator),
+    }
+    if !instance.labels.is_empty() {
+        println!("  Labels:     {}", format_labels(&instance.labels));
+    }
+    if !instance.annotations.is_empty() {
+        println!("  Annotations:");
+        for (key, value) in &instance.annotations {
+            println!("    {}: {}", key, value);
+        }
+    }
+    if let Some(provider) = &instance.config.fork_provider {
+        match instance.config.fork_block {
+            Some(block) => println!("  Fork:       {} at block {}", provider, block),
+            None => println!("  Fork:       {}", provider),
+        }
+    }
+    if let Some(messaging) = &instance.config.messaging {
+        let l1 = match (&messaging.l1_instance, &messaging.rpc_url) {
+            (Some(instance), _) => format!("instance '{}'", instance),
+            (None, Some(url)) => url.clone(),
+            (None, None) => "-".to_string(),
+        };
+        println!(
+            "  Messaging:  {} via {}, contract {}",
+            messaging.chain.as_deref().unwrap_or("ethereum"),
+            l1,
+            messaging.contract_address
+        );
+    }
+    if let Some(chain_spec) = &instance.config.chain_spec {
+        println!("  Chain Spec: {} (config drive)", chain_spec);
+    }
+    if !instance.config.env.is_empty() {
+        println!("  Env:        {}", format_labels(&instance.config.env));
+    }
+    if instance.config.config_drive_args {
+        println!("  Katana Args: config drive only");
+    }
+    if instance.config.tls {
+        println!("  TLS:        certificate on config drive");
+    }
+    if let Some(delivery) = &instance.secrets_delivery {
+        match &delivery.error {
+            Some(error) => println!("  Secrets:    {} ({})", delivery.state, error),
+            None => println!("  Secrets:    {}", delivery.state),
+        }
+    }
+    if let Some(expires_at) = &instance.config.expires_at {
+        println!("  Expires:    {}", expires_at);
+    }
+    if let Some(idle_timeout) = instance.config.idle_timeout_secs {
+        println!("  Idle Stop:  after {}", format_duration(idle_timeout));
+    }
     println!("  Created:    {}", instance.created_at);
 
     if let Some(endpoints) = &instance.endpoints {
@@ -67,3 +155,160 @@ pub fn print_instance_details(instance: &InstanceResponse) {
         }
     }
 }
+
+pub fn print_stats_history(history: &StatsHistoryResponse) {
+    if history.samples.is_empty() {
+        println!(
+            "No stats recorded for instance '{}'.",
+            history.instance_name
+        );
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            "TIME",
+            "CPU",
+            "RSS",
+            "DISK",
+            "NET RX",
+            "NET TX",
+            "BLOCK READ",
+            "BLOCK WRITE",
+        ]);
+
+    for sample in &history.samples {
+        table.add_row(vec![
+            sample.timestamp.clone(),
+            sample
+                .cpu_percent
+                .map(|cpu| format!("{:.1}%", cpu))
+                .unwrap_or_else(|| "-".to_string()),
+            format_bytes(sample.rss_bytes),
+            format_bytes(sample.disk_bytes),
+            format_bytes(sample.net_rx_bytes),
+            format_bytes(sample.net_tx_bytes),
+            format_bytes(sample.block_read_bytes),
+            format_bytes(sample.block_write_bytes),
+        ]);
+    }
+
+    println!("{table}");
+    println!(
+        "Sampled every {}; network and block I/O are totals since boot.",
+        format_duration(history.interval_secs)
+    );
+}
+
+pub fn print_template_list(templates: &[TemplateResponse]) {
+    if templates.is_empty() {
+        println!("No templates found.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["NAME", "VCPUS", "MEMORY", "TEE", "DESCRIPTION"]);
+
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+
+    for template in templates {
+        table.add_row(vec![
+            template.name.clone(),
+            or_dash(template.vcpus.map(|v| v.to_string())),
+            or_dash(template.memory_mb.map(|m| format!("{} MB", m))),
+            or_dash(template.tee.map(|t| t.to_string())),
+            template.description.clone().unwrap_or_default(),
+        ]);
+    }
+
+    println!("{table}");
+}
+
+/// Print a template, listing only the settings it sets
+pub fn print_template_details(template: &TemplateResponse) {
+    println!("Template: {}", template.name);
+    if let Some(description) = &template.description {
+        println!("  Description: {}", description);
+    }
+
+    let settings = [
+        ("vCPUs", template.vcpus.map(|v| v.to_string())),
+        ("Memory", template.memory_mb.map(|m| format!("{} MB", m))),
+        ("Storage", template.storage_bytes.map(format_storage)),
+        ("Dev Mode", template.dev.map(|d| d.to_string())),
+        ("TEE Mode", template.tee.map(|t| t.to_string())),
+        ("vCPU Type", template.vcpu_type.clone()),
+        ("SEV-SNP", template.sev_snp.as_ref().map(format_sev_snp)),
+        ("Accelerator", template.accelerator.clone()),
+        ("Chain ID", template.chain_id.clone()),
+        (
+            "Block Time",
+            template.block_time.map(|b| format!("{} ms", b)),
+        ),
+        ("Accounts", template.accounts.map(|a| a.to_string())),
+        ("Disable Fee", template.disable_fee.map(|d| d.to_string())),
+        ("TTL", template.ttl_secs.map(format_duration)),
+        ("Idle Stop", template.idle_timeout_secs.map(format_duration)),
+    ];
+
+    for (label, value) in settings {
+        if let Some(value) = value {
+            println!("  {:<12} {}", format!("{}:", label), value);
+        }
+    }
+    if !template.extra_args.is_empty() {
+        println!("  {:<12} {}", "Katana Args:", template.extra_args.join(" "));
+    }
+    println!("  {:<12} {}", "Created:", template.created_at);
+}
+
+/// Summarize SEV-SNP launch settings, e.g. "policy 0x30000, kernel hashes"
+fn format_sev_snp(sev_snp: &SevSnpRequest) -> String {
+    let mut parts = Vec::new();
+    if let Some(policy) = sev_snp.policy {
+        parts.push(format!("policy {:#x}", policy));
+    }
+    if sev_snp.host_data.is_some() {
+        parts.push("host data".to_string());
+    }
+    if sev_snp.id_block.is_some() {
+        parts.push("ID block".to_string());
+    }
+    if sev_snp.kernel_hashes == Some(true) {
+        parts.push("kernel hashes".to_string());
+    }
+    if parts.is_empty() {
+        "host defaults".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+/// Format seconds as a human-readable duration, e.g. "1h 30m"
+fn format_duration(secs: u64) -> String {
+    humantime::format_duration(std::time::Duration::from_secs(secs)).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_balance() {
+        assert_eq!(
+            format_balance("0x21e19e0c9bab2400000").as_deref(),
+            Some("10000.0000")
+        );
+        assert_eq!(
+            format_balance("0x6f05b59d3b20000").as_deref(),
+            Some("0.5000")
+        );
+        assert_eq!(format_balance("not hex"), None);
+    }
+}
@@ -1,9 +1,11 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 mod commands;
 mod config;
 mod format;
+mod manifest;
 
 use config::CliConfig;
 use katana_client::Client;
@@ -23,72 +25,155 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Create a new instance
-    Create {
+    Create(Box<commands::create::CreateArgs>),
+    /// Start an instance, or all stopped instances matching -l
+    Start {
+        #[command(flatten)]
+        target: commands::Target,
+    },
+    /// Stop an instance, or all running instances matching -l
+    Stop {
+        #[command(flatten)]
+        target: commands::Target,
+        /// Seconds to wait for the guest to power off before killing it
+        #[arg(long)]
+        grace_period: Option<u64>,
+    },
+    /// Pause an instance (freeze vCPUs)
+    Pause {
         /// Instance name
         name: String,
-        /// Number of vCPUs
-        #[arg(long, default_value = "2")]
-        vcpus: u32,
-        /// Memory size (e.g., "4G", "2048M")
-        #[arg(long, default_value = "2G")]
-        memory: String,
-        /// Storage size (e.g., "10G", "5120M")
-        #[arg(long, default_value = "10G")]
-        storage: String,
-        /// RPC port (auto-allocated if not specified)
-        #[arg(long)]
-        port: Option<u16>,
-        /// Enable development mode
-        #[arg(long, default_value = "true")]
-        dev: bool,
-        /// Enable TEE mode
-        #[arg(long)]
-        tee: bool,
     },
-    /// Start an instance
-    Start {
+    /// Resume a paused or suspended instance
+    Resume {
         /// Instance name
         name: String,
     },
-    /// Stop an instance
-    Stop {
+    /// Suspend an instance to RAM (ACPI S3)
+    Suspend {
         /// Instance name
         name: String,
     },
-    /// Delete an instance
-    Delete {
+    /// Wake a suspended instance
+    Wake {
         /// Instance name
         name: String,
     },
+    /// Reset (hard reboot) an instance
+    Reset {
+        /// Instance name
+        name: String,
+    },
+    /// Delete an instance, or all instances matching -l
+    Delete {
+        #[command(flatten)]
+        target: commands::Target,
+        /// Kill the VM if it is running or stuck, then delete it
+        #[arg(long, short = 'f')]
+        force: bool,
+    },
     /// List all instances
-    List,
-    /// Show instance details
-    Show {
+    List {
+        /// Label selector, e.g. "team=bridge,env=ci"
+        #[arg(long, short = 'l')]
+        selector: Option<String>,
+    },
+    /// Set (key=value) or remove (key-) labels on an instance
+    Label {
         /// Instance name
         name: String,
+        /// Changes, e.g. team=bridge env-
+        #[arg(required = true, allow_hyphen_values = true)]
+        changes: Vec<String>,
     },
-    /// View instance logs
-    Logs {
+    /// Set (key=value) or remove (key-) annotations on an instance
+    Annotate {
         /// Instance name
         name: String,
-        /// Number of lines to show
-        #[arg(long, short = 'n')]
-        tail: Option<usize>,
-        /// Stream logs in real-time (like tail -f)
-        #[arg(long, short = 'f')]
-        follow: bool,
+        /// Changes, e.g. "owner=Bridge team" note-
+        #[arg(required = true, allow_hyphen_values = true)]
+        changes: Vec<String>,
     },
+    /// Show instance details
+    Show {
+        /// Instance name
+        name: String,
+    },
+    /// View instance logs
+    Logs(commands::logs::LogsArgs),
     /// Show instance statistics
     Stats {
         /// Instance name
         name: String,
+        /// Show recorded resource usage over time
+        #[arg(long)]
+        history: bool,
+        /// Only history since this time (RFC 3339, or a duration such as "1h" ago)
+        #[arg(long, requires = "history")]
+        since: Option<String>,
+    },
+    /// Live resource usage of all instances
+    Top(commands::top::TopArgs),
+    /// Show or export the prefunded accounts of a dev mode instance
+    Accounts(commands::accounts::AccountsArgs),
+    /// Manage instance secrets, delivered to the guest at boot
+    Secret {
+        #[command(subcommand)]
+        command: commands::secret::SecretCommand,
+    },
+    /// Check whether the host can run instances
+    Doctor,
+    /// Manage instance templates
+    Template {
+        #[command(subcommand)]
+        command: commands::template::TemplateCommand,
+    },
+    /// Create, update or delete instances to match a manifest
+    Apply {
+        /// Manifest file (TOML, or YAML with a .yaml/.yml extension)
+        #[arg(long, short = 'f')]
+        file: PathBuf,
+        /// Delete instances created from this manifest that are no longer in it
+        #[arg(long)]
+        prune: bool,
+        /// Prune instances matching this label selector instead
+        #[arg(long = "selector", short = 'l', requires = "prune")]
+        selector: Option<String>,
+        /// Confirm deleting instances
+        #[arg(long, short = 'y')]
+        yes: bool,
+        /// Allow recreating instances whose fixed settings changed, losing
+        /// their chain data
+        #[arg(long)]
+        allow_replace: bool,
+    },
+    /// Show the changes `apply` would make, without making them
+    Diff {
+        /// Manifest file (TOML, or YAML with a .yaml/.yml extension)
+        #[arg(long, short = 'f')]
+        file: PathBuf,
+        /// Include instances created from this manifest that are no longer
+        /// in it as deletions
+        #[arg(long)]
+        prune: bool,
+        /// Prune instances matching this label selector instead
+        #[arg(long = "selector", short = 'l', requires = "prune")]
+        selector: Option<String>,
     },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     let cli = Cli::parse();
 
+    if let Err(err) = run(cli).await {
+        // API errors carry the daemon's message, e.g. invalid state transitions
+        eprintln!("Error: {:#}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
     // Load configuration
     let config = CliConfig::load()?;
 
@@ -111,37 +196,94 @@ async fn main() -> Result<()> {
 
     // Execute command
     match cli.command {
-        Commands::Create {
-            name,
-            vcpus,
-            memory,
-            storage,
-            port,
-            dev,
-            tee,
-        } => {
-            commands::create::execute(
-                &client,
-                name,
-                vcpus,
-                memory,
-                storage,
-                port,
-                dev,
-                tee,
-                &output_format,
-            )
-            .await?
+        Commands::Create(args) => commands::create::execute(&client, *args, &output_format).await?,
+        Commands::Start { target } => match (target.name, target.selector) {
+            (_, Some(selector)) => {
+                commands::start::execute_selected(&client, &selector, &output_format).await?
+            }
+            (Some(name), None) => commands::start::execute(&client, name, &output_format).await?,
+            (None, None) => unreachable!("clap requires a name or selector"),
+        },
+        Commands::Stop {
+            target,
+            grace_period,
+        } => match (target.name, target.selector) {
+            (_, Some(selector)) => {
+                commands::stop::execute_selected(&client, &selector, grace_period, &output_format)
+                    .await?
+            }
+            (Some(name), None) => {
+                commands::stop::execute(&client, name, grace_period, &output_format).await?
+            }
+            (None, None) => unreachable!("clap requires a name or selector"),
+        },
+        Commands::Pause { name } => commands::pause::execute(&client, name, &output_format).await?,
+        Commands::Resume { name } => {
+            commands::resume::execute(&client, name, &output_format).await?
+        }
+        Commands::Suspend { name } => {
+            commands::suspend::execute(&client, name, &output_format).await?
+        }
+        Commands::Wake { name } => commands::wake::execute(&client, name, &output_format).await?,
+        Commands::Reset { name } => commands::reset::execute(&client, name, &output_format).await?,
+        Commands::Delete { target, force } => match (target.name, target.selector) {
+            (_, Some(selector)) => {
+                commands::delete::execute_selected(&client, &selector, force, &output_format)
+                    .await?
+            }
+            (Some(name), None) => commands::delete::execute(&client, name, force).await?,
+            (None, None) => unreachable!("clap requires a name or selector"),
+        },
+        Commands::List { selector } => {
+            commands::list::execute(&client, selector, &output_format).await?
+        }
+        Commands::Label { name, changes } => {
+            commands::label::execute(&client, name, changes, false, &output_format).await?
+        }
+        Commands::Annotate { name, changes } => {
+            commands::label::execute(&client, name, changes, true, &output_format).await?
         }
-        Commands::Start { name } => commands::start::execute(&client, name, &output_format).await?,
-        Commands::Stop { name } => commands::stop::execute(&client, name, &output_format).await?,
-        Commands::Delete { name } => commands::delete::execute(&client, name).await?,
-        Commands::List => commands::list::execute(&client, &output_format).await?,
         Commands::Show { name } => commands::show::execute(&client, name, &output_format).await?,
-        Commands::Logs { name, tail, follow } => {
-            commands::logs::execute(&client, name, tail, follow).await?
+        Commands::Logs(args) => commands::logs::execute(&client, args, &output_format).await?,
+        Commands::Stats {
+            name,
+            history: true,
+            since,
+        } => commands::stats::execute_history(&client, name, since, &output_format).await?,
+        Commands::Stats { name, .. } => {
+            commands::stats::execute(&client, name, &output_format).await?
+        }
+        Commands::Top(args) => commands::top::execute(&client, args, &output_format).await?,
+        Commands::Accounts(args) => {
+            commands::accounts::execute(&client, args, &output_format).await?
+        }
+        Commands::Secret { command } => {
+            commands::secret::execute(&client, command, &output_format).await?
+        }
+        Commands::Doctor => commands::doctor::execute(&client, &output_format).await?,
+        Commands::Template { command } => {
+            commands::template::execute(&client, command, &output_format).await?
+        }
+        Commands::Apply {
+            file,
+            prune,
+            selector,
+            yes,
+            allow_replace,
+        } => {
+            let options = commands::apply::ApplyOptions {
+                prune,
+                selector,
+                yes,
+                allow_replace,
+            };
+            commands::apply::execute(&client, file, options, &output_format).await?
         }
-        Commands::Stats { name } => commands::stats::execute(&client, name, &output_format).await?,
+        Commands::Diff {
+            file,
+            prune,
+            selector,
+        } => commands::diff::execute(&client, file, prune, selector, &output_format).await?,
     }
 
     Ok(())
@@ -0,0 +1,798 @@
+// Declarative instance manifests for `apply` and `diff`
+use anyhow::{bail, Context, Result};
+use byte_unit::Byte;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use katana_models::{
+    CreateInstanceRequest, InstanceResponse, MessagingSettings, UpdateInstanceRequest,
+};
+
+/// Accounts the daemon pre-funds when none are requested
+const DEFAULT_ACCOUNTS: u16 = 10;
+
+/// Label naming the manifest an instance was created from. `apply --prune`
+/// only deletes instances carrying it, unless given a selector.
+pub const MANIFEST_LABEL: &str = "katana.io/manifest";
+
+/// Desired set of instances, loaded from TOML or YAML
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Stamped on its instances as `MANIFEST_LABEL`; `load` defaults it to
+    /// the file name without extension
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub instances: Vec<InstanceSpec>,
+}
+
+/// Desired state of a single instance
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstanceSpec {
+    pub name: String,
+    #[serde(default = "default_vcpus")]
+    pub vcpus: u32,
+    #[serde(default = "default_memory")]
+    pub memory: String,
+    #[serde(default = "default_storage")]
+    pub storage: String,
+    /// RPC port, auto-allocated if not set
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default = "default_dev")]
+    pub dev: bool,
+    #[serde(default)]
+    pub tee: bool,
+    /// vCPU model, defaults to "host" or "EPYC-v4" with TEE
+    #[serde(default)]
+    pub vcpu_type: Option<String>,
+    #[serde(default = "default_accelerator")]
+    pub accelerator: String,
+    #[serde(default)]
+    pub chain_id: Option<String>,
+    #[serde(default)]
+    pub block_time: Option<u64>,
+    #[serde(default)]
+    pub accounts: Option<u16>,
+    #[serde(default)]
+    pub disable_fee: bool,
+    #[serde(default)]
+    pub katana_args: Vec<String>,
+    /// Starknet RPC URL to fork state from
+    #[serde(default)]
+    pub fork_provider: Option<String>,
+    #[serde(default)]
+    pub fork_block: Option<u64>,
+    /// L1 messaging with a settlement chain
+    #[serde(default)]
+    pub messaging: Option<MessagingSettings>,
+    /// Environment variables for Katana
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+}
+
+fn default_vcpus() -> u32 {
+    2
+}
+
+fn default_memory() -> String {
+    "2G".to_string()
+}
+
+fn default_storage() -> String {
+    "10G".to_string()
+}
+
+fn default_dev() -> bool {
+    true
+}
+
+fn default_accelerator() -> String {
+    "auto".to_string()
+}
+
+/// vCPU model the daemon uses when none is given
+fn default_vcpu_type(tee: bool) -> String {
+    if tee {
+        "EPYC-v4".to_string()
+    } else {
+        "host".to_string()
+    }
+}
+
+impl Manifest {
+    /// Load a manifest, using YAML for `.yaml`/`.yml` files and TOML otherwise
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest {}", path.display()))?;
+
+        let is_yaml = matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("yaml" | "yml")
+        );
+
+        let default_name = path.file_stem().and_then(|stem| stem.to_str());
+        let manifest = if is_yaml {
+            Self::from_yaml(&contents, default_name)
+        } else {
+            Self::from_toml(&contents, default_name)
+        };
+
+        manifest.with_context(|| format!("Invalid manifest {}", path.display()))
+    }
+
+    pub fn from_toml(contents: &str, default_name: Option<&str>) -> Result<Self> {
+        toml::from_str::<Self>(contents)?.finish(default_name)
+    }
+
+    pub fn from_yaml(contents: &str, default_name: Option<&str>) -> Result<Self> {
+        serde_yaml::from_str::<Self>(contents)?.finish(default_name)
+    }
+
+    /// Validate the manifest and stamp its name, `default_name` unless it
+    /// sets one, on every instance
+    fn finish(mut self, default_name: Option<&str>) -> Result<Self> {
+        if self.name.is_none() {
+            self.name = default_name.map(|name| name.to_string());
+        }
+        self.validate()?;
+
+        if let Some(name) = &self.name {
+            for spec in &mut self.instances {
+                match spec.labels.get(MANIFEST_LABEL) {
+                    Some(value) if value != name => bail!(
+                        "Instance '{}' sets {} to '{}', but the manifest is '{}'",
+                        spec.name,
+                        MANIFEST_LABEL,
+                        value,
+                        name
+                    ),
+                    _ => {
+                        spec.labels.insert(MANIFEST_LABEL.to_string(), name.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Names of the instances created from this manifest, i.e. labelled
+    /// with its name
+    pub fn managed(&self, current: &[InstanceResponse]) -> Vec<String> {
+        let Some(name) = &self.name else {
+            return vec![];
+        };
+
+        current
+            .iter()
+            .filter(|instance| instance.labels.get(MANIFEST_LABEL) == Some(name))
+            .map(|instance| instance.name.clone())
+            .collect()
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(name) = &self.name {
+            let valid = !name.is_empty()
+                && name.len() <= 63
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+            if !valid {
+                bail!(
+                    "Invalid manifest name '{}': must be 1-63 alphanumerics, '-', '_' or '.'",
+                    name
+                );
+            }
+        }
+
+        let mut names = HashSet::new();
+
+        for spec in &self.instances {
+            if spec.name.is_empty() {
+                bail!("Instance name cannot be empty");
+            }
+            if !names.insert(spec.name.as_str()) {
+                bail!("Duplicate instance '{}'", spec.name);
+            }
+            spec.validate()
+                .with_context(|| format!("Invalid instance '{}'", spec.name))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl InstanceSpec {
+    fn validate(&self) -> Result<()> {
+        parse_bytes(&self.memory)?;
+        parse_bytes(&self.storage)?;
+
+        if !matches!(self.accelerator.as_str(), "kvm" | "tcg" | "auto") {
+            bail!(
+                "Invalid accelerator '{}' (expected kvm, tcg or auto)",
+                self.accelerator
+            );
+        }
+        if self.tee && self.accelerator == "tcg" {
+            bail!("TEE mode requires KVM acceleration");
+        }
+
+        Ok(())
+    }
+
+    fn vcpu_type(&self) -> String {
+        self.vcpu_type
+            .clone()
+            .unwrap_or_else(|| default_vcpu_type(self.tee))
+    }
+
+    fn memory_mb(&self) -> u64 {
+        parse_bytes(&self.memory).unwrap_or_default() / 1024 / 1024
+    }
+
+    fn storage_bytes(&self) -> u64 {
+        parse_bytes(&self.storage).unwrap_or_default()
+    }
+
+    pub fn create_request(&self) -> CreateInstanceRequest {
+        CreateInstanceRequest {
+            name: self.name.clone(),
+            template: None,
+            vcpus: Some(self.vcpus),
+            memory: Some(self.memory.clone()),
+            storage: Some(self.storage.clone()),
+            port: self.port,
+            dev: Some(self.dev),
+            tee: Some(self.tee),
+            vcpu_type: Some(self.vcpu_type()),
+            chain_id: self.chain_id.clone(),
+            block_time: self.block_time,
+            accounts: self.accounts,
+            disable_fee: Some(self.disable_fee),
+            extra_args: self.katana_args.clone(),
+            fork_provider: self.fork_provider.clone(),
+            fork_block: self.fork_block,
+            messaging: self.messaging.clone(),
+            genesis: None,
+            chain_spec: None,
+            env: self.env.clone(),
+            config_drive_args: None,
+            tls_cert: None,
+            tls_key: None,
+            accelerator: Some(self.accelerator.clone()),
+            balloon: None,
+            sev_snp: None,
+            labels: self.labels.clone(),
+            annotations: self.annotations.clone(),
+            ttl: None,
+            expires_at: None,
+            idle_timeout: None,
+        }
+    }
+
+    pub fn update_request(&self) -> UpdateInstanceRequest {
+        UpdateInstanceRequest {
+            vcpus: self.vcpus,
+            memory: self.memory.clone(),
+            dev: self.dev,
+            chain_id: self.chain_id.clone(),
+            block_time: self.block_time,
+            accounts: self.accounts,
+            disable_fee: self.disable_fee,
+            extra_args: self.katana_args.clone(),
+            fork_provider: self.fork_provider.clone(),
+            fork_block: self.fork_block,
+            messaging: self.messaging.clone(),
+            env: self.env.clone(),
+            accelerator: Some(self.accelerator.clone()),
+        }
+    }
+}
+
+fn parse_bytes(value: &str) -> Result<u64> {
+    Byte::parse_str(value, true)
+        .map(|b| b.as_u64())
+        .map_err(|e| anyhow::anyhow!("Invalid size '{}': {}", value, e))
+}
+
+/// What `apply` will do to converge an instance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionKind {
+    Create,
+    /// Settings change that can be applied to the existing instance
+    Update,
+    /// Change to a setting fixed at creation; the instance is recreated
+    Replace,
+    /// Instance is managed by the manifest but no longer in it (only with
+    /// `--prune`)
+    Delete,
+    Unchanged,
+}
+
+/// Fields that can change without restarting the instance
+pub const METADATA_FIELDS: &[&str] = &["labels", "annotations"];
+
+/// A single differing setting
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub field: &'static str,
+    pub current: String,
+    pub desired: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedAction {
+    pub name: String,
+    pub action: ActionKind,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<Change>,
+}
+
+/// Compare the manifest against the current instances. Instances named in
+/// `prunable` that are not in the manifest are deleted.
+///
+/// Deletions come first so that ports they hold are released before creates.
+pub fn plan(
+    manifest: &Manifest,
+    current: &[InstanceResponse],
+    prunable: &[String],
+) -> Vec<PlannedAction> {
+    let mut actions = Vec::new();
+
+    for name in prunable {
+        if !manifest.instances.iter().any(|spec| &spec.name == name) {
+            actions.push(PlannedAction {
+                name: name.clone(),
+                action: ActionKind::Delete,
+                changes: vec![],
+            });
+        }
+    }
+
+    for spec in &manifest.instances {
+        let action = match current.iter().find(|instance| instance.name == spec.name) {
+            None => PlannedAction {
+                name: spec.name.clone(),
+                action: ActionKind::Create,
+                changes: vec![],
+            },
+            Some(instance) => {
+                let (fixed, mutable) = diff_instance(spec, instance);
+                let action = if !fixed.is_empty() {
+                    ActionKind::Replace
+                } else if !mutable.is_empty() {
+                    ActionKind::Update
+                } else {
+                    ActionKind::Unchanged
+                };
+
+                PlannedAction {
+                    name: spec.name.clone(),
+                    action,
+                    changes: fixed.into_iter().chain(mutable).collect(),
+                }
+            }
+        };
+        actions.push(action);
+    }
+
+    actions
+}
+
+/// Differences in (creation-time, updatable) settings
+fn diff_instance(spec: &InstanceSpec, instance: &InstanceResponse) -> (Vec<Change>, Vec<Change>) {
+    let config = &instance.config;
+    let mut fixed = Vec::new();
+    let mut mutable = Vec::new();
+
+    fn compare<T: PartialEq + std::fmt::Debug>(
+        changes: &mut Vec<Change>,
+        field: &'static str,
+        current: T,
+        desired: T,
+    ) {
+        if current != desired {
+            changes.push(Change {
+                field,
+                current: format!("{:?}", current),
+                desired: format!("{:?}", desired),
+            });
+        }
+    }
+
+    compare(
+        &mut fixed,
+        "storage_bytes",
+        config.storage_bytes,
+        spec.storage_bytes(),
+    );
+    if let Some(port) = spec.port {
+        compare(&mut fixed, "port", config.rpc_port, port);
+    }
+    compare(&mut fixed, "tee", config.tee_mode, spec.tee);
+    compare(
+        &mut fixed,
+        "vcpu_type",
+        config.vcpu_type.clone(),
+        spec.vcpu_type(),
+    );
+
+    compare(&mut mutable, "vcpus", config.vcpus, spec.vcpus);
+    compare(
+        &mut mutable,
+        "memory_mb",
+        config.memory_mb,
+        spec.memory_mb(),
+    );
+    compare(
+        &mut mutable,
+        "accelerator",
+        config.accelerator.clone(),
+        spec.accelerator.clone(),
+    );
+    compare(&mut mutable, "dev", config.dev, spec.dev);
+    compare(
+        &mut mutable,
+        "chain_id",
+        config.chain_id.clone(),
+        spec.chain_id.clone(),
+    );
+    compare(
+        &mut mutable,
+        "block_time",
+        config.block_time,
+        spec.block_time,
+    );
+    compare(
+        &mut mutable,
+        "accounts",
+        config.accounts,
+        spec.accounts.or(Some(DEFAULT_ACCOUNTS)),
+    );
+    compare(
+        &mut mutable,
+        "disable_fee",
+        config.disable_fee,
+        spec.disable_fee,
+    );
+    compare(
+        &mut mutable,
+        "katana_args",
+        config.extra_args.clone(),
+        spec.katana_args.clone(),
+    );
+    compare(
+        &mut mutable,
+        "fork_provider",
+        config.fork_provider.clone(),
+        spec.fork_provider.clone(),
+    );
+    compare(
+        &mut mutable,
+        "fork_block",
+        config.fork_block,
+        spec.fork_block,
+    );
+    compare(
+        &mut mutable,
+        "messaging",
+        config.messaging.clone(),
+        spec.messaging.clone().map(messaging_with_defaults),
+    );
+    compare(&mut mutable, "env", &config.env, &spec.env);
+    compare(&mut mutable, "labels", &instance.labels, &spec.labels);
+    compare(
+        &mut mutable,
+        "annotations",
+        &instance.annotations,
+        &spec.annotations,
+    );
+
+    (fixed, mutable)
+}
+
+/// Messaging settings as the daemon stores them, so unset defaults don't
+/// show up as changes
+fn messaging_with_defaults(messaging: MessagingSettings) -> MessagingSettings {
+    MessagingSettings {
+        chain: messaging.chain.or_else(|| Some("ethereum".to_string())),
+        interval_secs: messaging.interval_secs.or(Some(2)),
+        from_block: messaging.from_block.or(Some(0)),
+        key_secret: messaging
+            .key_secret
+            .or_else(|| Some("MESSAGING_KEY".to_string())),
+        ..messaging
+    }
+}
+
+/// Print a plan in a compact, diff-like form
+pub fn print_plan(actions: &[PlannedAction]) {
+    for action in actions {
+        let (marker, label) = match action.action {
+            ActionKind::Create => ("+", "create"),
+            ActionKind::Update => ("~", "update"),
+            ActionKind::Replace => ("-/+", "replace"),
+            ActionKind::Delete => ("-", "delete"),
+            ActionKind::Unchanged => ("=", "unchanged"),
+        };
+        println!("{:>3} {} ({})", marker, action.name, label);
+
+        for change in &action.changes {
+            println!(
+                "      {}: {} -> {}",
+                change.field, change.current, change.desired
+            );
+        }
+    }
+
+    let pending = actions
+        .iter()
+        .filter(|a| a.action != ActionKind::Unchanged)
+        .count();
+    if pending == 0 {
+        println!("\nNo changes. Instances match the manifest.");
+    } else {
+        println!("\n{} change(s) to apply.", pending);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use katana_models::InstanceConfigResponse;
+
+    const MANIFEST: &str = r#"
+        [[instances]]
+        name = "l2"
+        vcpus = 4
+        memory = "4G"
+        chain_id = "KATANA_L2"
+        block_time = 1000
+
+        [[instances]]
+        name = "l3"
+        katana_args = ["--http.cors_origins=*"]
+    "#;
+
+    fn instance(name: &str) -> InstanceResponse {
+        InstanceResponse {
+            id: format!("{}-id", name),
+            name: name.to_string(),
+            status: "Running".to_string(),
+            config: InstanceConfigResponse {
+                vcpus: 2,
+                // What the daemon stores for the default "2G"
+                memory_mb: 1907,
+                storage_bytes: 10_000_000_000,
+                rpc_port: 5050,
+                metrics_port: None,
+                tee_mode: false,
+                vsock_cid: None,
+                accelerator: "auto".to_string(),
+                balloon: false,
+                vcpu_type: "host".to_string(),
+                chain_id: None,
+                dev: true,
+                block_time: None,
+                accounts: Some(10),
+                disable_fee: false,
+                extra_args: vec![],
+                fork_provider: None,
+                fork_block: None,
+                messaging: None,
+                chain_spec: None,
+                env: BTreeMap::new(),
+                config_drive_args: false,
+                tls: false,
+                expires_at: None,
+                idle_timeout_secs: None,
+            },
+            created_at: String::new(),
+            updated_at: String::new(),
+            endpoints: None,
+            accelerator: None,
+            labels: BTreeMap::new(),
+            annotations: BTreeMap::new(),
+            secrets_delivery: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_toml() {
+        let manifest = Manifest::from_toml(MANIFEST, None).unwrap();
+
+        assert_eq!(manifest.instances.len(), 2);
+        let l2 = &manifest.instances[0];
+        assert_eq!(l2.vcpus, 4);
+        // Sizes are parsed like the daemon does: "4G" is 4 * 10^9 bytes
+        assert_eq!(l2.memory_mb(), 3814);
+        assert_eq!(l2.block_time, Some(1000));
+
+        let l3 = &manifest.instances[1];
+        assert_eq!(l3.vcpus, 2);
+        assert!(l3.dev);
+        assert_eq!(l3.accelerator, "auto");
+        assert_eq!(
+            l3.create_request().extra_args,
+            vec!["--http.cors_origins=*"]
+        );
+    }
+
+    #[test]
+    fn test_parse_yaml() {
+        let manifest = Manifest::from_yaml(
+            "instances:\n  - name: tee\n    tee: true\n    accelerator: kvm\n",
+            None,
+        )
+        .unwrap();
+
+        let spec = &manifest.instances[0];
+        assert!(spec.tee);
+        assert_eq!(spec.create_request().vcpu_type.as_deref(), Some("EPYC-v4"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let duplicate = "[[instances]]\nname = \"a\"\n[[instances]]\nname = \"a\"\n";
+        assert!(Manifest::from_toml(duplicate, None).is_err());
+
+        let bad_memory = "[[instances]]\nname = \"a\"\nmemory = \"lots\"\n";
+        assert!(Manifest::from_toml(bad_memory, None).is_err());
+
+        let tee_tcg = "[[instances]]\nname = \"a\"\ntee = true\naccelerator = \"tcg\"\n";
+        assert!(Manifest::from_toml(tee_tcg, None).is_err());
+
+        let unknown = "[[instances]]\nname = \"a\"\ncpus = 2\n";
+        assert!(Manifest::from_toml(unknown, None).is_err());
+    }
+
+    #[test]
+    fn test_plan() {
+        let manifest = Manifest::from_toml(
+            r#"
+            [[instances]]
+            name = "same"
+
+            [[instances]]
+            name = "bigger"
+            memory = "4GiB"
+
+            [[instances]]
+            name = "moved"
+            port = 6060
+
+            [[instances]]
+            name = "new"
+
+            [[instances]]
+            name = "relabeled"
+            labels = { team = "bridge" }
+            "#,
+            None,
+        )
+        .unwrap();
+
+        let current = vec![
+            instance("same"),
+            instance("bigger"),
+            instance("moved"),
+            instance("relabeled"),
+            instance("orphan"),
+        ];
+
+        let actions = plan(&manifest, &current, &[]);
+        let kinds: Vec<_> = actions
+            .iter()
+            .map(|a| (a.name.as_str(), a.action))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("same", ActionKind::Unchanged),
+                ("bigger", ActionKind::Update),
+                ("moved", ActionKind::Replace),
+                ("new", ActionKind::Create),
+                ("relabeled", ActionKind::Update),
+            ]
+        );
+        assert_eq!(actions[4].changes[0].field, "labels");
+        assert_eq!(
+            actions[1].changes,
+            vec![Change {
+                field: "memory_mb",
+                current: "1907".to_string(),
+                desired: "4096".to_string(),
+            }]
+        );
+
+        let pruned = plan(&manifest, &current, &["orphan".to_string()]);
+        assert_eq!(pruned[0].name, "orphan");
+        assert_eq!(pruned[0].action, ActionKind::Delete);
+    }
+
+    #[test]
+    fn test_manifest_label() {
+        let manifest = Manifest::from_toml(
+            "[[instances]]\nname = \"l2\"\n[[instances]]\nname = \"l3\"\n",
+            Some("bridge"),
+        )
+        .unwrap();
+        assert_eq!(
+            manifest.instances[0]
+                .create_request()
+                .labels
+                .get(MANIFEST_LABEL),
+            Some(&"bridge".to_string())
+        );
+
+        // Only instances labelled with this manifest are pruned
+        let mut managed = instance("old");
+        managed
+            .labels
+            .insert(MANIFEST_LABEL.to_string(), "bridge".to_string());
+        let mut other = instance("other");
+        other
+            .labels
+            .insert(MANIFEST_LABEL.to_string(), "ci".to_string());
+        let current = vec![managed, other, instance("by-hand")];
+
+        let prunable = manifest.managed(&current);
+        assert_eq!(prunable, vec!["old"]);
+        let actions = plan(&manifest, &current, &prunable);
+        assert_eq!(actions[0].name, "old");
+        assert_eq!(actions[0].action, ActionKind::Delete);
+        assert!(actions[1..].iter().all(|a| a.action == ActionKind::Create));
+
+        let conflicting = "name = \"bridge\"\n[[instances]]\nname = \"l2\"\n\
+                           labels = { \"katana.io/manifest\" = \"ci\" }\n";
+        assert!(Manifest::from_toml(conflicting, None).is_err());
+        assert!(Manifest::from_toml("name = \"no spaces\"\n", None).is_err());
+    }
+
+    #[test]
+    fn test_plan_messaging() {
+        let manifest = Manifest::from_toml(
+            r#"
+            [[instances]]
+            name = "l3"
+
+            [instances.messaging]
+            l1_instance = "l2"
+            chain = "starknet"
+            contract_address = "0x1"
+            sender_address = "0x2"
+            "#,
+            None,
+        )
+        .unwrap();
+
+        let mut current = instance("l3");
+        current.config.messaging = Some(MessagingSettings {
+            chain: Some("starknet".to_string()),
+            rpc_url: None,
+            l1_instance: Some("l2".to_string()),
+            contract_address: "0x1".to_string(),
+            sender_address: "0x2".to_string(),
+            interval_secs: Some(2),
+            from_block: Some(0),
+            key_secret: Some("MESSAGING_KEY".to_string()),
+        });
+
+        // Defaults filled in by the daemon are not changes
+        let actions = plan(&manifest, &[current], &[]);
+        assert_eq!(actions[0].action, ActionKind::Unchanged);
+
+        let actions = plan(&manifest, &[instance("l3")], &[]);
+        assert_eq!(actions[0].action, ActionKind::Update);
+        assert_eq!(actions[0].changes[0].field, "messaging");
+    }
+}
@@ -10,10 +10,114 @@ use std::path::PathBuf;
 use tokio::net::UnixStream;
 
 use katana_models::{
-    CreateInstanceRequest, ErrorResponse, InstanceResponse, ListInstancesResponse, LogsResponse,
-    StatsResponse,
+    AccountsResponse, AttestationResponse, CreateInstanceRequest, CreateTemplateRequest,
+    ErrorResponse, HostInfoResponse, InstanceResponse, ListInstancesResponse, ListSecretsResponse,
+    ListTemplatesResponse, LogsResponse, SecretInfo, SetSecretRequest, StatsHistoryResponse,
+    StatsResponse, TemplateResponse, UpdateInstanceRequest, UpdateLabelsRequest,
 };
 
+/// Error reported by the daemon API.
+///
+/// Returned inside `anyhow::Error`; use `downcast_ref::<ApiError>()` to inspect it.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub code: String,
+    pub message: String,
+}
+
+impl ApiError {
+    /// The operation is not valid for the instance's current state
+    pub fn is_invalid_state(&self) -> bool {
+        self.code == "INVALID_STATE"
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.code == "NOT_FOUND"
+    }
+
+    fn from_response(status: StatusCode, body: Value) -> Self {
+        match serde_json::from_value::<ErrorResponse>(body.clone()) {
+            Ok(error) => Self {
+                status,
+                code: error.error.code,
+                message: error.error.message,
+            },
+            Err(_) => Self {
+                status,
+                code: status.as_str().to_string(),
+                message: format!("HTTP {}: {}", status, body),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Options for fetching or streaming logs; unset fields use the daemon defaults
+#[derive(Debug, Clone, Default)]
+pub struct LogsQuery {
+    /// Maximum number of lines, counted from the end
+    pub tail: Option<usize>,
+    /// Also parse lines into structured records
+    pub structured: bool,
+    /// Log to read: "kernel", "katana" or "all" (default)
+    pub source: Option<String>,
+    /// RFC 3339 time, or a duration such as "10m" ago
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Regex lines must match
+    pub grep: Option<String>,
+    /// Minimum level (trace, debug, info, warn or error)
+    pub level: Option<String>,
+    /// Only lines before this byte offset (`get_logs` paging)
+    pub before: Option<u64>,
+    /// Stream from this byte offset instead of the last lines (`stream_logs`)
+    pub offset: Option<u64>,
+}
+
+impl LogsQuery {
+    fn to_query_string(&self) -> String {
+        let mut params = Vec::new();
+
+        if let Some(tail) = self.tail {
+            params.push(format!("tail={}", tail));
+        }
+        if self.structured {
+            params.push("format=json".to_string());
+        }
+        for (key, value) in [
+            ("source", &self.source),
+            ("since", &self.since),
+            ("until", &self.until),
+            ("grep", &self.grep),
+            ("level", &self.level),
+        ] {
+            if let Some(value) = value {
+                params.push(format!("{}={}", key, encode_query_value(value)));
+            }
+        }
+        if let Some(before) = self.before {
+            params.push(format!("before={}", before));
+        }
+        if let Some(offset) = self.offset {
+            params.push(format!("offset={}", offset));
+        }
+
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", params.join("&"))
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Client {
     socket_path: PathBuf,
@@ -40,15 +144,53 @@ impl Client {
         self.get("/api/v1/instances").await
     }
 
+    /// List instances whose labels match a selector, e.g. "team=bridge,env=ci"
+    pub async fn list_instances_by_selector(
+        &self,
+        selector: &str,
+    ) -> Result<ListInstancesResponse> {
+        let path = format!(
+            "/api/v1/instances?selector={}",
+            encode_query_value(selector)
+        );
+        self.get(&path).await
+    }
+
     /// Get a specific instance by name
     pub async fn get_instance(&self, name: &str) -> Result<InstanceResponse> {
         let path = format!("/api/v1/instances/{}", name);
         self.get(&path).await
     }
 
-    /// Delete an instance
-    pub async fn delete_instance(&self, name: &str) -> Result<()> {
+    /// Replace the settings of a stopped instance
+    pub async fn update_instance(
+        &self,
+        name: &str,
+        request: UpdateInstanceRequest,
+    ) -> Result<InstanceResponse> {
         let path = format!("/api/v1/instances/{}", name);
+        let body = serde_json::to_value(&request)?;
+        self.request(Method::PUT, &path, Some(body)).await
+    }
+
+    /// Add, change or remove labels and annotations
+    pub async fn update_labels(
+        &self,
+        name: &str,
+        request: UpdateLabelsRequest,
+    ) -> Result<InstanceResponse> {
+        let path = format!("/api/v1/instances/{}/labels", name);
+        let body = serde_json::to_value(&request)?;
+        self.request(Method::PATCH, &path, Some(body)).await
+    }
+
+    /// Delete an instance. With `force`, a running or wedged VM is killed first.
+    pub async fn delete_instance(&self, name: &str, force: bool) -> Result<()> {
+        let path = if force {
+            format!("/api/v1/instances/{}?force=true", name)
+        } else {
+            format!("/api/v1/instances/{}", name)
+        };
         self.delete(&path).await
     }
 
@@ -58,26 +200,65 @@ impl Client {
         self.post(&path, None).await
     }
 
-    /// Stop an instance
-    pub async fn stop_instance(&self, name: &str) -> Result<InstanceResponse> {
-        let path = format!("/api/v1/instances/{}/stop", name);
+    /// Stop an instance, giving the guest `grace_period` seconds to power off
+    pub async fn stop_instance(
+        &self,
+        name: &str,
+        grace_period: Option<u64>,
+    ) -> Result<InstanceResponse> {
+        let path = match grace_period {
+            Some(secs) => format!("/api/v1/instances/{}/stop?grace_period={}", name, secs),
+            None => format!("/api/v1/instances/{}/stop", name),
+        };
+        self.post(&path, None).await
+    }
+
+    /// Pause an instance (freeze vCPUs)
+    pub async fn pause_instance(&self, name: &str) -> Result<InstanceResponse> {
+        let path = format!("/api/v1/instances/{}/pause", name);
         self.post(&path, None).await
     }
 
-    /// Get logs for an instance
-    pub async fn get_logs(&self, name: &str, tail: Option<usize>) -> Result<LogsResponse> {
-        let tail_param = tail.unwrap_or(100);
-        let path = format!("/api/v1/instances/{}/logs?tail={}", name, tail_param);
+    /// Resume a paused or suspended instance
+    pub async fn resume_instance(&self, name: &str) -> Result<InstanceResponse> {
+        let path = format!("/api/v1/instances/{}/resume", name);
+        self.post(&path, None).await
+    }
+
+    /// Suspend an instance to RAM
+    pub async fn suspend_instance(&self, name: &str) -> Result<InstanceResponse> {
+        let path = format!("/api/v1/instances/{}/suspend", name);
+        self.post(&path, None).await
+    }
+
+    /// Wake a suspended instance
+    pub async fn wake_instance(&self, name: &str) -> Result<InstanceResponse> {
+        let path = format!("/api/v1/instances/{}/wake", name);
+        self.post(&path, None).await
+    }
+
+    /// Reset (hard reboot) an instance
+    pub async fn reset_instance(&self, name: &str) -> Result<InstanceResponse> {
+        let path = format!("/api/v1/instances/{}/reset", name);
+        self.post(&path, None).await
+    }
+
+    /// Get logs for an instance, newest last
+    pub async fn get_logs(&self, name: &str, query: &LogsQuery) -> Result<LogsResponse> {
+        let path = format!("/api/v1/instances/{}/logs{}", name, query.to_query_string());
         self.get(&path).await
     }
 
     /// Stream logs for an instance using Server-Sent Events
-    pub async fn stream_logs<F>(&self, name: &str, tail: Option<usize>, callback: F) -> Result<()>
+    pub async fn stream_logs<F>(&self, name: &str, query: &LogsQuery, callback: F) -> Result<()>
     where
         F: FnMut(String, String),
     {
-        let tail_param = tail.unwrap_or(20);
-        let path = format!("/api/v1/instances/{}/logs/stream?tail={}", name, tail_param);
+        let path = format!(
+            "/api/v1/instances/{}/logs/stream{}",
+            name,
+            query.to_query_string()
+        );
         self.stream_sse(&path, callback).await
     }
 
@@ -87,6 +268,120 @@ impl Client {
         self.get(&path).await
     }
 
+    /// Get the prefunded accounts of a dev mode instance, with their keys
+    pub async fn get_accounts(&self, name: &str) -> Result<AccountsResponse> {
+        let path = format!("/api/v1/instances/{}/accounts", name);
+        self.get(&path).await
+    }
+
+    /// Get an SEV-SNP attestation report bound to `report_data` (64 bytes,
+    /// hex-encoded) from a TEE instance
+    pub async fn get_attestation(
+        &self,
+        name: &str,
+        report_data: &str,
+    ) -> Result<AttestationResponse> {
+        let path = format!(
+            "/api/v1/instances/{}/attestation?report_data={}",
+            name,
+            encode_query_value(report_data)
+        );
+        self.get(&path).await
+    }
+
+    /// List the names of an instance's secrets
+    pub async fn list_secrets(&self, name: &str) -> Result<ListSecretsResponse> {
+        let path = format!("/api/v1/instances/{}/secrets", name);
+        self.get(&path).await
+    }
+
+    /// Create or replace an instance secret
+    pub async fn set_secret(
+        &self,
+        name: &str,
+        secret: &str,
+        request: SetSecretRequest,
+    ) -> Result<SecretInfo> {
+        let path = format!("/api/v1/instances/{}/secrets/{}", name, secret);
+        let body = serde_json::to_value(&request)?;
+        self.request(Method::PUT, &path, Some(body)).await
+    }
+
+    /// Delete an instance secret
+    pub async fn delete_secret(&self, name: &str, secret: &str) -> Result<()> {
+        let path = format!("/api/v1/instances/{}/secrets/{}", name, secret);
+        self.delete(&path).await
+    }
+
+    /// Get recorded resource usage for an instance, optionally only samples
+    /// since an RFC 3339 time or a duration ago such as "1h"
+    pub async fn get_stats_history(
+        &self,
+        name: &str,
+        since: Option<&str>,
+    ) -> Result<StatsHistoryResponse> {
+        let mut path = format!("/api/v1/instances/{}/stats/history", name);
+        if let Some(since) = since {
+            path.push_str(&format!("?since={}", encode_query_value(since)));
+        }
+        self.get(&path).await
+    }
+
+    /// Stream the latest resource usage of all instances (or those matching
+    /// a label selector) using Server-Sent Events, one `stats` event per
+    /// `interval` (e.g. "5s", defaults to the daemon's sampling interval)
+    pub async fn stream_stats<F>(
+        &self,
+        interval: Option<&str>,
+        selector: Option<&str>,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(String, String),
+    {
+        let params: Vec<String> = [("interval", interval), ("selector", selector)]
+            .into_iter()
+            .filter_map(|(key, value)| Some(format!("{}={}", key, encode_query_value(value?))))
+            .collect();
+
+        let mut path = "/api/v1/stats/stream".to_string();
+        if !params.is_empty() {
+            path.push_str(&format!("?{}", params.join("&")));
+        }
+        self.stream_sse(&path, callback).await
+    }
+
+    /// Create a template
+    pub async fn create_template(
+        &self,
+        request: CreateTemplateRequest,
+    ) -> Result<TemplateResponse> {
+        let body = serde_json::to_value(&request)?;
+        self.post("/api/v1/templates", Some(body)).await
+    }
+
+    /// List all templates
+    pub async fn list_templates(&self) -> Result<ListTemplatesResponse> {
+        self.get("/api/v1/templates").await
+    }
+
+    /// Get a specific template by name
+    pub async fn get_template(&self, name: &str) -> Result<TemplateResponse> {
+        let path = format!("/api/v1/templates/{}", name);
+        self.get(&path).await
+    }
+
+    /// Delete a template
+    pub async fn delete_template(&self, name: &str) -> Result<()> {
+        let path = format!("/api/v1/templates/{}", name);
+        self.delete(&path).await
+    }
+
+    /// Get host capabilities
+    pub async fn get_host_info(&self) -> Result<HostInfoResponse> {
+        self.get("/api/v1/host").await
+    }
+
     async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
         self.request(Method::GET, path, None).await
     }
@@ -96,10 +391,10 @@ impl Client {
     }
 
     async fn delete(&self, path: &str) -> Result<()> {
-        let (status, _) = self.request_raw(Method::DELETE, path, None).await?;
+        let (status, response_body) = self.request_raw(Method::DELETE, path, None).await?;
 
         if !status.is_success() {
-            anyhow::bail!("HTTP {}: Delete failed", status);
+            return Err(ApiError::from_response(status, response_body).into());
         }
 
         Ok(())
@@ -114,12 +409,7 @@ impl Client {
         let (status, response_body) = self.request_raw(method, path, body).await?;
 
         if !status.is_success() {
-            // Try to parse error response
-            if let Ok(error) = serde_json::from_value::<ErrorResponse>(response_body.clone()) {
-                anyhow::bail!("{}", error.error.message);
-            } else {
-                anyhow::bail!("HTTP {}: {}", status, response_body);
-            }
+            return Err(ApiError::from_response(status, response_body).into());
         }
 
         // Deserialize to the target type
@@ -220,7 +510,7 @@ impl Client {
         let byte_stream = BodyStream::new(body).map(|result| {
             result
                 .map(|frame| frame.into_data().unwrap_or_default())
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+                .map_err(std::io::Error::other)
         });
         let mut event_stream = byte_stream.eventsource();
 
@@ -245,3 +535,16 @@ impl Client {
         Ok(())
     }
 }
+
+/// Percent-encode a query string value
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
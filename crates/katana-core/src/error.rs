@@ -20,6 +20,12 @@ pub enum HypervisorError {
     #[error("Instance already exists: {0}")]
     InstanceAlreadyExists(String),
 
+    #[error("Template not found: {0}")]
+    TemplateNotFound(String),
+
+    #[error("Template already exists: {0}")]
+    TemplateAlreadyExists(String),
+
     #[error("Invalid state transition: from {from} to {to}")]
     InvalidStateTransition { from: String, to: String },
 
@@ -29,6 +35,9 @@ pub enum HypervisorError {
     #[error("No ports available in range")]
     NoPortsAvailable,
 
+    #[error("No vsock guest CIDs available from {0}")]
+    NoVsockCidsAvailable(u32),
+
     #[error("VM process not found: {0}")]
     VmProcessNotFound(i32),
 
@@ -56,6 +65,15 @@ pub enum HypervisorError {
     #[error("Attestation verification failed: {0}")]
     AttestationFailed(String),
 
+    #[error("Guest agent error: {0}")]
+    Agent(String),
+
+    #[error("Katana RPC error: {0}")]
+    Rpc(String),
+
+    #[error("Secret error: {0}")]
+    Secret(String),
+
     #[error("Measurement mismatch: expected {expected}, got {actual}")]
     MeasurementMismatch { expected: String, actual: String },
 }
@@ -0,0 +1,325 @@
+// Host capability detection module
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// QEMU system emulator used to run instances
+pub const QEMU_BINARY: &str = "qemu-system-x86_64";
+
+const KVM_DEVICE: &str = "/dev/kvm";
+const SEV_DEVICE: &str = "/dev/sev";
+const KVM_AMD_PARAMS: &str = "/sys/module/kvm_amd/parameters";
+const NBD_MODULE: &str = "/sys/module/nbd";
+
+/// Capabilities of the host relevant to running Katana instances
+#[derive(Debug, Clone)]
+pub struct HostCapabilities {
+    pub kvm: KvmStatus,
+    pub qemu: QemuStatus,
+    pub qemu_img: ToolStatus,
+    pub qemu_nbd: ToolStatus,
+    pub nbd: NbdStatus,
+    pub sev: SevStatus,
+    /// `/dev/vhost-vsock` is present, needed for the guest agent channel
+    pub vhost_vsock: bool,
+    pub cpu_count: usize,
+    pub memory_total_bytes: u64,
+    pub memory_available_bytes: u64,
+}
+
+/// Availability of `/dev/kvm` for the current user
+#[derive(Debug, Clone)]
+pub struct KvmStatus {
+    pub present: bool,
+    pub accessible: bool,
+    /// Reason the device could not be opened, if any
+    pub error: Option<String>,
+}
+
+/// QEMU system emulator binary and the machine types it supports
+#[derive(Debug, Clone)]
+pub struct QemuStatus {
+    pub binary: ToolStatus,
+    pub machine_types: Vec<String>,
+}
+
+/// An external tool looked up in `PATH`
+#[derive(Debug, Clone)]
+pub struct ToolStatus {
+    pub path: Option<PathBuf>,
+    pub version: Option<String>,
+}
+
+/// NBD kernel module status, needed to format qcow2 data disks
+#[derive(Debug, Clone)]
+pub struct NbdStatus {
+    pub module_loaded: bool,
+    pub devices: usize,
+}
+
+/// Host-side AMD SEV support.
+///
+/// Unlike `SevSnpConfig::is_available`, which looks for the guest-side
+/// `/dev/sev-guest` device, this checks whether the host can launch SEV guests.
+#[derive(Debug, Clone)]
+pub struct SevStatus {
+    /// `/dev/sev` (AMD secure processor) is present
+    pub device: bool,
+    /// kvm_amd `sev` parameter is enabled
+    pub sev: bool,
+    /// kvm_amd `sev_es` parameter is enabled
+    pub sev_es: bool,
+    /// kvm_amd `sev_snp` parameter is enabled
+    pub sev_snp: bool,
+}
+
+impl HostCapabilities {
+    /// Probe the host. Runs external commands, so this blocks.
+    pub fn detect() -> Self {
+        let (memory_total_bytes, memory_available_bytes) = detect_memory();
+
+        Self {
+            kvm: KvmStatus::detect(),
+            qemu: QemuStatus::detect(),
+            qemu_img: ToolStatus::detect("qemu-img"),
+            qemu_nbd: ToolStatus::detect("qemu-nbd"),
+            nbd: NbdStatus::detect(),
+            sev: SevStatus::detect(),
+            vhost_vsock: crate::vsock::vhost_vsock_available(),
+            cpu_count: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            memory_total_bytes,
+            memory_available_bytes,
+        }
+    }
+}
+
+impl KvmStatus {
+    pub fn detect() -> Self {
+        let path = Path::new(KVM_DEVICE);
+
+        if !path.exists() {
+            return Self {
+                present: false,
+                accessible: false,
+                error: Some(format!("{} does not exist", KVM_DEVICE)),
+            };
+        }
+
+        match OpenOptions::new().read(true).write(true).open(path) {
+            Ok(_) => Self {
+                present: true,
+                accessible: true,
+                error: None,
+            },
+            Err(e) => Self {
+                present: true,
+                accessible: false,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// Check whether `/dev/kvm` can be opened by the current user
+pub fn kvm_accessible() -> bool {
+    KvmStatus::detect().accessible
+}
+
+impl QemuStatus {
+    pub fn detect() -> Self {
+        let binary = ToolStatus::detect(QEMU_BINARY);
+
+        let machine_types = if binary.path.is_some() {
+            Command::new(QEMU_BINARY)
+                .args(["-machine", "help"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| parse_machine_types(&String::from_utf8_lossy(&output.stdout)))
+                .unwrap_or_default()
+        } else {
+            vec![]
+        };
+
+        Self {
+            binary,
+            machine_types,
+        }
+    }
+
+    /// Check whether the q35 machine type used for instances is supported
+    pub fn supports_q35(&self) -> bool {
+        self.machine_types.iter().any(|m| m == "q35")
+    }
+}
+
+impl ToolStatus {
+    pub fn detect(name: &str) -> Self {
+        let path = find_in_path(name);
+
+        let version = path.as_ref().and_then(|_| {
+            Command::new(name)
+                .arg("--version")
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| parse_version(&String::from_utf8_lossy(&output.stdout)))
+        });
+
+        Self { path, version }
+    }
+
+    pub fn available(&self) -> bool {
+        self.path.is_some()
+    }
+}
+
+impl NbdStatus {
+    pub fn detect() -> Self {
+        let devices = (0..16)
+            .filter(|i| Path::new(&format!("/dev/nbd{}", i)).exists())
+            .count();
+
+        Self {
+            module_loaded: Path::new(NBD_MODULE).exists(),
+            devices,
+        }
+    }
+}
+
+impl SevStatus {
+    pub fn detect() -> Self {
+        let param = |name: &str| {
+            fs::read_to_string(Path::new(KVM_AMD_PARAMS).join(name))
+                .map(|value| parse_bool_param(&value))
+                .unwrap_or(false)
+        };
+
+        Self {
+            device: Path::new(SEV_DEVICE).exists(),
+            sev: param("sev"),
+            sev_es: param("sev_es"),
+            sev_snp: param("sev_snp"),
+        }
+    }
+
+    /// Check whether the host can launch SEV-SNP guests
+    pub fn snp_supported(&self) -> bool {
+        self.device && self.sev_snp
+    }
+}
+
+/// Look up an executable in `PATH`
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path_var = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&path_var)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Total and available memory in bytes
+fn detect_memory() -> (u64, u64) {
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+    (system.total_memory(), system.available_memory())
+}
+
+/// Extract the version number from `--version` output.
+///
+/// e.g. "QEMU emulator version 8.2.2 (Debian 1:8.2.2+ds-0ubuntu1)" -> "8.2.2".
+/// Without a "version" marker, as in "qemu-nbd 8.2.2 (...)", the first token
+/// that looks like a version is taken.
+fn parse_version(output: &str) -> Option<String> {
+    let first_line = output.lines().next()?;
+    if let Some((_, rest)) = first_line.split_once("version ") {
+        return rest.split_whitespace().next().map(|v| v.to_string());
+    }
+
+    first_line
+        .split_whitespace()
+        .find(|token| {
+            token.starts_with(|c: char| c.is_ascii_digit())
+                && token.contains('.')
+                && token.chars().all(|c| c.is_ascii_digit() || c == '.')
+        })
+        .map(|v| v.to_string())
+}
+
+/// Extract machine type names from `-machine help` output
+fn parse_machine_types(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("Supported machines"))
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Parse a kernel module boolean parameter ("Y"/"N" or "1"/"0")
+fn parse_bool_param(value: &str) -> bool {
+    matches!(value.trim(), "Y" | "y" | "1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            parse_version("QEMU emulator version 8.2.2 (Debian 1:8.2.2+ds-0ubuntu1)\nCopyright"),
+            Some("8.2.2".to_string())
+        );
+        assert_eq!(
+            parse_version("qemu-img version 7.0.0\n"),
+            Some("7.0.0".to_string())
+        );
+        assert_eq!(
+            parse_version("qemu-nbd 8.2.2 (Debian 1:8.2.2+ds-0ubuntu1)\nWritten by"),
+            Some("8.2.2".to_string())
+        );
+        assert_eq!(parse_version("garbage"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn test_parse_machine_types() {
+        let output = "Supported machines are:\n\
+            microvm              microvm (i386)\n\
+            pc                   Standard PC (i440FX + PIIX, 1996) (alias of pc-i440fx-8.2)\n\
+            q35                  Standard PC (Q35 + ICH9, 2009) (alias of pc-q35-8.2)\n\
+            pc-q35-8.2           Standard PC (Q35 + ICH9, 2009)\n";
+
+        let machines = parse_machine_types(output);
+        assert_eq!(machines, vec!["microvm", "pc", "q35", "pc-q35-8.2"]);
+
+        let status = QemuStatus {
+            binary: ToolStatus {
+                path: None,
+                version: None,
+            },
+            machine_types: machines,
+        };
+        assert!(status.supports_q35());
+    }
+
+    #[test]
+    fn test_parse_bool_param() {
+        assert!(parse_bool_param("Y\n"));
+        assert!(parse_bool_param("1"));
+        assert!(!parse_bool_param("N\n"));
+        assert!(!parse_bool_param("0"));
+    }
+
+    #[test]
+    fn test_detect() {
+        // Results depend on the host; just verify detection doesn't panic
+        let caps = HostCapabilities::detect();
+        assert!(caps.cpu_count >= 1);
+        assert!(!caps.kvm.accessible || caps.kvm.present);
+    }
+}
@@ -1,5 +1,72 @@
+use super::config_drive::ChainSpecKind;
+use super::messaging::MessagingConfig;
+use crate::qemu::QemuConfig;
+use crate::secrets::secret_references;
+use crate::tee::SevSnpConfig;
+use crate::{HypervisorError, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Hardware acceleration used to run the VM
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Accelerator {
+    /// Hardware virtualization via /dev/kvm
+    Kvm,
+    /// Software emulation, much slower but works without /dev/kvm
+    Tcg,
+    /// KVM when available, otherwise TCG
+    #[default]
+    Auto,
+}
+
+impl Accelerator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Accelerator::Kvm => "kvm",
+            Accelerator::Tcg => "tcg",
+            Accelerator::Auto => "auto",
+        }
+    }
+
+    /// Resolve to a concrete accelerator (never `Auto`)
+    pub fn resolve(self, kvm_available: bool) -> Result<Accelerator> {
+        match self {
+            Accelerator::Kvm if !kvm_available => Err(HypervisorError::InvalidConfig(
+                "KVM acceleration requested but /dev/kvm is not accessible".to_string(),
+            )),
+            Accelerator::Kvm => Ok(Accelerator::Kvm),
+            Accelerator::Tcg => Ok(Accelerator::Tcg),
+            Accelerator::Auto if kvm_available => Ok(Accelerator::Kvm),
+            Accelerator::Auto => Ok(Accelerator::Tcg),
+        }
+    }
+}
+
+impl std::fmt::Display for Accelerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Accelerator {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "kvm" => Ok(Accelerator::Kvm),
+            "tcg" => Ok(Accelerator::Tcg),
+            "auto" => Ok(Accelerator::Auto),
+            _ => Err(format!(
+                "Invalid accelerator '{}' (expected kvm, tcg or auto)",
+                s
+            )),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstanceConfig {
@@ -11,11 +78,24 @@ pub struct InstanceConfig {
     // Network
     pub rpc_port: u16,
     pub metrics_port: Option<u16>,
+    /// Guest CID for the vsock control channel
+    #[serde(default)]
+    pub vsock_cid: Option<u32>,
+
+    // Virtualization
+    #[serde(default)]
+    pub accelerator: Accelerator,
+    /// Attach a virtio-balloon device, so stats show the guest's balloon
+    /// size. Ignored for TEE instances
+    #[serde(default)]
+    pub balloon: bool,
 
     // TEE configuration
     pub tee_mode: bool,
     pub vcpu_type: String,
     pub expected_measurement: Option<String>,
+    #[serde(default)]
+    pub sev_snp: Option<SevSnpConfig>,
 
     // Boot components
     pub kernel_path: PathBuf,
@@ -33,6 +113,33 @@ pub struct InstanceConfig {
     pub accounts: Option<u16>,
     pub disable_fee: bool,
     pub extra_args: Vec<String>,
+    /// Starknet RPC to fork state from, as given by the user
+    #[serde(default)]
+    pub fork_provider: Option<String>,
+    /// Block to fork at, defaults to the provider's latest
+    #[serde(default)]
+    pub fork_block: Option<u64>,
+    /// L1 messaging with a settlement chain
+    #[serde(default)]
+    pub messaging: Option<MessagingConfig>,
+    /// Custom genesis or chain spec on the config drive
+    #[serde(default)]
+    pub chain_spec: Option<ChainSpecKind>,
+    /// Environment variables for Katana, delivered on the config drive
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Pass Katana's args on the config drive only, keeping them off the
+    /// kernel cmdline. Needs a guest init that reads the config drive
+    #[serde(default)]
+    pub config_drive_args: bool,
+
+    // Lifecycle
+    /// Unix timestamp after which the instance is stopped and deleted
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// Stop the instance after this many seconds without RPC connections
+    #[serde(default)]
+    pub idle_timeout_secs: Option<u64>,
 }
 
 impl Default for InstanceConfig {
@@ -43,9 +150,13 @@ impl Default for InstanceConfig {
             storage_bytes: 10 * 1024 * 1024 * 1024, // 10GB
             rpc_port: 5050,
             metrics_port: None,
+            vsock_cid: None,
+            accelerator: Accelerator::Auto,
+            balloon: false,
             tee_mode: false,
             vcpu_type: "host".to_string(),
             expected_measurement: None,
+            sev_snp: None,
             kernel_path: PathBuf::new(),
             initrd_path: PathBuf::new(),
             ovmf_path: None,
@@ -57,11 +168,64 @@ impl Default for InstanceConfig {
             accounts: Some(10),
             disable_fee: false,
             extra_args: vec![],
+            fork_provider: None,
+            fork_block: None,
+            messaging: None,
+            chain_spec: None,
+            env: BTreeMap::new(),
+            config_drive_args: false,
+            expires_at: None,
+            idle_timeout_secs: None,
         }
     }
 }
 
 impl InstanceConfig {
+    /// SEV-SNP launch configuration, if TEE mode is enabled.
+    ///
+    /// Instances created before the SEV-SNP settings were persisted fall back
+    /// to values probed from the host CPU.
+    pub fn sev_snp_config(&self) -> Option<SevSnpConfig> {
+        if !self.tee_mode {
+            return None;
+        }
+
+        Some(self.sev_snp.clone().unwrap_or_else(|| SevSnpConfig {
+            vcpu_type: self.vcpu_type.clone(),
+            ..SevSnpConfig::for_host()
+        }))
+    }
+
+    /// Resolve the configured accelerator against the host.
+    ///
+    /// TEE mode requires KVM, so it never falls back to TCG.
+    pub fn resolve_accelerator(&self, kvm_available: bool) -> Result<Accelerator> {
+        let accelerator = self.accelerator.resolve(kvm_available)?;
+
+        if self.tee_mode && accelerator != Accelerator::Kvm {
+            return Err(HypervisorError::InvalidConfig(
+                "TEE mode requires KVM acceleration".to_string(),
+            ));
+        }
+
+        Ok(accelerator)
+    }
+
+    /// Check whether the instance's TTL has passed
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
+    /// Kernel command line, carrying Katana's args unless they are passed
+    /// on the config drive
+    pub fn kernel_cmdline(&self) -> String {
+        if self.config_drive_args {
+            QemuConfig::config_drive_kernel_cmdline()
+        } else {
+            QemuConfig::build_kernel_cmdline(&self.build_katana_args())
+        }
+    }
+
     pub fn build_katana_args(&self) -> Vec<String> {
         let mut args = vec![
             "--http.addr=0.0.0.0".to_string(),
@@ -72,6 +236,10 @@ impl InstanceConfig {
             args.push(format!("--chain-id={}", chain_id));
         }
 
+        if let Some(chain_spec) = self.chain_spec {
+            args.extend(chain_spec.katana_args());
+        }
+
         if self.dev_mode {
             args.push("--dev".to_string());
         }
@@ -88,7 +256,322 @@ impl InstanceConfig {
             args.push("--disable-fee".to_string());
         }
 
+        if let Some(provider) = &self.fork_provider {
+            let url = fork_provider_for_guest(provider).unwrap_or_else(|_| provider.clone());
+            args.push(format!("--fork.provider={}", url));
+
+            if let Some(block) = self.fork_block {
+                args.push(format!("--fork.block={}", block));
+            }
+        }
+
+        if let Some(messaging) = &self.messaging {
+            args.extend(messaging.katana_args());
+        }
+
+        // Instances created before this was derived from tee_mode carry it in extra_args
+        if self.tee_mode && !self.extra_args.iter().any(|a| a == "--tee.provider") {
+            args.push("--tee.provider".to_string());
+            args.push("sev-snp".to_string());
+        }
+
         args.extend(self.extra_args.clone());
         args
     }
+
+    /// Secrets referenced as `${NAME}` by the fork provider or the messaging
+    /// RPC, which must be set for the guest to expand them
+    pub fn secret_references(&self) -> Vec<String> {
+        let urls = [
+            self.fork_provider.as_deref(),
+            self.messaging.as_ref().and_then(|m| m.rpc_url.as_deref()),
+        ];
+
+        urls.into_iter()
+            .flatten()
+            .flat_map(|url| secret_references(url).unwrap_or_default())
+            .collect()
+    }
+}
+
+/// Host address as seen from the guest with QEMU user networking
+pub const GUEST_HOST_ADDR: &str = "10.0.2.2";
+
+/// Check a fork provider URL and rewrite it for use from inside the guest.
+///
+/// The guest reaches the internet through QEMU's user networking NAT, which
+/// forwards `10.0.2.2` to the host's `127.0.0.1`, so a provider on
+/// `localhost` (such as a local stand-in RPC in tests) is rewritten to that
+/// address. Other loopback addresses are not forwarded and are rejected.
+pub fn fork_provider_for_guest(provider: &str) -> Result<String> {
+    url_for_guest(provider, "fork provider")
+}
+
+/// Check an http(s) URL given for `what` and rewrite a `localhost` host to
+/// the host address as seen from the guest, see `fork_provider_for_guest`.
+///
+/// Secret references (`${NAME}`) are kept as they are for the guest to
+/// expand, rather than percent-encoded.
+pub fn url_for_guest(value: &str, what: &str) -> Result<String> {
+    let references = secret_references(value)?;
+    let placeholder = |index: usize| format!("katana-secret-ref-{}", index);
+
+    let mut masked = value.to_string();
+    for (index, name) in references.iter().enumerate() {
+        masked = masked.replacen(&format!("${{{}}}", name), &placeholder(index), 1);
+    }
+
+    let mut url = reqwest::Url::parse(&masked).map_err(|e| {
+        HypervisorError::InvalidConfig(format!("Invalid {} URL '{}': {}", what, value, e))
+    })?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(HypervisorError::InvalidConfig(format!(
+            "The {} must be an http(s) URL, got '{}'",
+            what, value
+        )));
+    }
+
+    let Some(host) = url.host_str() else {
+        return Err(HypervisorError::InvalidConfig(format!(
+            "The {} URL '{}' has no host",
+            what, value
+        )));
+    };
+    match host {
+        "localhost" | "127.0.0.1" | "[::1]" => {
+            url.set_host(Some(GUEST_HOST_ADDR)).map_err(|e| {
+                HypervisorError::InvalidConfig(format!("Invalid {} URL: {}", what, e))
+            })?;
+        }
+        host if host
+            .trim_matches(|c| c == '[' || c == ']')
+            .parse::<IpAddr>()
+            .is_ok_and(|addr| addr.is_loopback()) =>
+        {
+            return Err(HypervisorError::InvalidConfig(format!(
+                "The {} URL '{}' uses loopback address {}, which the guest cannot \
+                 reach; use localhost or 127.0.0.1",
+                what, value, host
+            )));
+        }
+        _ => {}
+    }
+
+    let mut url = url.to_string();
+    for (index, name) in references.iter().enumerate() {
+        url = url.replacen(&placeholder(index), &format!("${{{}}}", name), 1);
+    }
+
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accelerator_resolve() {
+        assert_eq!(Accelerator::Auto.resolve(true).unwrap(), Accelerator::Kvm);
+        assert_eq!(Accelerator::Auto.resolve(false).unwrap(), Accelerator::Tcg);
+        assert_eq!(Accelerator::Tcg.resolve(true).unwrap(), Accelerator::Tcg);
+        assert_eq!(Accelerator::Kvm.resolve(true).unwrap(), Accelerator::Kvm);
+        assert!(Accelerator::Kvm.resolve(false).is_err());
+    }
+
+    #[test]
+    fn test_tee_requires_kvm() {
+        let config = InstanceConfig {
+            tee_mode: true,
+            ..Default::default()
+        };
+        assert_eq!(config.resolve_accelerator(true).unwrap(), Accelerator::Kvm);
+        assert!(config.resolve_accelerator(false).is_err());
+
+        let config = InstanceConfig {
+            tee_mode: true,
+            accelerator: Accelerator::Tcg,
+            ..Default::default()
+        };
+        assert!(config.resolve_accelerator(true).is_err());
+    }
+
+    #[test]
+    fn test_build_katana_args_tee_provider() {
+        let mut config = InstanceConfig {
+            tee_mode: true,
+            ..Default::default()
+        };
+        let args = config.build_katana_args();
+        assert_eq!(args.iter().filter(|a| *a == "--tee.provider").count(), 1);
+        assert!(args.contains(&"sev-snp".to_string()));
+
+        // Legacy configs with the provider in extra_args don't get it twice
+        config.extra_args = vec!["--tee.provider".to_string(), "sev-snp".to_string()];
+        let args = config.build_katana_args();
+        assert_eq!(args.iter().filter(|a| *a == "--tee.provider").count(), 1);
+
+        config.tee_mode = false;
+        config.extra_args.clear();
+        assert!(!config
+            .build_katana_args()
+            .contains(&"--tee.provider".to_string()));
+    }
+
+    #[test]
+    fn test_is_expired() {
+        let mut config = InstanceConfig::default();
+        assert!(!config.is_expired(i64::MAX));
+
+        config.expires_at = Some(1_000);
+        assert!(!config.is_expired(999));
+        assert!(config.is_expired(1_000));
+    }
+
+    #[test]
+    fn test_accelerator_serde() {
+        assert_eq!(serde_json::to_string(&Accelerator::Tcg).unwrap(), "\"tcg\"");
+        assert_eq!("auto".parse::<Accelerator>().unwrap(), Accelerator::Auto);
+        assert!("hvf".parse::<Accelerator>().is_err());
+
+        // Configs persisted before the accelerator setting default to auto
+        let mut value = serde_json::to_value(InstanceConfig::default()).unwrap();
+        value.as_object_mut().unwrap().remove("accelerator");
+        let config: InstanceConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(config.accelerator, Accelerator::Auto);
+    }
+
+    #[test]
+    fn test_kernel_cmdline() {
+        let mut config = InstanceConfig {
+            dev_mode: true,
+            ..Default::default()
+        };
+        assert!(config.kernel_cmdline().contains("katana.args="));
+        assert!(config.kernel_cmdline().contains("--dev"));
+
+        config.config_drive_args = true;
+        assert!(!config.kernel_cmdline().contains("--dev"));
+        assert!(config.kernel_cmdline().contains("katana.config="));
+    }
+
+    #[test]
+    fn test_build_katana_args_fork() {
+        let mut config = InstanceConfig {
+            fork_provider: Some("https://rpc.example.com/v0_8".to_string()),
+            fork_block: Some(1_000_000),
+            ..Default::default()
+        };
+        let args = config.build_katana_args();
+        assert!(args.contains(&"--fork.provider=https://rpc.example.com/v0_8".to_string()));
+        assert!(args.contains(&"--fork.block=1000000".to_string()));
+
+        // A block without a provider is ignored
+        config.fork_provider = None;
+        let args = config.build_katana_args();
+        assert!(!args.iter().any(|a| a.starts_with("--fork.")));
+    }
+
+    #[test]
+    fn test_fork_provider_for_guest() {
+        assert_eq!(
+            fork_provider_for_guest("http://localhost:5050").unwrap(),
+            "http://10.0.2.2:5050/"
+        );
+        assert_eq!(
+            fork_provider_for_guest("http://127.0.0.1:8545/rpc").unwrap(),
+            "http://10.0.2.2:8545/rpc"
+        );
+        assert_eq!(
+            fork_provider_for_guest("https://rpc.example.com/v0_8").unwrap(),
+            "https://rpc.example.com/v0_8"
+        );
+        assert_eq!(
+            fork_provider_for_guest("http://[::1]:5050").unwrap(),
+            "http://10.0.2.2:5050/"
+        );
+        // QEMU only forwards the host's 127.0.0.1
+        assert!(fork_provider_for_guest("http://127.0.0.2:5050").is_err());
+        assert!(fork_provider_for_guest("http://127.1.2.3:5050").is_err());
+        assert!(fork_provider_for_guest("ws://rpc.example.com").is_err());
+        assert!(fork_provider_for_guest("not a url").is_err());
+
+        // Secret references are left for the guest to expand
+        assert_eq!(
+            fork_provider_for_guest("https://eth.example.com/v2/${FORK_API_KEY}").unwrap(),
+            "https://eth.example.com/v2/${FORK_API_KEY}"
+        );
+        assert_eq!(
+            fork_provider_for_guest("http://localhost:8545/?key=${KEY}&user=${USER}").unwrap(),
+            "http://10.0.2.2:8545/?key=${KEY}&user=${USER}"
+        );
+        assert!(fork_provider_for_guest("https://eth.example.com/${KEY").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fork_provider_reaches_local_rpc() {
+        use crate::rpc::KatanaRpcClient;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        // Stand-in RPC answering a single JSON-RPC call
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = String::new();
+            let mut buf = [0u8; 4096];
+            while !request.contains("starknet_chainId") {
+                let n = stream.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.push_str(&String::from_utf8_lossy(&buf[..n]));
+            }
+
+            let body = r#"{"jsonrpc":"2.0","id":1,"result":"0x4b4154414e41"}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n\
+                 content-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            request
+        });
+
+        let config = InstanceConfig {
+            fork_provider: Some(format!("http://localhost:{}/rpc", port)),
+            fork_block: Some(7),
+            ..Default::default()
+        };
+        let args = config.build_katana_args();
+        let provider = args
+            .iter()
+            .find_map(|a| a.strip_prefix("--fork.provider="))
+            .unwrap();
+        assert_eq!(provider, format!("http://{}:{}/rpc", GUEST_HOST_ADDR, port));
+        assert!(args.contains(&"--fork.block=7".to_string()));
+
+        // QEMU's NAT forwards the guest's view of the host to 127.0.0.1
+        let from_host = provider.replacen(GUEST_HOST_ADDR, "127.0.0.1", 1);
+        let client = KatanaRpcClient::new(from_host).unwrap();
+        assert_eq!(client.chain_id().await.unwrap(), "0x4b4154414e41");
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /rpc "));
+        assert!(request.contains("starknet_chainId"));
+    }
+
+    #[test]
+    fn test_secret_references() {
+        let mut config = InstanceConfig {
+            fork_provider: Some("https://eth.example.com/v2/${FORK_API_KEY}".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(config.secret_references(), vec!["FORK_API_KEY"]);
+
+        config.fork_provider = Some("https://rpc.example.com".to_string());
+        assert!(config.secret_references().is_empty());
+    }
 }
@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Key/value metadata attached to an instance
+pub type Labels = BTreeMap<String, String>;
+
+const MAX_KEY_LEN: usize = 253;
+const MAX_LABEL_VALUE_LEN: usize = 63;
+
+/// Check that a label or annotation key is well formed.
+///
+/// Keys are ASCII alphanumerics plus `-`, `_`, `.` and `/`, so that
+/// prefixes such as `example.com/team` can be used.
+pub fn validate_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.len() > MAX_KEY_LEN {
+        return Err(format!(
+            "Invalid key '{}': must be 1-{} characters",
+            key, MAX_KEY_LEN
+        ));
+    }
+
+    if !key
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+    {
+        return Err(format!(
+            "Invalid key '{}': only alphanumerics, '-', '_', '.' and '/' are allowed",
+            key
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check that a label value can be used in a selector.
+///
+/// Annotation values are free-form and not checked.
+pub fn validate_label_value(value: &str) -> Result<(), String> {
+    if value.len() > MAX_LABEL_VALUE_LEN {
+        return Err(format!(
+            "Invalid label value '{}': must be at most {} characters",
+            value, MAX_LABEL_VALUE_LEN
+        ));
+    }
+
+    if !value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "Invalid label value '{}': only alphanumerics, '-', '_' and '.' are allowed",
+            value
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check every key and value of a label set
+pub fn validate_labels(labels: &Labels) -> Result<(), String> {
+    for (key, value) in labels {
+        validate_key(key)?;
+        validate_label_value(value)?;
+    }
+    Ok(())
+}
+
+/// Filter over instance labels, e.g. `team=bridge,env!=prod,owner,!tmp`.
+///
+/// All comma-separated requirements must match. An empty selector matches
+/// every instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LabelSelector {
+    requirements: Vec<Requirement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Requirement {
+    Equals(String, String),
+    NotEquals(String, String),
+    Exists(String),
+    NotExists(String),
+}
+
+impl LabelSelector {
+    pub fn matches(&self, labels: &Labels) -> bool {
+        self.requirements.iter().all(|req| match req {
+            Requirement::Equals(key, value) => labels.get(key) == Some(value),
+            Requirement::NotEquals(key, value) => labels.get(key) != Some(value),
+            Requirement::Exists(key) => labels.contains_key(key),
+            Requirement::NotExists(key) => !labels.contains_key(key),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+}
+
+impl FromStr for LabelSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let requirements = s
+            .split(',')
+            .map(str::trim)
+            .filter(|term| !term.is_empty())
+            .map(parse_requirement)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { requirements })
+    }
+}
+
+fn parse_requirement(term: &str) -> Result<Requirement, String> {
+    let requirement = if let Some((key, value)) = term.split_once("!=") {
+        Requirement::NotEquals(key.trim().to_string(), value.trim().to_string())
+    } else if let Some((key, value)) = term.split_once("==").or_else(|| term.split_once('=')) {
+        Requirement::Equals(key.trim().to_string(), value.trim().to_string())
+    } else if let Some(key) = term.strip_prefix('!') {
+        Requirement::NotExists(key.trim().to_string())
+    } else {
+        Requirement::Exists(term.to_string())
+    };
+
+    let (key, value) = match &requirement {
+        Requirement::Equals(key, value) | Requirement::NotEquals(key, value) => (key, Some(value)),
+        Requirement::Exists(key) | Requirement::NotExists(key) => (key, None),
+    };
+
+    validate_key(key).map_err(|e| format!("Invalid selector '{}': {}", term, e))?;
+    if let Some(value) = value {
+        validate_label_value(value).map_err(|e| format!("Invalid selector '{}': {}", term, e))?;
+    }
+
+    Ok(requirement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> Labels {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_selector_matches() {
+        let instance = labels(&[("team", "bridge"), ("env", "ci")]);
+
+        let matches = |selector: &str| {
+            selector
+                .parse::<LabelSelector>()
+                .unwrap()
+                .matches(&instance)
+        };
+
+        assert!(matches("team=bridge"));
+        assert!(matches("team==bridge,env=ci"));
+        assert!(matches(" team = bridge , env "));
+        assert!(matches("env!=prod"));
+        assert!(matches("!owner"));
+        assert!(matches(""));
+        assert!(!matches("team=bridge,env=prod"));
+        assert!(!matches("owner"));
+        assert!(!matches("!team"));
+        assert!(!matches("env!=ci"));
+    }
+
+    #[test]
+    fn test_selector_rejects_invalid_terms() {
+        assert!("=bridge".parse::<LabelSelector>().is_err());
+        assert!("team=a b".parse::<LabelSelector>().is_err());
+        assert!("te am".parse::<LabelSelector>().is_err());
+    }
+
+    #[test]
+    fn test_validate_labels() {
+        assert!(validate_labels(&labels(&[("example.com/team", "bridge"), ("empty", "")])).is_ok());
+        assert!(validate_labels(&labels(&[("team", "a,b")])).is_err());
+        assert!(validate_labels(&labels(&[("", "x")])).is_err());
+        assert!(validate_key(&"k".repeat(254)).is_err());
+    }
+}
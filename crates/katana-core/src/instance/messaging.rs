@@ -0,0 +1,304 @@
+// L1 messaging
+//
+// Katana's messaging mode relays messages to and from a settlement chain,
+// configured by a JSON file passed with `--messaging`. The daemon writes that
+// file to the config drive without the private key, naming the secret that
+// holds it instead. The guest init completes the file with the secret it
+// received over vsock and writes it to `GUEST_MESSAGING_PATH`, off the drive.
+use super::config::{url_for_guest, GUEST_HOST_ADDR};
+use super::config_drive::validate_env_name;
+use crate::{HypervisorError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Messaging settings on the config drive, see `MessagingDriveConfig`
+pub const MESSAGING_FILE: &str = "messaging.json";
+
+/// Completed messaging config inside the guest, passed to Katana
+pub const GUEST_MESSAGING_PATH: &str = "/run/katana/messaging.json";
+
+/// Secret holding the messaging private key unless another is named
+pub const DEFAULT_MESSAGING_KEY_SECRET: &str = "MESSAGING_KEY";
+
+/// Seconds between polls of the settlement chain unless configured
+pub const DEFAULT_MESSAGING_INTERVAL: u64 = 2;
+
+/// Kind of settlement chain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessagingChain {
+    /// Ethereum or an Ethereum devnet such as Anvil
+    #[default]
+    Ethereum,
+    /// Another Starknet chain, e.g. a sibling Katana instance
+    Starknet,
+}
+
+impl MessagingChain {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessagingChain::Ethereum => "ethereum",
+            MessagingChain::Starknet => "starknet",
+        }
+    }
+}
+
+impl std::fmt::Display for MessagingChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for MessagingChain {
+    type Err = HypervisorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ethereum" => Ok(MessagingChain::Ethereum),
+            "starknet" => Ok(MessagingChain::Starknet),
+            other => Err(HypervisorError::InvalidConfig(format!(
+                "Invalid messaging chain '{}', expected ethereum or starknet",
+                other
+            ))),
+        }
+    }
+}
+
+/// L1 messaging settings of an instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessagingConfig {
+    #[serde(default)]
+    pub chain: MessagingChain,
+    /// Settlement chain RPC as given by the user; a loopback host means the
+    /// host. Exclusive with `l1_instance`
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    /// Sibling instance serving the settlement chain, reached through its
+    /// forwarded RPC port on the host
+    #[serde(default)]
+    pub l1_instance: Option<String>,
+    /// Settlement (core) contract address
+    pub contract_address: String,
+    /// Account sending messages to the settlement chain
+    pub sender_address: String,
+    /// Seconds between polls of the settlement chain
+    #[serde(default = "default_interval")]
+    pub interval_secs: u64,
+    /// Settlement chain block to start reading messages from
+    #[serde(default)]
+    pub from_block: u64,
+    /// Secret holding the sender's private key
+    #[serde(default = "default_key_secret")]
+    pub key_secret: String,
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_MESSAGING_INTERVAL
+}
+
+fn default_key_secret() -> String {
+    DEFAULT_MESSAGING_KEY_SECRET.to_string()
+}
+
+/// Contents of `MESSAGING_FILE`: Katana's messaging config, with the name of
+/// the secret in place of `private_key`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessagingDriveConfig {
+    pub chain: String,
+    pub rpc_url: String,
+    pub contract_address: String,
+    pub sender_address: String,
+    pub interval: u64,
+    pub from_block: u64,
+    pub private_key_secret: String,
+}
+
+impl MessagingConfig {
+    /// Check the settings that don't depend on other instances
+    pub fn validate(&self) -> Result<()> {
+        match (&self.rpc_url, &self.l1_instance) {
+            (Some(url), None) => {
+                url_for_guest(url, "messaging RPC")?;
+            }
+            (None, Some(_)) => {}
+            _ => {
+                return Err(HypervisorError::InvalidConfig(
+                    "Messaging needs exactly one of rpc_url and l1_instance".to_string(),
+                ))
+            }
+        }
+
+        for (what, address) in [
+            ("contract", &self.contract_address),
+            ("sender", &self.sender_address),
+        ] {
+            let hex = address.strip_prefix("0x").unwrap_or("");
+            if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(HypervisorError::InvalidConfig(format!(
+                    "Messaging {} address '{}' is not a 0x-prefixed hex address",
+                    what, address
+                )));
+            }
+        }
+
+        if self.interval_secs == 0 {
+            return Err(HypervisorError::InvalidConfig(
+                "Messaging interval must be at least 1 second".to_string(),
+            ));
+        }
+
+        validate_env_name(&self.key_secret)
+    }
+
+    /// Settlement chain RPC as reached from the guest. `l1_rpc_port` is the
+    /// host port forwarded to the `l1_instance`'s RPC.
+    pub fn guest_rpc_url(&self, l1_rpc_port: Option<u16>) -> Result<String> {
+        match (&self.rpc_url, l1_rpc_port) {
+            (Some(url), _) => url_for_guest(url, "messaging RPC"),
+            (None, Some(port)) => Ok(format!("http://{}:{}", GUEST_HOST_ADDR, port)),
+            (None, None) => Err(HypervisorError::InvalidConfig(
+                "Messaging has no settlement chain RPC".to_string(),
+            )),
+        }
+    }
+
+    /// Katana arguments enabling messaging with the completed config
+    pub fn katana_args(&self) -> Vec<String> {
+        vec![format!("--messaging={}", GUEST_MESSAGING_PATH)]
+    }
+}
+
+/// Write the messaging config, minus the private key, to the config drive
+pub fn write_messaging_config(
+    config_dir: &Path,
+    messaging: &MessagingConfig,
+    guest_rpc_url: &str,
+) -> Result<()> {
+    let config = MessagingDriveConfig {
+        chain: messaging.chain.to_string(),
+        rpc_url: guest_rpc_url.to_string(),
+        contract_address: messaging.contract_address.clone(),
+        sender_address: messaging.sender_address.clone(),
+        interval: messaging.interval_secs,
+        from_block: messaging.from_block,
+        private_key_secret: messaging.key_secret.clone(),
+    };
+
+    fs::create_dir_all(config_dir)?;
+    fs::write(
+        config_dir.join(MESSAGING_FILE),
+        serde_json::to_vec_pretty(&config)?,
+    )?;
+
+    Ok(())
+}
+
+/// Remove the messaging config from the config drive, if any, so an instance
+/// that no longer uses messaging doesn't keep stale settings
+pub fn remove_messaging_config(config_dir: &Path) -> Result<()> {
+    match fs::remove_file(config_dir.join(MESSAGING_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messaging() -> MessagingConfig {
+        MessagingConfig {
+            chain: MessagingChain::Ethereum,
+            rpc_url: Some("http://localhost:8545".to_string()),
+            l1_instance: None,
+            contract_address: "0x5FbDB2315678afecb367f032d93F642f64180aa3".to_string(),
+            sender_address: "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".to_string(),
+            interval_secs: 2,
+            from_block: 0,
+            key_secret: DEFAULT_MESSAGING_KEY_SECRET.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(messaging().validate().is_ok());
+
+        let both = MessagingConfig {
+            l1_instance: Some("l1".to_string()),
+            ..messaging()
+        };
+        assert!(both.validate().is_err());
+
+        let neither = MessagingConfig {
+            rpc_url: None,
+            ..messaging()
+        };
+        assert!(neither.validate().is_err());
+
+        let bad_address = MessagingConfig {
+            contract_address: "5FbDB2315678".to_string(),
+            ..messaging()
+        };
+        assert!(bad_address.validate().is_err());
+
+        let bad_interval = MessagingConfig {
+            interval_secs: 0,
+            ..messaging()
+        };
+        assert!(bad_interval.validate().is_err());
+    }
+
+    #[test]
+    fn test_guest_rpc_url() {
+        // A devnet on the host's loopback
+        assert_eq!(
+            messaging().guest_rpc_url(None).unwrap(),
+            "http://10.0.2.2:8545/"
+        );
+
+        // A sibling instance through its forwarded port
+        let sibling = MessagingConfig {
+            chain: MessagingChain::Starknet,
+            rpc_url: None,
+            l1_instance: Some("l2".to_string()),
+            ..messaging()
+        };
+        assert_eq!(
+            sibling.guest_rpc_url(Some(5051)).unwrap(),
+            "http://10.0.2.2:5051"
+        );
+        assert!(sibling.guest_rpc_url(None).is_err());
+    }
+
+    #[test]
+    fn test_katana_args() {
+        let config = crate::instance::InstanceConfig {
+            messaging: Some(messaging()),
+            ..Default::default()
+        };
+        assert!(config
+            .build_katana_args()
+            .contains(&"--messaging=/run/katana/messaging.json".to_string()));
+    }
+
+    #[test]
+    fn test_write_messaging_config() {
+        let dir = tempfile::tempdir().unwrap();
+
+        write_messaging_config(dir.path(), &messaging(), "http://10.0.2.2:8545/").unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.path().join(MESSAGING_FILE)).unwrap()).unwrap();
+        assert_eq!(written["chain"], "ethereum");
+        assert_eq!(written["rpc_url"], "http://10.0.2.2:8545/");
+        assert_eq!(written["interval"], 2);
+        assert_eq!(written["private_key_secret"], "MESSAGING_KEY");
+        assert!(written.get("private_key").is_none());
+
+        remove_messaging_config(dir.path()).unwrap();
+        assert!(!dir.path().join(MESSAGING_FILE).exists());
+        // Nothing to remove
+        remove_messaging_config(dir.path()).unwrap();
+    }
+}
@@ -1,11 +1,19 @@
 // Instance management module
 pub mod config;
+pub mod config_drive;
+pub mod labels;
+pub mod messaging;
 pub mod state;
 pub mod storage;
+pub mod template;
 
-pub use config::InstanceConfig;
-pub use state::{InstanceState, InstanceStatus};
+pub use config::{fork_provider_for_guest, Accelerator, InstanceConfig};
+pub use config_drive::{write_chain_spec, ChainSpecKind, CONFIG_DRIVE_DIR};
+pub use labels::{LabelSelector, Labels};
+pub use messaging::{MessagingChain, MessagingConfig};
+pub use state::{InstanceState, InstanceStatus, KATANA_LOG_FILE};
 pub use storage::StorageManager;
+pub use template::{InstanceTemplate, SevSnpSettings, TemplateConfig};
 
 use anyhow::Result;
 use std::path::PathBuf;
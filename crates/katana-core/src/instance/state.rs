@@ -78,11 +78,36 @@ pub struct InstanceState {
     pub vm_pid: Option<i32>,
     pub qmp_socket: Option<PathBuf>,
     pub serial_log: Option<PathBuf>,
+    /// Accelerator the VM was last launched with
+    pub accelerator: Option<super::Accelerator>,
+    /// Identifying metadata, matched by label selectors
+    #[serde(default)]
+    pub labels: super::Labels,
+    /// Non-identifying metadata, e.g. owner or purpose
+    #[serde(default)]
+    pub annotations: super::Labels,
+    /// Whether the guest got its secrets at the last start, if it has any.
+    /// Written by `StateDatabase::set_secrets_delivery`, not `save_instance`.
+    #[serde(default)]
+    pub secrets_delivery: Option<crate::secrets::SecretsDelivery>,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
+/// Katana's own log in the instance directory, separate from the serial console
+pub const KATANA_LOG_FILE: &str = "katana.log";
+
 impl InstanceState {
+    /// Path of Katana's stdout/stderr log
+    pub fn katana_log(&self) -> PathBuf {
+        self.config.data_dir.join(KATANA_LOG_FILE)
+    }
+
+    /// Directory exposed to the guest as its config drive
+    pub fn config_dir(&self) -> PathBuf {
+        self.config.data_dir.join(super::CONFIG_DRIVE_DIR)
+    }
+
     pub fn new(id: String, name: String, config: super::InstanceConfig) -> Self {
         let now = chrono::Utc::now().timestamp();
         Self {
@@ -93,6 +118,10 @@ impl InstanceState {
             vm_pid: None,
             qmp_socket: None,
             serial_log: None,
+            accelerator: None,
+            labels: Default::default(),
+            annotations: Default::default(),
+            secrets_delivery: None,
             created_at: now,
             updated_at: now,
         }
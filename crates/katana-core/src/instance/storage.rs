@@ -1,4 +1,7 @@
-use crate::{HypervisorError, Result};
+use crate::{
+    instance::{CONFIG_DRIVE_DIR, KATANA_LOG_FILE},
+    HypervisorError, Result,
+};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -46,7 +49,7 @@ impl StorageManager {
         );
 
         let status = Command::new("qemu-img")
-            .args(&[
+            .args([
                 "create",
                 "-f",
                 "qcow2",
@@ -108,7 +111,7 @@ impl StorageManager {
         // Connect qcow2 image to nbd device
         tracing::debug!("Connecting {} to {}", disk_image.display(), nbd_device);
         let status = Command::new("qemu-nbd")
-            .args(&[
+            .args([
                 "--connect",
                 &nbd_device,
                 disk_image.to_str().ok_or_else(|| {
@@ -132,14 +135,14 @@ impl StorageManager {
         // Format the nbd device
         tracing::debug!("Formatting {} with ext4", nbd_device);
         let format_result = Command::new("mkfs.ext4")
-            .args(&["-F", &nbd_device])
+            .args(["-F", &nbd_device])
             .output()
             .map_err(|e| HypervisorError::InvalidConfig(format!("Failed to run mkfs.ext4: {}", e)));
 
         // Disconnect nbd device (always do this, even if formatting failed)
         tracing::debug!("Disconnecting {}", nbd_device);
         let disconnect_result = Command::new("qemu-nbd")
-            .args(&["--disconnect", &nbd_device])
+            .args(["--disconnect", &nbd_device])
             .status();
 
         if let Err(e) = disconnect_result {
@@ -182,7 +185,7 @@ impl StorageManager {
 
         // Use qemu-img info to get actual disk usage
         let output = Command::new("qemu-img")
-            .args(&["info", "--output=json", disk_image.to_str().unwrap()])
+            .args(["info", "--output=json", disk_image.to_str().unwrap()])
             .output()
             .map_err(|e| {
                 HypervisorError::InvalidConfig(format!("Failed to run qemu-img: {}", e))
@@ -238,6 +241,8 @@ impl StorageManager {
             instance_dir: instance_dir.clone(),
             disk_image: instance_dir.join("katana-data.qcow2"),
             serial_log: instance_dir.join("serial.log"),
+            katana_log: instance_dir.join(KATANA_LOG_FILE),
+            config_dir: instance_dir.join(CONFIG_DRIVE_DIR),
             qmp_socket: instance_dir.join("qmp.sock"),
             pid_file: instance_dir.join("qemu.pid"),
         }
@@ -267,7 +272,7 @@ impl StorageManager {
 
         // Connect qcow2 to nbd device
         let status = Command::new("qemu-nbd")
-            .args(&["--connect", &nbd_device, paths.disk_image.to_str().unwrap()])
+            .args(["--connect", &nbd_device, paths.disk_image.to_str().unwrap()])
             .status()
             .map_err(|e| {
                 HypervisorError::InvalidConfig(format!("Failed to run qemu-nbd: {}", e))
@@ -287,12 +292,12 @@ impl StorageManager {
 
         // Mount the device
         let status = Command::new("mount")
-            .args(&["-t", "ext4", &nbd_device, mount_point.to_str().unwrap()])
+            .args(["-t", "ext4", &nbd_device, mount_point.to_str().unwrap()])
             .status()
             .map_err(|e| {
                 // Clean up nbd connection on mount failure
                 let _ = Command::new("qemu-nbd")
-                    .args(&["--disconnect", &nbd_device])
+                    .args(["--disconnect", &nbd_device])
                     .status();
                 HypervisorError::InvalidConfig(format!("Failed to mount disk: {}", e))
             })?;
@@ -300,7 +305,7 @@ impl StorageManager {
         if !status.success() {
             // Clean up nbd connection
             let _ = Command::new("qemu-nbd")
-                .args(&["--disconnect", &nbd_device])
+                .args(["--disconnect", &nbd_device])
                 .status();
             return Err(HypervisorError::InvalidConfig(
                 "Failed to mount disk image".into(),
@@ -346,13 +351,13 @@ impl StorageManager {
             );
             // Try force unmount
             let _ = Command::new("umount")
-                .args(&["-f", mount_point.to_str().unwrap()])
+                .args(["-f", mount_point.to_str().unwrap()])
                 .status();
         }
 
         // Disconnect nbd device
         let status = Command::new("qemu-nbd")
-            .args(&["--disconnect", nbd_device])
+            .args(["--disconnect", nbd_device])
             .status()
             .map_err(|e| {
                 HypervisorError::InvalidConfig(format!("Failed to disconnect nbd: {}", e))
@@ -390,7 +395,7 @@ impl StorageManager {
 
             // Check if device is in use by trying to read its size
             let output = Command::new("blockdev")
-                .args(&["--getsize64", &device])
+                .args(["--getsize64", &device])
                 .output();
 
             if let Ok(output) = output {
@@ -416,6 +421,8 @@ pub struct InstancePaths {
     pub instance_dir: PathBuf,
     pub disk_image: PathBuf,
     pub serial_log: PathBuf,
+    pub katana_log: PathBuf,
+    pub config_dir: PathBuf,
     pub qmp_socket: PathBuf,
     pub pid_file: PathBuf,
 }
@@ -99,7 +99,7 @@ mod tests {
             .create_instance_storage("test-instance-1", 100)
             .unwrap();
 
-        std::fs::write(instance_dir.join("test.txt"), &vec![0u8; 200]).unwrap();
+        std::fs::write(instance_dir.join("test.txt"), vec![0u8; 200]).unwrap();
 
         // Should error (200 bytes > 100 bytes quota)
         let result = storage.check_quota("test-instance-1", 100);
@@ -0,0 +1,231 @@
+use super::config::Accelerator;
+use serde::{Deserialize, Serialize};
+
+/// Named, reusable settings for creating instances
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    pub config: TemplateConfig,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl InstanceTemplate {
+    pub fn new(name: String, description: Option<String>, config: TemplateConfig) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Self {
+            name,
+            description,
+            config,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Skeleton of an `InstanceConfig`.
+///
+/// Only settings that make sense to share between instances are included;
+/// ports, CIDs and paths are always allocated per instance. Unset fields fall
+/// back to the create request or the daemon defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TemplateConfig {
+    // Resource limits
+    #[serde(default)]
+    pub vcpus: Option<u32>,
+    #[serde(default)]
+    pub memory_mb: Option<u64>,
+    #[serde(default)]
+    pub storage_bytes: Option<u64>,
+
+    // Virtualization
+    #[serde(default)]
+    pub accelerator: Option<Accelerator>,
+
+    // TEE configuration
+    #[serde(default)]
+    pub tee_mode: Option<bool>,
+    #[serde(default)]
+    pub vcpu_type: Option<String>,
+    #[serde(default)]
+    pub sev_snp: Option<SevSnpSettings>,
+
+    // Katana-specific configuration
+    #[serde(default)]
+    pub chain_id: Option<String>,
+    #[serde(default)]
+    pub dev_mode: Option<bool>,
+    #[serde(default)]
+    pub block_time: Option<u64>,
+    #[serde(default)]
+    pub accounts: Option<u16>,
+    #[serde(default)]
+    pub disable_fee: Option<bool>,
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+
+    // Lifecycle
+    /// Time to live in seconds, counted from creation
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    #[serde(default)]
+    pub idle_timeout_secs: Option<u64>,
+}
+
+impl TemplateConfig {
+    /// Layer `overrides` on top of this template.
+    ///
+    /// Fields set in `overrides` win; extra args are appended so that later
+    /// Katana flags take precedence.
+    pub fn overlay(&self, overrides: TemplateConfig) -> TemplateConfig {
+        let mut extra_args = self.extra_args.clone();
+        extra_args.extend(overrides.extra_args);
+
+        TemplateConfig {
+            vcpus: overrides.vcpus.or(self.vcpus),
+            memory_mb: overrides.memory_mb.or(self.memory_mb),
+            storage_bytes: overrides.storage_bytes.or(self.storage_bytes),
+            accelerator: overrides.accelerator.or(self.accelerator),
+            tee_mode: overrides.tee_mode.or(self.tee_mode),
+            vcpu_type: overrides.vcpu_type.or_else(|| self.vcpu_type.clone()),
+            sev_snp: match (&self.sev_snp, overrides.sev_snp) {
+                (Some(base), Some(overrides)) => Some(base.overlay(overrides)),
+                (base, overrides) => overrides.or_else(|| base.clone()),
+            },
+            chain_id: overrides.chain_id.or_else(|| self.chain_id.clone()),
+            dev_mode: overrides.dev_mode.or(self.dev_mode),
+            block_time: overrides.block_time.or(self.block_time),
+            accounts: overrides.accounts.or(self.accounts),
+            disable_fee: overrides.disable_fee.or(self.disable_fee),
+            extra_args,
+            ttl_secs: overrides.ttl_secs.or(self.ttl_secs),
+            idle_timeout_secs: overrides.idle_timeout_secs.or(self.idle_timeout_secs),
+        }
+    }
+}
+
+/// SEV-SNP launch settings held by a template or given at create time.
+/// Unset fields fall back to values probed from the host.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SevSnpSettings {
+    #[serde(default)]
+    pub cbitpos: Option<u8>,
+    #[serde(default)]
+    pub reduced_phys_bits: Option<u8>,
+    #[serde(default)]
+    pub policy: Option<u64>,
+    /// Base64-encoded 32-byte host data
+    #[serde(default)]
+    pub host_data: Option<String>,
+    /// Base64-encoded ID block
+    #[serde(default)]
+    pub id_block: Option<String>,
+    /// Base64-encoded ID authentication information structure
+    #[serde(default)]
+    pub id_auth: Option<String>,
+    #[serde(default)]
+    pub kernel_hashes: Option<bool>,
+}
+
+impl SevSnpSettings {
+    /// Layer `overrides` on top of these settings, field by field
+    pub fn overlay(&self, overrides: SevSnpSettings) -> SevSnpSettings {
+        SevSnpSettings {
+            cbitpos: overrides.cbitpos.or(self.cbitpos),
+            reduced_phys_bits: overrides.reduced_phys_bits.or(self.reduced_phys_bits),
+            policy: overrides.policy.or(self.policy),
+            host_data: overrides.host_data.or_else(|| self.host_data.clone()),
+            id_block: overrides.id_block.or_else(|| self.id_block.clone()),
+            id_auth: overrides.id_auth.or_else(|| self.id_auth.clone()),
+            kernel_hashes: overrides.kernel_hashes.or(self.kernel_hashes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay() {
+        let template = TemplateConfig {
+            vcpus: Some(8),
+            memory_mb: Some(16384),
+            tee_mode: Some(true),
+            vcpu_type: Some("EPYC-v4".to_string()),
+            block_time: Some(1000),
+            disable_fee: Some(true),
+            extra_args: vec!["--invoke-max-steps=1000000".to_string()],
+            ttl_secs: Some(7200),
+            ..Default::default()
+        };
+
+        let merged = template.overlay(TemplateConfig {
+            vcpus: Some(2),
+            disable_fee: Some(false),
+            chain_id: Some("KATANA_TEST".to_string()),
+            extra_args: vec!["--http.cors_origins=*".to_string()],
+            ..Default::default()
+        });
+
+        assert_eq!(merged.vcpus, Some(2));
+        assert_eq!(merged.memory_mb, Some(16384));
+        assert_eq!(merged.tee_mode, Some(true));
+        assert_eq!(merged.vcpu_type.as_deref(), Some("EPYC-v4"));
+        assert_eq!(merged.block_time, Some(1000));
+        assert_eq!(merged.disable_fee, Some(false));
+        assert_eq!(merged.chain_id.as_deref(), Some("KATANA_TEST"));
+        assert_eq!(merged.storage_bytes, None);
+        assert_eq!(merged.ttl_secs, Some(7200));
+        assert_eq!(
+            merged.extra_args,
+            vec!["--invoke-max-steps=1000000", "--http.cors_origins=*"]
+        );
+    }
+
+    #[test]
+    fn test_overlay_empty_template() {
+        let overrides = TemplateConfig {
+            vcpus: Some(4),
+            accelerator: Some(Accelerator::Tcg),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            TemplateConfig::default().overlay(overrides.clone()),
+            overrides
+        );
+    }
+
+    #[test]
+    fn test_overlay_sev_snp() {
+        let template = TemplateConfig {
+            tee_mode: Some(true),
+            sev_snp: Some(SevSnpSettings {
+                policy: Some(0x30000),
+                host_data: Some("aG9zdA==".to_string()),
+                kernel_hashes: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        // Request settings win field by field
+        let merged = template.overlay(TemplateConfig {
+            sev_snp: Some(SevSnpSettings {
+                policy: Some(0x30001),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let sev_snp = merged.sev_snp.unwrap();
+        assert_eq!(sev_snp.policy, Some(0x30001));
+        assert_eq!(sev_snp.host_data.as_deref(), Some("aG9zdA=="));
+        assert_eq!(sev_snp.kernel_hashes, Some(true));
+
+        // A template's settings apply when the request has none
+        let merged = template.overlay(TemplateConfig::default());
+        assert_eq!(merged.sev_snp, template.sev_snp);
+    }
+}
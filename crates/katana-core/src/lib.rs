@@ -1,8 +1,14 @@
 pub mod error;
+pub mod host;
 pub mod instance;
+pub mod logs;
 pub mod port;
 pub mod qemu;
+pub mod rpc;
+pub mod secrets;
 pub mod state;
+pub mod stats;
 pub mod tee;
+pub mod vsock;
 
 pub use error::{HypervisorError, Result};
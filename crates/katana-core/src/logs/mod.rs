@@ -0,0 +1,8 @@
+// Serial log management module
+pub mod parser;
+pub mod reader;
+pub mod rotation;
+
+pub use parser::{level_severity, parse_line, LogRecord};
+pub use reader::{read_page, LogFilter, LogPage};
+pub use rotation::LogRotationPolicy;
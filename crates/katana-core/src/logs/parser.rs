@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+/// Tracing levels from least to most severe
+pub const LEVELS: &[&str] = &["TRACE", "DEBUG", "INFO", "WARN", "ERROR"];
+
+/// Severity of a level name (case-insensitive), higher is more severe
+pub fn level_severity(level: &str) -> Option<usize> {
+    LEVELS.iter().position(|l| l.eq_ignore_ascii_case(level))
+}
+
+/// A structured line of Katana's tracing output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogRecord {
+    /// RFC 3339 timestamp as printed by Katana
+    pub timestamp: String,
+    /// TRACE, DEBUG, INFO, WARN or ERROR
+    pub level: String,
+    /// Module that emitted the event, e.g. `katana::core::backend`
+    pub target: String,
+    pub message: String,
+}
+
+/// Parse a line of Katana's default tracing format:
+///
+/// ```text
+/// 2025-01-10T12:34:56.789012Z  INFO katana::core::backend: Block mined. block_number=1
+/// ```
+///
+/// ANSI colours and a leading list of spans (`rpc{method=..}: `) are
+/// tolerated. Returns `None` for anything else, such as kernel and init
+/// output on the serial console.
+pub fn parse_line(line: &str) -> Option<LogRecord> {
+    let line = strip_ansi(line);
+    let line = line.trim_end();
+
+    let (timestamp, rest) = line.split_once(' ')?;
+    chrono::DateTime::parse_from_rfc3339(timestamp).ok()?;
+
+    let rest = rest.trim_start();
+    let (level, rest) = rest.split_once(' ')?;
+    if !LEVELS.contains(&level) {
+        return None;
+    }
+
+    let (mut target, mut message) = rest.trim_start().split_once(": ")?;
+    if target.contains('{') {
+        // Spans come before the target
+        (target, message) = message.split_once(": ")?;
+    }
+    if target.is_empty() || target.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some(LogRecord {
+        timestamp: timestamp.to_string(),
+        level: level.to_string(),
+        target: target.to_string(),
+        message: message.to_string(),
+    })
+}
+
+/// Remove ANSI escape sequences (`ESC [ ... letter`)
+fn strip_ansi(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip up to and including the final byte of the sequence
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plain_line() {
+        let record = parse_line(
+            "2025-01-10T12:34:56.789012Z  INFO katana::core::backend: Block mined. block_number=1",
+        )
+        .unwrap();
+
+        assert_eq!(record.timestamp, "2025-01-10T12:34:56.789012Z");
+        assert_eq!(record.level, "INFO");
+        assert_eq!(record.target, "katana::core::backend");
+        assert_eq!(record.message, "Block mined. block_number=1");
+    }
+
+    #[test]
+    fn test_parse_coloured_line_with_spans() {
+        let line = "\x1b[2m2025-01-10T12:34:56.789Z\x1b[0m \x1b[33m WARN\x1b[0m rpc{method=\"starknet_call\"}: katana_rpc: Slow request\r";
+        let record = parse_line(line).unwrap();
+
+        assert_eq!(record.level, "WARN");
+        assert_eq!(record.target, "katana_rpc");
+        assert_eq!(record.message, "Slow request");
+    }
+
+    #[test]
+    fn test_parse_rejects_console_output() {
+        assert!(parse_line("[    0.000000] Linux version 6.1.0").is_none());
+        assert!(parse_line("Starting katana...").is_none());
+        assert!(parse_line("2025-01-10T12:34:56Z NOTICE something: happened").is_none());
+        assert!(parse_line("").is_none());
+    }
+}
@@ -0,0 +1,289 @@
+use super::parser::{level_severity, parse_line};
+use crate::Result;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// How much of the log is read at a time when scanning backwards
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Criteria for selecting log lines.
+///
+/// Time and level filters only apply to Katana tracing output; other console
+/// output never matches them. Logs are assumed to be in chronological order.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Matched against the raw line
+    pub grep: Option<Regex>,
+    /// Minimum severity, see `level_severity`
+    pub min_level: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Verdict {
+    Match,
+    Skip,
+    /// Older than `since`, and so is everything before it
+    TooOld,
+}
+
+impl LogFilter {
+    pub fn matches(&self, line: &str) -> bool {
+        self.check(line) == Verdict::Match
+    }
+
+    fn check(&self, line: &str) -> Verdict {
+        if self.since.is_some() || self.until.is_some() || self.min_level.is_some() {
+            let Some(record) = parse_line(line) else {
+                return Verdict::Skip;
+            };
+            let Ok(timestamp) = DateTime::parse_from_rfc3339(&record.timestamp) else {
+                return Verdict::Skip;
+            };
+            let timestamp = timestamp.with_timezone(&Utc);
+
+            if self.since.is_some_and(|since| timestamp < since) {
+                return Verdict::TooOld;
+            }
+            if self.until.is_some_and(|until| timestamp > until) {
+                return Verdict::Skip;
+            }
+            if self
+                .min_level
+                .is_some_and(|min| level_severity(&record.level) < Some(min))
+            {
+                return Verdict::Skip;
+            }
+        }
+
+        if self.grep.as_ref().is_some_and(|re| !re.is_match(line)) {
+            return Verdict::Skip;
+        }
+
+        Verdict::Match
+    }
+}
+
+/// A window of matching lines read from the end of a log
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogPage {
+    /// Matching lines, oldest first
+    pub lines: Vec<String>,
+    /// Offset of the earliest line examined; pass it as `before` to get the
+    /// previous page
+    pub start_offset: u64,
+    /// Offset just past the last line examined
+    pub end_offset: u64,
+    /// Lines examined, matching or not
+    pub lines_scanned: usize,
+    /// Whether lines before `start_offset` could still match
+    pub has_more: bool,
+}
+
+/// Read up to `limit` lines matching `filter` that end at or before byte
+/// offset `before` (the end of the file by default).
+///
+/// The log is scanned backwards in chunks, so the cost depends on how far
+/// back the matches are rather than on the size of the file.
+pub fn read_page(
+    path: &Path,
+    filter: &LogFilter,
+    limit: usize,
+    before: Option<u64>,
+) -> Result<LogPage> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let end = before.map_or(size, |before| before.min(size));
+
+    let mut page = LogPage {
+        start_offset: end,
+        end_offset: end,
+        has_more: end > 0,
+        ..Default::default()
+    };
+    if limit == 0 {
+        return Ok(page);
+    }
+
+    let mut pos = end;
+    // Start of a line that continues past `pos`, read in an earlier chunk
+    let mut carry: Vec<u8> = Vec::new();
+    // A trailing newline doesn't start another line
+    let mut at_end = true;
+
+    while pos > 0 {
+        let read_size = CHUNK_SIZE.min(pos);
+        pos -= read_size;
+
+        let mut buffer = vec![0u8; read_size as usize];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut buffer)?;
+        buffer.extend_from_slice(&carry);
+
+        let mut line_end = buffer.len();
+        for i in (0..buffer.len()).rev() {
+            if buffer[i] != b'\n' {
+                continue;
+            }
+
+            let skip = at_end && i + 1 == line_end;
+            at_end = false;
+            if !skip
+                && visit(
+                    &mut page,
+                    filter,
+                    limit,
+                    &buffer[i + 1..line_end],
+                    pos + i as u64 + 1,
+                )
+            {
+                return Ok(finish(page));
+            }
+            line_end = i;
+        }
+
+        carry = buffer[..line_end].to_vec();
+    }
+
+    // The first line of the file has no newline before it
+    if !carry.is_empty() {
+        visit(&mut page, filter, limit, &carry, 0);
+    }
+    page.has_more = false;
+
+    Ok(finish(page))
+}
+
+/// Examine one line. Returns true once scanning should stop.
+fn visit(page: &mut LogPage, filter: &LogFilter, limit: usize, bytes: &[u8], offset: u64) -> bool {
+    let line = String::from_utf8_lossy(bytes);
+    let line = line.strip_suffix('\r').unwrap_or(&line);
+
+    page.lines_scanned += 1;
+
+    match filter.check(line) {
+        Verdict::Match => page.lines.push(line.to_string()),
+        Verdict::Skip => {}
+        Verdict::TooOld => {
+            page.has_more = false;
+            return true;
+        }
+    }
+
+    page.start_offset = offset;
+
+    if page.lines.len() >= limit {
+        page.has_more = offset > 0;
+        return true;
+    }
+
+    false
+}
+
+fn finish(mut page: LogPage) -> LogPage {
+    page.lines.reverse();
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write_log(dir: &TempDir, content: &str) -> std::path::PathBuf {
+        let path = dir.path().join("serial.log");
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn katana_line(minute: u32, level: &str, message: &str) -> String {
+        format!(
+            "2025-01-10T12:{:02}:00.000000Z {:>5} katana::core: {}\n",
+            minute, level, message
+        )
+    }
+
+    #[test]
+    fn test_tail_and_pagination() {
+        let dir = TempDir::new().unwrap();
+        let content: String = (1..=5).map(|i| format!("line {}\n", i)).collect();
+        let path = write_log(&dir, &content);
+
+        let page = read_page(&path, &LogFilter::default(), 2, None).unwrap();
+        assert_eq!(page.lines, vec!["line 4", "line 5"]);
+        assert_eq!(page.start_offset, 21);
+        assert_eq!(page.end_offset, content.len() as u64);
+        assert!(page.has_more);
+
+        let page = read_page(&path, &LogFilter::default(), 2, Some(page.start_offset)).unwrap();
+        assert_eq!(page.lines, vec!["line 2", "line 3"]);
+        assert!(page.has_more);
+
+        let page = read_page(&path, &LogFilter::default(), 2, Some(page.start_offset)).unwrap();
+        assert_eq!(page.lines, vec!["line 1"]);
+        assert_eq!(page.start_offset, 0);
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn test_lines_spanning_chunks() {
+        let dir = TempDir::new().unwrap();
+        let long_line = "x".repeat(CHUNK_SIZE as usize + 10);
+        let path = write_log(&dir, &format!("first\r\n{}\nlast", long_line));
+
+        let page = read_page(&path, &LogFilter::default(), 10, None).unwrap();
+        assert_eq!(
+            page.lines,
+            vec!["first".to_string(), long_line, "last".to_string()]
+        );
+        assert_eq!(page.lines_scanned, 3);
+    }
+
+    #[test]
+    fn test_filters() {
+        let dir = TempDir::new().unwrap();
+        let content = [
+            katana_line(0, "INFO", "Starting node"),
+            "[    1.000000] kernel message\n".to_string(),
+            katana_line(5, "ERROR", "Mining failed"),
+            katana_line(10, "INFO", "Block mined"),
+            katana_line(15, "WARN", "Slow block"),
+        ]
+        .concat();
+        let path = write_log(&dir, &content);
+        let at = |minute| format!("2025-01-10T12:{:02}:00Z", minute).parse().unwrap();
+
+        let filter = LogFilter {
+            min_level: level_severity("warn"),
+            ..Default::default()
+        };
+        let page = read_page(&path, &filter, 10, None).unwrap();
+        assert_eq!(page.lines.len(), 2);
+        assert!(page.lines[0].contains("Mining failed"));
+
+        let filter = LogFilter {
+            grep: Some(Regex::new("(?i)block").unwrap()),
+            ..Default::default()
+        };
+        let page = read_page(&path, &filter, 10, None).unwrap();
+        assert_eq!(page.lines.len(), 2);
+
+        // Scanning stops at the first line before `since`
+        let filter = LogFilter {
+            since: Some(at(5)),
+            until: Some(at(10)),
+            ..Default::default()
+        };
+        let page = read_page(&path, &filter, 10, None).unwrap();
+        assert_eq!(page.lines.len(), 2);
+        assert!(page.lines[0].contains("Mining failed"));
+        assert!(page.lines[1].contains("Block mined"));
+        assert_eq!(page.lines_scanned, 5);
+        assert!(!page.has_more);
+    }
+}
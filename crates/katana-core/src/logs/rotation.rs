@@ -0,0 +1,200 @@
+use crate::Result;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Size-based rotation and retention for serial logs.
+///
+/// Rotation uses copy-truncate: QEMU keeps the log open in append mode, so
+/// the live file is copied to `serial.log.1` and truncated in place, and new
+/// output continues at the start of the emptied file. Older copies shift to
+/// `.2`, `.3`, ... and anything beyond `keep` is deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRotationPolicy {
+    /// Rotate once the live log reaches this size
+    pub max_bytes: u64,
+    /// Number of rotated logs to keep; 0 only truncates
+    pub keep: usize,
+    /// Gzip rotated logs (`serial.log.1.gz`)
+    pub compress: bool,
+}
+
+impl Default for LogRotationPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024 * 1024,
+            keep: 5,
+            compress: true,
+        }
+    }
+}
+
+impl LogRotationPolicy {
+    /// Rotate `path` if it has reached `max_bytes`.
+    ///
+    /// Returns whether the log was rotated. A missing log is not an error.
+    pub fn rotate_if_needed(&self, path: &Path) -> Result<bool> {
+        let size = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+
+        if size < self.max_bytes {
+            return Ok(false);
+        }
+
+        self.rotate(path)?;
+        Ok(true)
+    }
+
+    /// Rotate `path` unconditionally
+    pub fn rotate(&self, path: &Path) -> Result<()> {
+        if self.keep > 0 {
+            // Drop the oldest copy, then shift the rest up by one
+            remove_if_exists(&self.rotated_path(path, self.keep))?;
+            for index in (1..self.keep).rev() {
+                let from = self.rotated_path(path, index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(path, index + 1))?;
+                }
+            }
+
+            let target = self.rotated_path(path, 1);
+            if self.compress {
+                let mut encoder = GzEncoder::new(File::create(&target)?, Compression::default());
+                io::copy(&mut File::open(path)?, &mut encoder)?;
+                encoder.finish()?;
+            } else {
+                fs::copy(path, &target)?;
+            }
+        }
+
+        OpenOptions::new().write(true).open(path)?.set_len(0)?;
+
+        tracing::debug!("Rotated log {}", path.display());
+        Ok(())
+    }
+
+    /// Path of the `index`th rotated copy of `path`, starting at 1
+    pub fn rotated_path(&self, path: &Path, index: usize) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        if self.compress {
+            name.push(".gz");
+        }
+        PathBuf::from(name)
+    }
+
+    /// Open the `index`th rotated copy of `path` for reading, decompressing
+    /// it if needed. Returns `None` if there is no such copy.
+    pub fn open_rotated(&self, path: &Path, index: usize) -> Result<Option<Box<dyn BufRead>>> {
+        let file = match File::open(self.rotated_path(path, index)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Some(if self.compress {
+            Box::new(BufReader::new(GzDecoder::new(file)))
+        } else {
+            Box::new(BufReader::new(file))
+        }))
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    fn policy(keep: usize, compress: bool) -> LogRotationPolicy {
+        LogRotationPolicy {
+            max_bytes: 10,
+            keep,
+            compress,
+        }
+    }
+
+    #[test]
+    fn test_rotate_below_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("serial.log");
+        fs::write(&log, "short").unwrap();
+
+        assert!(!policy(2, false).rotate_if_needed(&log).unwrap());
+        assert!(!policy(2, false)
+            .rotate_if_needed(&temp_dir.path().join("missing.log"))
+            .unwrap());
+        assert_eq!(fs::read_to_string(&log).unwrap(), "short");
+    }
+
+    #[test]
+    fn test_rotate_keeps_newest() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("serial.log");
+        let policy = policy(2, false);
+
+        for content in ["first line\n", "second line\n", "third line\n"] {
+            fs::write(&log, content).unwrap();
+            assert!(policy.rotate_if_needed(&log).unwrap());
+        }
+
+        assert_eq!(fs::read_to_string(&log).unwrap(), "");
+        assert_eq!(
+            fs::read_to_string(policy.rotated_path(&log, 1)).unwrap(),
+            "third line\n"
+        );
+        assert_eq!(
+            fs::read_to_string(policy.rotated_path(&log, 2)).unwrap(),
+            "second line\n"
+        );
+        assert!(!policy.rotated_path(&log, 3).exists());
+    }
+
+    #[test]
+    fn test_rotate_compressed() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("serial.log");
+        fs::write(&log, "compressed content\n").unwrap();
+
+        let policy = policy(1, true);
+        policy.rotate(&log).unwrap();
+
+        let rotated = policy.rotated_path(&log, 1);
+        assert_eq!(rotated, temp_dir.path().join("serial.log.1.gz"));
+
+        let mut content = String::new();
+        GzDecoder::new(File::open(rotated).unwrap())
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "compressed content\n");
+
+        let mut reader = policy.open_rotated(&log, 1).unwrap().unwrap();
+        let mut content = String::new();
+        reader.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "compressed content\n");
+        assert!(policy.open_rotated(&log, 2).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rotate_without_retention() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("serial.log");
+        fs::write(&log, "discarded content\n").unwrap();
+
+        policy(0, false).rotate(&log).unwrap();
+
+        assert_eq!(fs::read_to_string(&log).unwrap(), "");
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,137 @@
+// Detect client traffic on forwarded ports from the host TCP tables
+//
+// Instances use QEMU user networking, which exposes no traffic counters, so
+// RPC activity is inferred from host sockets on the forwarded port.
+use std::fs;
+use std::net::Ipv4Addr;
+
+/// Loopback address the daemon's own RPC probes connect from, so that they
+/// aren't mistaken for client activity
+pub const PROBE_ADDRESS: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 2);
+
+const TCP_TABLES: &[&str] = &["/proc/net/tcp", "/proc/net/tcp6"];
+
+const TCP_ESTABLISHED: u8 = 0x01;
+/// Closed connections linger in TIME_WAIT for about a minute
+const TCP_TIME_WAIT: u8 = 0x06;
+
+/// Check whether a port has open connections or ones closed within the last
+/// minute. Listening sockets and connections from `PROBE_ADDRESS` don't count.
+pub fn port_has_recent_connections(port: u16) -> bool {
+    TCP_TABLES.iter().any(|table| {
+        fs::read_to_string(table)
+            .map(|contents| table_has_connections(&contents, port))
+            .unwrap_or(false)
+    })
+}
+
+/// Scan a `/proc/net/tcp` style table for connections on `port`.
+///
+/// Either end may match: the forwarded side holds the port locally, while the
+/// client side of a closed loopback connection holds it as the remote port.
+fn table_has_connections(contents: &str, port: u16) -> bool {
+    contents.lines().skip(1).any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 4 {
+            return false;
+        }
+
+        let state = u8::from_str_radix(fields[3], 16).unwrap_or(0);
+        if state != TCP_ESTABLISHED && state != TCP_TIME_WAIT {
+            return false;
+        }
+
+        if is_probe_address(fields[1]) || is_probe_address(fields[2]) {
+            return false;
+        }
+
+        [fields[1], fields[2]]
+            .iter()
+            .any(|address| address_port(address) == Some(port))
+    })
+}
+
+/// Extract the port from an "ADDR:PORT" hex pair
+fn address_port(address: &str) -> Option<u16> {
+    let (_, port) = address.rsplit_once(':')?;
+    u16::from_str_radix(port, 16).ok()
+}
+
+/// Check whether an "ADDR:PORT" hex pair is on `PROBE_ADDRESS`, either as an
+/// IPv4 address or an IPv4-mapped IPv6 one. The kernel prints addresses as
+/// 32-bit words in host byte order.
+fn is_probe_address(address: &str) -> bool {
+    let Some((ip, _)) = address.rsplit_once(':') else {
+        return false;
+    };
+    let probe = format!("{:08X}", u32::from_ne_bytes(PROBE_ADDRESS.octets()));
+    let mapped = format!(
+        "0000000000000000{:08X}{}",
+        u32::from_ne_bytes([0, 0, 0xff, 0xff]),
+        probe
+    );
+
+    ip.eq_ignore_ascii_case(&probe) || ip.eq_ignore_ascii_case(&mapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n";
+
+    #[test]
+    fn test_listening_only() {
+        // 0x13BA = 5050, state 0A = LISTEN
+        let table = format!(
+            "{}   0: 00000000:13BA 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 1 1\n",
+            HEADER
+        );
+        assert!(!table_has_connections(&table, 5050));
+    }
+
+    #[test]
+    fn test_established() {
+        let table = format!(
+            "{}   1: 0100007F:13BA 0100007F:D431 01 00000000:00000000 00:00000000 00000000  1000        0 2 1\n",
+            HEADER
+        );
+        assert!(table_has_connections(&table, 5050));
+        assert!(!table_has_connections(&table, 5051));
+    }
+
+    #[test]
+    fn test_time_wait_client_side() {
+        let table = format!(
+            "{}   2: 0100007F:D431 0100007F:13BA 06 00000000:00000000 03:00000A3C 00000000     0        0 0 3\n",
+            HEADER
+        );
+        assert!(table_has_connections(&table, 5050));
+    }
+
+    #[test]
+    fn test_probe_connections_ignored() {
+        // 0x0200007F = 127.0.0.2, both ends of a probe and a lingering one
+        let table = format!(
+            "{}   1: 0100007F:13BA 0200007F:D431 01 00000000:00000000 00:00000000 00000000  1000        0 2 1\n   \
+             2: 0200007F:D432 0100007F:13BA 06 00000000:00000000 03:00000A3C 00000000     0        0 0 3\n",
+            HEADER
+        );
+        assert!(!table_has_connections(&table, 5050));
+
+        let table = format!(
+            "{}   0: 0000000000000000FFFF00000100007F:13BA 0000000000000000FFFF00000200007F:D433 01 00000000:00000000 00:00000000 00000000  1000        0 3 1\n",
+            HEADER
+        );
+        assert!(!table_has_connections(&table, 5050));
+    }
+
+    #[test]
+    fn test_ipv6() {
+        let table = format!(
+            "{}   0: 00000000000000000000000001000000:13BA 00000000000000000000000001000000:D432 01 00000000:00000000 00:00000000 00000000  1000        0 3 1\n",
+            HEADER
+        );
+        assert!(table_has_connections(&table, 5050));
+    }
+}
@@ -16,10 +16,9 @@ impl PortAllocator {
         let allocated_ports = self.db.get_allocated_ports()?;
 
         // Find the next available port
-        let mut candidate = base_port;
         let max_attempts = 1000;
 
-        for _ in 0..max_attempts {
+        for candidate in (base_port..=u16::MAX).take(max_attempts) {
             // Check if port is not in database
             if !allocated_ports.contains(&candidate) {
                 // Check if port is actually available on the system
@@ -27,7 +26,6 @@ impl PortAllocator {
                     return Ok(candidate);
                 }
             }
-            candidate += 1;
         }
 
         Err(HypervisorError::NoPortsAvailable)
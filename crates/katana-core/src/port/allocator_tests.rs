@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
     use super::super::PortAllocator;
-    use crate::instance::{InstanceConfig, InstanceState};
+    use crate::instance::{Accelerator, InstanceConfig, InstanceState};
     use crate::state::StateDatabase;
     use tempfile::TempDir;
 
@@ -20,9 +20,13 @@ mod tests {
             storage_bytes: 10 * 1024 * 1024 * 1024,
             rpc_port: 5050,
             metrics_port: None,
+            vsock_cid: None,
+            accelerator: Accelerator::Auto,
+            balloon: false,
             tee_mode: false,
             vcpu_type: "host".to_string(),
             expected_measurement: None,
+            sev_snp: None,
             kernel_path: "/tmp/vmlinuz".into(),
             initrd_path: "/tmp/initrd.img".into(),
             ovmf_path: None,
@@ -34,6 +38,14 @@ mod tests {
             accounts: Some(10),
             disable_fee: true,
             extra_args: vec![],
+            fork_provider: None,
+            fork_block: None,
+            messaging: None,
+            chain_spec: None,
+            env: Default::default(),
+            config_drive_args: false,
+            expires_at: None,
+            idle_timeout_secs: None,
         };
 
         // Use unique ID based on name for testing
@@ -157,8 +169,8 @@ mod tests {
         }
 
         // All ports should be unique and sequential
-        for i in 0..10 {
-            assert_eq!(ports[i], base_port + i as u16);
+        for (i, port) in ports.iter().enumerate() {
+            assert_eq!(*port, base_port + i as u16);
         }
     }
 
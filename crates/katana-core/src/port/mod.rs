@@ -1,4 +1,5 @@
 // Port allocation module
+pub mod activity;
 pub mod allocator;
 
 pub use allocator::PortAllocator;
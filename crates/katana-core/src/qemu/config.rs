@@ -1,5 +1,14 @@
+use crate::instance::config_drive::{GUEST_CONFIG_DIR, KATANA_CONFIG_FILE};
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+/// Name of the virtio-serial port the guest writes Katana's output to
+/// (`/dev/virtio-ports/org.katana.log` inside the guest)
+pub const KATANA_LOG_PORT: &str = "org.katana.log";
+
+/// Kernel parameters shared by all instances
+const BASE_KERNEL_CMDLINE: &str = "console=ttyS0 loglevel=4";
+
 #[derive(Debug, Clone)]
 pub struct QemuConfig {
     // Resource limits
@@ -18,12 +27,19 @@ pub struct QemuConfig {
     // Network
     pub rpc_port: u16,
 
+    /// Guest CID for a vhost-vsock device, if any
+    pub vsock_cid: Option<u32>,
+
     // Storage
     pub disk_image: Option<PathBuf>,
+    /// Directory attached read-only as the guest's config drive
+    pub config_drive: Option<PathBuf>,
 
     // Paths
     pub qmp_socket: PathBuf,
     pub serial_log: PathBuf,
+    /// Katana's stdout/stderr, written by the guest to the `KATANA_LOG_PORT` port
+    pub katana_log: PathBuf,
     pub pid_file: PathBuf,
 
     // TEE configuration
@@ -31,23 +47,60 @@ pub struct QemuConfig {
 
     // Enable KVM acceleration
     pub enable_kvm: bool,
+
+    /// Attach a virtio-balloon device (not for SEV-SNP guests)
+    pub balloon: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SevSnpConfig {
     pub cbitpos: u8,
     pub reduced_phys_bits: u8,
     pub vcpu_type: String,
+
+    /// Guest policy. QEMU's default (0x30000) is used when unset.
+    #[serde(default)]
+    pub policy: Option<u64>,
+
+    /// Base64-encoded 32 bytes of host data, reflected in attestation reports
+    #[serde(default)]
+    pub host_data: Option<String>,
+
+    /// Base64-encoded 96-byte ID block
+    #[serde(default)]
+    pub id_block: Option<String>,
+
+    /// Base64-encoded 4096-byte ID authentication information structure
+    #[serde(default)]
+    pub id_auth: Option<String>,
+
+    /// Include kernel, initrd and cmdline hashes in the launch measurement
+    #[serde(default)]
+    pub kernel_hashes: bool,
 }
 
 impl QemuConfig {
+    /// CPU model passed to `-cpu`.
+    ///
+    /// `host` passthrough only works under KVM, so TCG uses `max` instead.
+    pub fn cpu_model(&self) -> &str {
+        if !self.enable_kvm && self.cpu_type == "host" {
+            "max"
+        } else {
+            &self.cpu_type
+        }
+    }
+
     /// Build QEMU command line arguments
     pub fn to_qemu_args(&self) -> Vec<String> {
         let mut args = vec!["qemu-system-x86_64".to_string()];
 
-        // Enable KVM if requested
+        // Enable KVM if requested, otherwise fall back to TCG emulation
         if self.enable_kvm {
             args.push("-enable-kvm".to_string());
+        } else {
+            args.push("-accel".to_string());
+            args.push("tcg".to_string());
         }
 
         // CPU configuration
@@ -62,10 +115,7 @@ impl QemuConfig {
 
             // SEV-SNP guest object
             args.push("-object".to_string());
-            args.push(format!(
-                "sev-snp-guest,id=sev0,cbitpos={},reduced-phys-bits={}",
-                sev_snp.cbitpos, sev_snp.reduced_phys_bits
-            ));
+            args.push(sev_snp.to_guest_object());
 
             // BIOS (OVMF) is required for SEV
             if let Some(ref bios_path) = self.bios_path {
@@ -75,7 +125,7 @@ impl QemuConfig {
         } else {
             // Non-TEE mode
             args.push("-cpu".to_string());
-            args.push(self.cpu_type.clone());
+            args.push(self.cpu_model().to_string());
 
             args.push("-machine".to_string());
             args.push("q35".to_string());
@@ -98,13 +148,27 @@ impl QemuConfig {
         args.push("-append".to_string());
         args.push(self.kernel_cmdline.clone());
 
-        // Network - user networking with port forwarding
+        // Network - user networking with port forwarding. Outbound connections,
+        // e.g. to a fork provider, go through its NAT; the host is 10.0.2.2
         args.push("-netdev".to_string());
         args.push(format!("user,id=net0,hostfwd=tcp::{}-:5050", self.rpc_port));
 
         args.push("-device".to_string());
         args.push("virtio-net-pci,netdev=net0".to_string());
 
+        // Guest agent control channel
+        if let Some(cid) = self.vsock_cid {
+            args.push("-device".to_string());
+            args.push(format!("vhost-vsock-pci,guest-cid={}", cid));
+        }
+
+        // Memory balloon, so the host can see the guest's memory size. Not
+        // for SEV-SNP guests, whose private memory the host can't reclaim
+        if self.balloon && self.sev_snp.is_none() {
+            args.push("-device".to_string());
+            args.push("virtio-balloon-pci,id=balloon0".to_string());
+        }
+
         // Storage - virtio-blk disk image
         if let Some(ref disk_path) = self.disk_image {
             args.push("-drive".to_string());
@@ -114,13 +178,44 @@ impl QemuConfig {
             ));
         }
 
+        // Config drive - host directory as a read-only FAT disk. Unlike the
+        // kernel cmdline it has no length limit
+        if let Some(ref config_drive) = self.config_drive {
+            args.push("-drive".to_string());
+            args.push(format!(
+                "file=fat:{},if=virtio,format=raw,readonly=on",
+                config_drive.to_string_lossy()
+            ));
+        }
+
         // No graphics (use -display none instead of -nographic for compatibility with -daemonize)
         args.push("-display".to_string());
         args.push("none".to_string());
 
-        // Serial console to file
+        // Serial console to file, opened in append mode so the daemon can
+        // rotate it with copy-truncate
+        args.push("-chardev".to_string());
+        args.push(format!(
+            "file,id=serial0,path={},append=on",
+            self.serial_log.to_string_lossy()
+        ));
         args.push("-serial".to_string());
-        args.push(format!("file:{}", self.serial_log.to_string_lossy()));
+        args.push("chardev:serial0".to_string());
+
+        // Katana's own output on a virtio-serial port, kept apart from the
+        // kernel and init messages on the console
+        args.push("-device".to_string());
+        args.push("virtio-serial-pci,id=virtio-serial0".to_string());
+        args.push("-chardev".to_string());
+        args.push(format!(
+            "file,id=katana-log,path={},append=on",
+            self.katana_log.to_string_lossy()
+        ));
+        args.push("-device".to_string());
+        args.push(format!(
+            "virtserialport,bus=virtio-serial0.0,chardev=katana-log,name={}",
+            KATANA_LOG_PORT
+        ));
 
         // QMP socket
         args.push("-qmp".to_string());
@@ -143,7 +238,49 @@ impl QemuConfig {
     pub fn build_kernel_cmdline(katana_args: &[String]) -> String {
         let katana_args_str = katana_args.join(" ");
 
-        format!("console=ttyS0 loglevel=4 katana.args={}", katana_args_str)
+        format!("{} katana.args={}", BASE_KERNEL_CMDLINE, katana_args_str)
+    }
+
+    /// Kernel command line for guests that read Katana's args from the config
+    /// drive. It is the same for every instance, so changing settings leaves
+    /// a measured cmdline alone.
+    pub fn config_drive_kernel_cmdline() -> String {
+        format!(
+            "{} katana.config={}/{}",
+            BASE_KERNEL_CMDLINE, GUEST_CONFIG_DIR, KATANA_CONFIG_FILE
+        )
+    }
+}
+
+impl SevSnpConfig {
+    /// Render the `sev-snp-guest` object definition passed to `-object`
+    pub fn to_guest_object(&self) -> String {
+        let mut object = format!(
+            "sev-snp-guest,id=sev0,cbitpos={},reduced-phys-bits={}",
+            self.cbitpos, self.reduced_phys_bits
+        );
+
+        if let Some(policy) = self.policy {
+            object.push_str(&format!(",policy={:#x}", policy));
+        }
+
+        if let Some(ref host_data) = self.host_data {
+            object.push_str(&format!(",host-data={}", host_data));
+        }
+
+        if let Some(ref id_block) = self.id_block {
+            object.push_str(&format!(",id-block={}", id_block));
+        }
+
+        if let Some(ref id_auth) = self.id_auth {
+            object.push_str(&format!(",id-auth={}", id_auth));
+        }
+
+        if self.kernel_hashes {
+            object.push_str(",kernel-hashes=on");
+        }
+
+        object
     }
 }
 
@@ -162,12 +299,16 @@ mod tests {
             bios_path: None,
             kernel_cmdline: "console=ttyS0".to_string(),
             rpc_port: 5050,
+            vsock_cid: None,
             disk_image: None,
+            config_drive: None,
             qmp_socket: PathBuf::from("/tmp/qmp.sock"),
             serial_log: PathBuf::from("/tmp/serial.log"),
+            katana_log: PathBuf::from("/tmp/katana.log"),
             pid_file: PathBuf::from("/tmp/qemu.pid"),
             sev_snp: None,
             enable_kvm: true,
+            balloon: true,
         }
     }
 
@@ -211,6 +352,13 @@ mod tests {
         assert!(args.contains(&"user,id=net0,hostfwd=tcp::5050-:5050".to_string()));
         assert!(args.contains(&"-device".to_string()));
         assert!(args.contains(&"virtio-net-pci,netdev=net0".to_string()));
+        assert!(args.contains(&"virtio-balloon-pci,id=balloon0".to_string()));
+
+        // The balloon is opt-in
+        let mut config = create_test_config();
+        config.balloon = false;
+        let args = config.to_qemu_args();
+        assert!(!args.contains(&"virtio-balloon-pci,id=balloon0".to_string()));
     }
 
     #[test]
@@ -219,7 +367,13 @@ mod tests {
         let args = config.to_qemu_args();
 
         assert!(args.contains(&"-serial".to_string()));
-        assert!(args.contains(&"file:/tmp/serial.log".to_string()));
+        assert!(args.contains(&"file,id=serial0,path=/tmp/serial.log,append=on".to_string()));
+        assert!(args.contains(&"chardev:serial0".to_string()));
+        assert!(args.contains(&"file,id=katana-log,path=/tmp/katana.log,append=on".to_string()));
+        assert!(args.contains(
+            &"virtserialport,bus=virtio-serial0.0,chardev=katana-log,name=org.katana.log"
+                .to_string()
+        ));
         assert!(args.contains(&"-qmp".to_string()));
         assert!(args.contains(&"unix:/tmp/qmp.sock,server,nowait".to_string()));
         assert!(args.contains(&"-display".to_string()));
@@ -244,6 +398,11 @@ mod tests {
             cbitpos: 51,
             reduced_phys_bits: 1,
             vcpu_type: "EPYC-v4".to_string(),
+            policy: None,
+            host_data: None,
+            id_block: None,
+            id_auth: None,
+            kernel_hashes: false,
         });
         config.bios_path = Some(PathBuf::from("/test/ovmf.fd"));
 
@@ -258,6 +417,32 @@ mod tests {
         assert!(args.contains(&"sev-snp-guest,id=sev0,cbitpos=51,reduced-phys-bits=1".to_string()));
         assert!(args.contains(&"-bios".to_string()));
         assert!(args.contains(&"/test/ovmf.fd".to_string()));
+
+        // No balloon for confidential guests
+        assert!(!args.contains(&"virtio-balloon-pci,id=balloon0".to_string()));
+    }
+
+    #[test]
+    fn test_sev_snp_launch_options() {
+        let mut config = create_test_config();
+        config.sev_snp = Some(SevSnpConfig {
+            cbitpos: 47,
+            reduced_phys_bits: 1,
+            vcpu_type: "EPYC-v4".to_string(),
+            policy: Some(0x30000),
+            host_data: Some("aG9zdA==".to_string()),
+            id_block: Some("YmxvY2s=".to_string()),
+            id_auth: Some("YXV0aA==".to_string()),
+            kernel_hashes: true,
+        });
+
+        let args = config.to_qemu_args();
+
+        assert!(args.contains(
+            &"sev-snp-guest,id=sev0,cbitpos=47,reduced-phys-bits=1,policy=0x30000,\
+              host-data=aG9zdA==,id-block=YmxvY2s=,id-auth=YXV0aA==,kernel-hashes=on"
+                .to_string()
+        ));
     }
 
     #[test]
@@ -314,6 +499,38 @@ mod tests {
         assert!(args.contains(&"user,id=net0,hostfwd=tcp::8080-:5050".to_string()));
     }
 
+    #[test]
+    fn test_vsock_device() {
+        let mut config = create_test_config();
+        let args = config.to_qemu_args();
+        assert!(!args.iter().any(|a| a.starts_with("vhost-vsock-pci")));
+
+        config.vsock_cid = Some(3);
+        let args = config.to_qemu_args();
+        assert!(args.contains(&"vhost-vsock-pci,guest-cid=3".to_string()));
+    }
+
+    #[test]
+    fn test_config_drive_kernel_cmdline() {
+        assert_eq!(
+            QemuConfig::config_drive_kernel_cmdline(),
+            "console=ttyS0 loglevel=4 katana.config=/config/katana.json"
+        );
+    }
+
+    #[test]
+    fn test_config_drive() {
+        let mut config = create_test_config();
+        let args = config.to_qemu_args();
+        assert!(!args.iter().any(|a| a.starts_with("file=fat:")));
+
+        config.config_drive = Some(PathBuf::from("/tmp/instance/config"));
+        let args = config.to_qemu_args();
+        assert!(args.contains(
+            &"file=fat:/tmp/instance/config,if=virtio,format=raw,readonly=on".to_string()
+        ));
+    }
+
     #[test]
     fn test_no_kvm_mode() {
         let mut config = create_test_config();
@@ -321,6 +538,17 @@ mod tests {
 
         let args = config.to_qemu_args();
         assert!(!args.contains(&"-enable-kvm".to_string()));
+
+        let accel_idx = args.iter().position(|a| a == "-accel").unwrap();
+        assert_eq!(args[accel_idx + 1], "tcg");
+
+        // host passthrough is unavailable under TCG
+        let cpu_idx = args.iter().position(|a| a == "-cpu").unwrap();
+        assert_eq!(args[cpu_idx + 1], "max");
+
+        // Explicit CPU models are kept
+        config.cpu_type = "qemu64".to_string();
+        assert_eq!(config.cpu_model(), "qemu64");
     }
 
     #[test]
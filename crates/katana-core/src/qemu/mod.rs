@@ -4,7 +4,7 @@ pub mod qmp;
 pub mod vm_instance;
 pub mod vm_managed;
 
-pub use config::QemuConfig;
+pub use config::{QemuConfig, KATANA_LOG_PORT};
 pub use qmp::QmpClient;
 pub use vm_instance::Vm;
 pub use vm_managed::ManagedVm;
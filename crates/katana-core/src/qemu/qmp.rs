@@ -3,11 +3,22 @@ use qmp::{Client, Endpoint};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+/// Client for a VM's QMP socket.
+///
+/// Netdev statistics are not supported: instances use the `-netdev user`
+/// backend, for which QEMU exposes no QMP counters. Network I/O is reported
+/// by the guest agent instead (see `crate::vsock::AgentRequest::Metrics`).
 #[derive(Debug)]
 pub struct QmpClient {
     client: Option<Client>,
 }
 
+impl Default for QmpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl QmpClient {
     pub fn new() -> Self {
         Self { client: None }
@@ -27,6 +38,19 @@ impl QmpClient {
         Ok(())
     }
 
+    /// Subscribe to QMP events (e.g. `SHUTDOWN`).
+    ///
+    /// Only events emitted after subscribing are received, so subscribe before
+    /// issuing the command whose events you want to observe.
+    pub fn events(&self) -> Result<qmp::EventStream> {
+        let client = self
+            .client
+            .as_ref()
+            .ok_or_else(|| HypervisorError::QemuFailed("Not connected to QMP".to_string()))?;
+
+        Ok(client.events())
+    }
+
     /// Query VM status
     pub async fn query_status(&mut self) -> Result<VmStatus> {
         let client = self
@@ -87,6 +111,67 @@ impl QmpClient {
         Ok(memory)
     }
 
+    /// Query I/O counters for each block device
+    pub async fn query_blockstats(&mut self) -> Result<Vec<BlockStats>> {
+        let client = self
+            .client
+            .as_ref()
+            .ok_or_else(|| HypervisorError::QemuFailed("Not connected to QMP".to_string()))?;
+
+        let response: serde_json::Value = client
+            .execute("query-blockstats", Option::<()>::None)
+            .await
+            .map_err(|e| {
+                HypervisorError::QemuFailed(format!("QMP query-blockstats failed: {}", e))
+            })?;
+
+        let stats: Vec<BlockStats> = serde_json::from_value(response).map_err(|e| {
+            HypervisorError::QemuFailed(format!("Failed to parse block stats: {}", e))
+        })?;
+
+        Ok(stats)
+    }
+
+    /// Query the memory balloon. Fails if the VM has no balloon device.
+    pub async fn query_balloon(&mut self) -> Result<BalloonInfo> {
+        let client = self
+            .client
+            .as_ref()
+            .ok_or_else(|| HypervisorError::QemuFailed("Not connected to QMP".to_string()))?;
+
+        let response: serde_json::Value = client
+            .execute("query-balloon", Option::<()>::None)
+            .await
+            .map_err(|e| HypervisorError::QemuFailed(format!("QMP query-balloon failed: {}", e)))?;
+
+        let balloon: BalloonInfo = serde_json::from_value(response).map_err(|e| {
+            HypervisorError::QemuFailed(format!("Failed to parse balloon info: {}", e))
+        })?;
+
+        Ok(balloon)
+    }
+
+    /// Query accelerator statistics for the whole VM or for each vCPU.
+    ///
+    /// Needs QEMU 7.1 or later, and only KVM provides statistics, so this is
+    /// empty under TCG.
+    pub async fn query_stats(&mut self, target: StatsTarget) -> Result<Vec<StatsResult>> {
+        let client = self
+            .client
+            .as_ref()
+            .ok_or_else(|| HypervisorError::QemuFailed("Not connected to QMP".to_string()))?;
+
+        let response: serde_json::Value = client
+            .execute("query-stats", Some(serde_json::json!({ "target": target })))
+            .await
+            .map_err(|e| HypervisorError::QemuFailed(format!("QMP query-stats failed: {}", e)))?;
+
+        let stats: Vec<StatsResult> = serde_json::from_value(response)
+            .map_err(|e| HypervisorError::QemuFailed(format!("Failed to parse stats: {}", e)))?;
+
+        Ok(stats)
+    }
+
     /// Initiate graceful shutdown of the VM via ACPI power button event.
     ///
     /// This command sends an ACPI power button press event to the guest operating system,
@@ -438,3 +523,101 @@ pub struct MemoryInfo {
     #[serde(rename = "base-memory")]
     pub base_memory: u64,
 }
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BlockStats {
+    /// Empty for devices only known by node name
+    #[serde(default)]
+    pub device: String,
+    #[serde(rename = "node-name")]
+    pub node_name: Option<String>,
+    pub stats: BlockDeviceStats,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BlockDeviceStats {
+    pub rd_bytes: u64,
+    pub wr_bytes: u64,
+    pub rd_operations: u64,
+    pub wr_operations: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BalloonInfo {
+    /// Memory currently available to the guest, in bytes
+    pub actual: u64,
+}
+
+/// What `query-stats` reports on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsTarget {
+    Vm,
+    Vcpu,
+}
+
+/// Statistics from one provider for the VM or one vCPU
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StatsResult {
+    /// e.g. "kvm"
+    pub provider: String,
+    /// Set for per-vCPU results
+    #[serde(rename = "qom-path")]
+    pub qom_path: Option<String>,
+    pub stats: Vec<Stat>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Stat {
+    pub name: String,
+    pub value: StatValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum StatValue {
+    Scalar(u64),
+    Boolean(bool),
+    /// Histogram buckets
+    List(Vec<u64>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query_stats() {
+        let response = serde_json::json!([{
+            "provider": "kvm",
+            "qom-path": "/machine/unattached/device[0]",
+            "stats": [
+                {"name": "exits", "value": 52340},
+                {"name": "guest_mode", "value": false},
+                {"name": "halt_poll_success_hist", "value": [0, 3, 1]}
+            ]
+        }]);
+
+        let results: Vec<StatsResult> = serde_json::from_value(response).unwrap();
+        assert_eq!(results[0].provider, "kvm");
+        assert_eq!(results[0].stats[0].value, StatValue::Scalar(52340));
+        assert_eq!(results[0].stats[1].value, StatValue::Boolean(false));
+        assert_eq!(results[0].stats[2].value, StatValue::List(vec![0, 3, 1]));
+    }
+
+    #[test]
+    fn test_parse_blockstats() {
+        let response = serde_json::json!([{
+            "device": "",
+            "node-name": "disk0",
+            "stats": {
+                "rd_bytes": 4096, "wr_bytes": 8192, "rd_operations": 1, "wr_operations": 2,
+                "flush_operations": 0
+            }
+        }]);
+
+        let stats: Vec<BlockStats> = serde_json::from_value(response).unwrap();
+        assert_eq!(stats[0].node_name.as_deref(), Some("disk0"));
+        assert_eq!(stats[0].stats.wr_bytes, 8192);
+    }
+}
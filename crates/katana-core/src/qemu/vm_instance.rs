@@ -3,6 +3,16 @@ use nix::sys::signal::{kill, Signal};
 use nix::unistd::Pid;
 use std::fs;
 use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long to wait for QEMU to exit after SIGTERM before sending SIGKILL
+const SIGTERM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for the kernel to reap QEMU after SIGKILL
+const SIGKILL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval for polling whether the QEMU process has exited
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(250);
 
 /// Represents a single QEMU VM instance with its configuration and state.
 ///
@@ -18,11 +28,12 @@ use std::process::{Command, Stdio};
 ///
 /// ```no_run
 /// # use katana_core::qemu::{QemuConfig, Vm};
-/// # fn example(config: QemuConfig) -> katana_core::Result<()> {
+/// # use std::time::Duration;
+/// # async fn example(config: QemuConfig) -> katana_core::Result<()> {
 /// let mut vm = Vm::new(config);
 /// vm.launch()?;
 /// // ... operations ...
-/// vm.stop(10)?;  // Required! Otherwise VM orphaned
+/// vm.stop(Duration::from_secs(30)).await?;  // Required! Otherwise VM orphaned
 /// # Ok(())
 /// # }
 /// ```
@@ -53,6 +64,7 @@ impl Vm {
     ///
     /// ```no_run
     /// # use katana_core::qemu::{QemuConfig, Vm};
+    /// # use std::time::Duration;
     /// # async fn example(config: QemuConfig) -> katana_core::Result<()> {
     /// let pid_str = std::fs::read_to_string("/tmp/qemu.pid")?;
     /// let pid: i32 = pid_str.trim().parse()
@@ -60,7 +72,7 @@ impl Vm {
     ///
     /// let mut vm = Vm::new(config);
     /// vm.attach(pid).await?;  // Verifies it's the right QEMU instance
-    /// vm.stop(10)?;
+    /// vm.stop(Duration::from_secs(30)).await?;
     /// # Ok(())
     /// # }
     /// ```
@@ -136,34 +148,73 @@ impl Vm {
         Ok(())
     }
 
-    /// Stop VM gracefully via SIGTERM. Force kills with SIGKILL after timeout.
-    pub fn stop(&mut self, timeout_secs: u64) -> Result<()> {
+    /// Stop VM gracefully.
+    ///
+    /// Sends an ACPI powerdown via QMP so the guest can flush its disks, and waits
+    /// up to `grace_period` for the `SHUTDOWN` event and QEMU to exit. Falls back
+    /// to SIGTERM, then SIGKILL if QEMU is still running `SIGTERM_TIMEOUT` later.
+    pub async fn stop(&mut self, grace_period: Duration) -> Result<()> {
         let pid = self.require_pid()?;
 
         tracing::info!("Stopping VM with PID: {}", pid);
 
-        // Send SIGTERM for graceful shutdown
+        if !grace_period.is_zero() {
+            match self.powerdown(grace_period).await {
+                Ok(true) => {
+                    tracing::info!("VM powered off gracefully");
+                    self.pid = None;
+                    return Ok(());
+                }
+                Ok(false) => {
+                    tracing::warn!(
+                        "VM did not power off within {:?}, sending SIGTERM",
+                        grace_period
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!("ACPI powerdown failed ({}), sending SIGTERM", e);
+                }
+            }
+        }
+
+        // The guest may have powered off on its own in the meantime
+        if !self.is_running() {
+            tracing::info!("VM already exited");
+            self.pid = None;
+            return Ok(());
+        }
+
+        // Send SIGTERM
         kill(Pid::from_raw(pid), Signal::SIGTERM)
             .map_err(|e| HypervisorError::QemuFailed(format!("Failed to send SIGTERM: {}", e)))?;
 
-        // Wait for process to exit
-        let start = std::time::Instant::now();
-        while start.elapsed().as_secs() < timeout_secs {
-            if !self.is_running() {
-                tracing::info!("VM stopped gracefully");
-                self.pid = None;
-                return Ok(());
-            }
-            std::thread::sleep(std::time::Duration::from_millis(500));
+        if self.wait_for_exit(SIGTERM_TIMEOUT).await {
+            tracing::info!("VM stopped after SIGTERM");
+            self.pid = None;
+            return Ok(());
         }
 
         // If still running, force kill
-        tracing::warn!("VM did not stop gracefully, sending SIGKILL");
+        tracing::warn!("VM did not stop after SIGTERM, sending SIGKILL");
         self.kill()?;
 
         Ok(())
     }
 
+    /// Wait up to `timeout` for the guest to power itself off, e.g. after the
+    /// guest agent was asked to shut down. Returns whether QEMU exited.
+    pub async fn wait_for_poweroff(&mut self, timeout: Duration) -> Result<bool> {
+        self.require_pid()?;
+
+        if !self.wait_for_exit(timeout).await {
+            return Ok(false);
+        }
+
+        tracing::info!("VM powered off");
+        self.pid = None;
+        Ok(true)
+    }
+
     /// Force kill VM with SIGKILL (immediate termination).
     ///
     /// **Warning**: May cause data loss. Use `stop()` for graceful shutdown.
@@ -183,6 +234,60 @@ impl Vm {
         Ok(())
     }
 
+    /// Kill the VM's QEMU process without QMP and remove its runtime files
+    /// (QMP socket, PID file and logs).
+    ///
+    /// Used to clean up wedged or broken instances. The process is looked up
+    /// from `pid` (e.g. the PID recorded in the database) and the PID file;
+    /// a candidate is only killed if its command line matches this VM, so a
+    /// stale PID reused by another process is left alone. Missing processes
+    /// and files are not errors.
+    pub async fn force_cleanup(&mut self, pid: Option<i32>) -> Result<()> {
+        let mut candidates: Vec<i32> = pid.into_iter().chain(self.pid).collect();
+        if let Ok(pid) = self.read_pid_file() {
+            candidates.push(pid);
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        for pid in candidates {
+            if let Err(e) = self.verify_qemu_process(pid) {
+                tracing::debug!("Not killing PID {}: {}", pid, e);
+                continue;
+            }
+
+            tracing::info!("Force killing VM with PID: {}", pid);
+            kill(Pid::from_raw(pid), Signal::SIGKILL).map_err(|e| {
+                HypervisorError::QemuFailed(format!("Failed to send SIGKILL: {}", e))
+            })?;
+
+            self.pid = Some(pid);
+            if !self.wait_for_exit(SIGKILL_TIMEOUT).await {
+                return Err(HypervisorError::QemuFailed(format!(
+                    "QEMU process {} did not exit after SIGKILL",
+                    pid
+                )));
+            }
+        }
+
+        self.pid = None;
+
+        for path in [
+            &self.config.qmp_socket,
+            &self.config.pid_file,
+            &self.config.serial_log,
+            &self.config.katana_log,
+        ] {
+            match fs::remove_file(path) {
+                Ok(()) => tracing::debug!("Removed {}", path.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(())
+    }
+
     /// Check if VM process is currently running.
     pub fn is_running(&self) -> bool {
         match self.pid {
@@ -288,6 +393,65 @@ impl Vm {
         &self.config.serial_log
     }
 
+    /// Request an ACPI powerdown and wait for QEMU to exit.
+    ///
+    /// Returns `Ok(false)` if QEMU is still running after `grace_period`.
+    async fn powerdown(&self, grace_period: Duration) -> Result<bool> {
+        let mut qmp_client = crate::qemu::QmpClient::new();
+        qmp_client.connect(&self.config.qmp_socket).await?;
+
+        // Subscribe before powering down so the SHUTDOWN event isn't missed
+        let mut events = qmp_client.events()?;
+        qmp_client.system_powerdown().await?;
+
+        let deadline = Instant::now() + grace_period;
+        let mut watch_events = true;
+
+        loop {
+            if !self.is_running() {
+                return Ok(true);
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(false);
+            }
+            let tick = remaining.min(EXIT_POLL_INTERVAL);
+
+            if !watch_events {
+                tokio::time::sleep(tick).await;
+                continue;
+            }
+
+            match tokio::time::timeout(tick, events.recv()).await {
+                Ok(Ok(event)) if event.name == "SHUTDOWN" => {
+                    // QEMU exits right after the guest shuts down
+                    tracing::info!("Guest shut down, waiting for QEMU to exit");
+                    watch_events = false;
+                }
+                Ok(Ok(_)) | Ok(Err(qmp::Error::EventLagged { .. })) => {}
+                // Connection closed, most likely because QEMU is exiting
+                Ok(Err(_)) => watch_events = false,
+                // No event within this tick, re-check the process
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for the QEMU process to exit.
+    async fn wait_for_exit(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+
+        while Instant::now() < deadline {
+            if !self.is_running() {
+                return true;
+            }
+            tokio::time::sleep(EXIT_POLL_INTERVAL).await;
+        }
+
+        !self.is_running()
+    }
+
     /// Helper to require a PID, returning an error if not launched.
     fn require_pid(&self) -> Result<i32> {
         self.pid
@@ -415,12 +579,16 @@ mod tests {
             bios_path: None,
             kernel_cmdline: "console=ttyS0".to_string(),
             rpc_port: 5050,
+            vsock_cid: None,
             disk_image: None,
+            config_drive: None,
             qmp_socket: PathBuf::from("/tmp/qmp.sock"),
             serial_log: PathBuf::from("/tmp/serial.log"),
+            katana_log: PathBuf::from("/tmp/katana.log"),
             pid_file: PathBuf::from("/tmp/qemu.pid"),
             sev_snp: None,
             enable_kvm: true,
+            balloon: false,
         }
     }
 
@@ -458,6 +626,55 @@ mod tests {
         assert_eq!(vm.pid(), Some(12345)); // PID unchanged
     }
 
+    #[tokio::test]
+    async fn test_stop_falls_back_to_sigterm() {
+        // No QMP socket, so powerdown fails and stop falls back to SIGTERM
+        let mut config = create_test_config();
+        config.qmp_socket = PathBuf::from("/nonexistent/qmp.sock");
+
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id() as i32;
+
+        // Reap the child as soon as it exits so it doesn't linger as a zombie
+        let reaper = std::thread::spawn(move || child.wait().unwrap());
+
+        let mut vm = Vm::new(config);
+        vm.pid = Some(pid);
+
+        let start = Instant::now();
+        vm.stop(Duration::from_secs(1)).await.unwrap();
+
+        assert!(vm.pid().is_none());
+        assert!(start.elapsed() < SIGTERM_TIMEOUT);
+        assert!(!reaper.join().unwrap().success());
+    }
+
+    #[tokio::test]
+    async fn test_force_cleanup_ignores_foreign_process() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut config = create_test_config();
+        config.qmp_socket = temp_dir.path().join("qmp.sock");
+        config.serial_log = temp_dir.path().join("serial.log");
+        config.katana_log = temp_dir.path().join("katana.log");
+        config.pid_file = temp_dir.path().join("qemu.pid");
+
+        // A stale PID file pointing at a process that isn't this VM
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        fs::write(&config.pid_file, child.id().to_string()).unwrap();
+        fs::write(&config.serial_log, "boot log").unwrap();
+
+        let mut vm = Vm::new(config.clone());
+        vm.force_cleanup(Some(99999)).await.unwrap();
+
+        assert!(vm.pid().is_none());
+        assert!(!config.pid_file.exists());
+        assert!(!config.serial_log.exists());
+        assert!(child.try_wait().unwrap().is_none());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
     #[test]
     fn test_accessors() {
         let config = create_test_config();
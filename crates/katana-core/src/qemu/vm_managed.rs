@@ -1,9 +1,10 @@
 use crate::{
-    instance::{InstanceState, InstanceStatus},
+    instance::{Accelerator, InstanceState, InstanceStatus},
     qemu::{QemuConfig, Vm},
     state::StateDatabase,
     Result,
 };
+use std::time::Duration;
 
 /// A database-tracked wrapper around `Vm` that automatically updates instance state.
 ///
@@ -20,6 +21,7 @@ use crate::{
 /// - **Reset**: Stays `Running` (VM reboots)
 /// - **Stop**: `Current` -> `Stopping` -> `Stopped` (PID cleared)
 /// - **Kill**: `Current` -> `Stopped` (immediate, PID cleared)
+/// - **Force cleanup**: `Any` -> `Stopped` (no QMP needed, runtime files removed)
 pub struct ManagedVm {
     /// The underlying QEMU VM instance
     vm: Vm,
@@ -85,6 +87,11 @@ impl ManagedVm {
                 let mut state = self.get_state()?;
                 state.update_status(InstanceStatus::Running);
                 state.vm_pid = self.vm.pid();
+                state.accelerator = Some(if self.vm.config().enable_kvm {
+                    Accelerator::Kvm
+                } else {
+                    Accelerator::Tcg
+                });
                 self.db.save_instance(&state)?;
 
                 tracing::info!(
@@ -220,13 +227,15 @@ impl ManagedVm {
 
     /// Stop VM gracefully with database state tracking.
     ///
+    /// See [`Vm::stop`] for the ACPI powerdown / SIGTERM / SIGKILL sequence.
+    ///
     /// Updates state: `Current` -> `Stopping` -> `Stopped` (clears PID)
-    pub fn stop(&mut self, timeout_secs: u64) -> Result<()> {
+    pub async fn stop(&mut self, grace_period: Duration) -> Result<()> {
         tracing::info!("ManagedVm: Stopping instance {}", self.instance_id);
 
         self.update_status(InstanceStatus::Stopping)?;
 
-        match self.vm.stop(timeout_secs) {
+        match self.vm.stop(grace_period).await {
             Ok(()) => {
                 // Clear PID and update status to Stopped
                 let mut state = self.get_state()?;
@@ -255,6 +264,26 @@ impl ManagedVm {
         }
     }
 
+    /// Wait up to `timeout` for the guest to power itself off. Updates state
+    /// to `Stopping`, then to `Stopped` and clears PID if it did.
+    ///
+    /// Returns whether the VM stopped; if not, `stop()` can take over.
+    pub async fn wait_for_poweroff(&mut self, timeout: Duration) -> Result<bool> {
+        self.update_status(InstanceStatus::Stopping)?;
+
+        if !self.vm.wait_for_poweroff(timeout).await? {
+            return Ok(false);
+        }
+
+        let mut state = self.get_state()?;
+        state.update_status(InstanceStatus::Stopped);
+        state.vm_pid = None;
+        self.db.save_instance(&state)?;
+
+        tracing::info!("ManagedVm: Instance {} powered off", self.instance_id);
+        Ok(true)
+    }
+
     /// Force kill VM immediately. Updates state to `Stopped` and clears PID.
     ///
     /// **Warning**: May cause data loss. Use `stop()` for graceful shutdown.
@@ -288,6 +317,29 @@ impl ManagedVm {
         }
     }
 
+    /// Kill an instance's QEMU process and remove its runtime files, whatever
+    /// state it is in.
+    ///
+    /// Unlike `from_instance` followed by `kill`, this does not need a live
+    /// QMP socket or a valid PID, so it works for `Failed` instances, stale
+    /// PIDs and instances stuck in `Starting`. Updates state to `Stopped` and
+    /// clears the PID.
+    pub async fn force_cleanup(instance_id: &str, db: &StateDatabase) -> Result<()> {
+        tracing::info!("ManagedVm: Force cleaning up instance {}", instance_id);
+
+        let state = db.get_instance_by_id(instance_id)?;
+        let mut vm = Vm::new(instance_state_to_qemu_config(&state)?);
+        vm.force_cleanup(state.vm_pid).await?;
+
+        // Re-fetch in case the state changed while waiting for QEMU to exit
+        let mut state = db.get_instance_by_id(instance_id)?;
+        state.vm_pid = None;
+        state.update_status(InstanceStatus::Stopped);
+        db.save_instance(&state)?;
+
+        Ok(())
+    }
+
     /// Check if the VM is currently running.
     ///
     /// This checks the actual QEMU process, not the database state.
@@ -357,22 +409,14 @@ fn instance_state_to_qemu_config(state: &InstanceState) -> Result<QemuConfig> {
     // Build paths in data directory
     let qmp_socket = config.data_dir.join("qmp.sock");
     let serial_log = config.data_dir.join("serial.log");
+    let katana_log = state.katana_log();
     let pid_file = config.data_dir.join("qemu.pid");
 
     // Build SEV-SNP config if in TEE mode
-    let sev_snp = if config.tee_mode {
-        Some(crate::qemu::config::SevSnpConfig {
-            cbitpos: 51,          // Standard for AMD SEV
-            reduced_phys_bits: 1, // Standard for AMD SEV
-            vcpu_type: config.vcpu_type.clone(),
-        })
-    } else {
-        None
-    };
+    let sev_snp = config.sev_snp_config();
 
-    // Build kernel command line with Katana arguments
-    let katana_args = config.build_katana_args();
-    let kernel_cmdline = QemuConfig::build_kernel_cmdline(&katana_args);
+    // Build kernel command line, with Katana arguments unless they're on the config drive
+    let kernel_cmdline = config.kernel_cmdline();
 
     Ok(QemuConfig {
         memory_mb: config.memory_mb,
@@ -383,12 +427,17 @@ fn instance_state_to_qemu_config(state: &InstanceState) -> Result<QemuConfig> {
         bios_path: config.ovmf_path.clone(),
         kernel_cmdline,
         rpc_port: config.rpc_port,
+        vsock_cid: config.vsock_cid,
         disk_image: config.disk_image.clone(),
+        config_drive: Some(state.config_dir()),
         qmp_socket,
         serial_log,
+        katana_log,
         pid_file,
         sev_snp,
-        enable_kvm: true, // Always enable KVM for production
+        // Instances launched before the accelerator was recorded always used KVM
+        enable_kvm: state.accelerator != Some(Accelerator::Tcg),
+        balloon: config.balloon,
     })
 }
 
@@ -413,9 +462,13 @@ mod tests {
             storage_bytes: 5 * 1024 * 1024 * 1024,
             rpc_port: 5050,
             metrics_port: None,
+            vsock_cid: None,
+            accelerator: Accelerator::Auto,
+            balloon: false,
             tee_mode: false,
             vcpu_type: "host".to_string(),
             expected_measurement: None,
+            sev_snp: None,
             kernel_path: data_dir.join("vmlinuz"),
             initrd_path: data_dir.join("initrd.img"),
             ovmf_path: None,
@@ -427,6 +480,14 @@ mod tests {
             accounts: Some(10),
             disable_fee: true,
             extra_args: vec![],
+            fork_provider: None,
+            fork_block: None,
+            messaging: None,
+            chain_spec: None,
+            env: Default::default(),
+            config_drive_args: false,
+            expires_at: None,
+            idle_timeout_secs: None,
         };
 
         let id = format!("test-id-{}", name);
@@ -448,20 +509,53 @@ mod tests {
         assert!(qemu_config.sev_snp.is_none());
     }
 
+    #[test]
+    fn test_instance_state_to_qemu_config_with_tcg() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut instance = create_test_instance("test1", temp_dir.path().to_path_buf());
+        instance.accelerator = Some(Accelerator::Tcg);
+
+        let qemu_config = instance_state_to_qemu_config(&instance).unwrap();
+
+        assert!(!qemu_config.enable_kvm);
+        assert_eq!(qemu_config.cpu_model(), "max");
+    }
+
     #[test]
     fn test_instance_state_to_qemu_config_with_tee() {
         let temp_dir = TempDir::new().unwrap();
         let mut instance = create_test_instance("test1", temp_dir.path().to_path_buf());
         instance.config.tee_mode = true;
         instance.config.vcpu_type = "EPYC-v4".to_string();
+        instance.config.sev_snp = Some(crate::tee::SevSnpConfig {
+            cbitpos: 47,
+            policy: Some(0x30000),
+            ..crate::tee::SevSnpConfig::default_epyc()
+        });
 
         let qemu_config = instance_state_to_qemu_config(&instance).unwrap();
 
         assert!(qemu_config.sev_snp.is_some());
         let sev_config = qemu_config.sev_snp.unwrap();
         assert_eq!(sev_config.vcpu_type, "EPYC-v4");
-        assert_eq!(sev_config.cbitpos, 51);
+        assert_eq!(sev_config.cbitpos, 47);
         assert_eq!(sev_config.reduced_phys_bits, 1);
+        assert_eq!(sev_config.policy, Some(0x30000));
+    }
+
+    #[test]
+    fn test_instance_state_to_qemu_config_with_legacy_tee() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut instance = create_test_instance("test1", temp_dir.path().to_path_buf());
+        instance.config.tee_mode = true;
+        instance.config.vcpu_type = "EPYC-v4".to_string();
+
+        // Instances without persisted SEV-SNP settings use host-probed values
+        let qemu_config = instance_state_to_qemu_config(&instance).unwrap();
+
+        let sev_config = qemu_config.sev_snp.unwrap();
+        assert_eq!(sev_config.vcpu_type, "EPYC-v4");
+        assert!(sev_config.validate().is_ok());
     }
 
     #[test]
@@ -498,6 +592,25 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_force_cleanup_stale_starting_instance() {
+        let (db, temp_dir) = create_test_db();
+        let mut instance = create_test_instance("test1", temp_dir.path().to_path_buf());
+        instance.update_status(InstanceStatus::Starting);
+        instance.vm_pid = Some(99999);
+        db.save_instance(&instance).unwrap();
+        std::fs::write(temp_dir.path().join("qemu.pid"), "99999").unwrap();
+
+        // from_instance can't attach to the stale PID, force_cleanup can clean it up
+        assert!(ManagedVm::from_instance(&instance.id, &db).await.is_err());
+        ManagedVm::force_cleanup(&instance.id, &db).await.unwrap();
+
+        let state = db.get_instance_by_id(&instance.id).unwrap();
+        assert!(matches!(state.status, InstanceStatus::Stopped));
+        assert!(state.vm_pid.is_none());
+        assert!(!temp_dir.path().join("qemu.pid").exists());
+    }
+
     #[test]
     fn test_get_state() {
         let (db, temp_dir) = create_test_db();
@@ -0,0 +1,405 @@
+// Katana JSON-RPC probe
+//
+// Reads chain-level state through an instance's forwarded RPC port, to tell
+// a devnet that produces blocks apart from one where only QEMU is alive.
+use crate::{port::activity::PROBE_ADDRESS, HypervisorError, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// How long a single RPC call may take before the node counts as unresponsive
+const RPC_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Chain-level state of a Katana node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainStats {
+    /// Number of the latest accepted block
+    pub block_number: u64,
+    /// Unix time in seconds of the latest accepted block
+    pub latest_block_timestamp: Option<u64>,
+    /// Chain ID as a hex felt
+    pub chain_id: Option<String>,
+    /// Transactions in the pending block
+    pub pending_transactions: Option<u64>,
+    /// Prefunded accounts, only served in dev mode
+    pub accounts: Vec<PredeployedAccount>,
+}
+
+/// A prefunded account from `dev_predeployedAccounts` or Katana's boot output
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PredeployedAccount {
+    pub address: String,
+    #[serde(default, alias = "public_key")]
+    pub public_key: Option<String>,
+    #[serde(default, alias = "private_key")]
+    pub private_key: Option<String>,
+    /// Fee token balance as a hex felt, when the node reports it
+    #[serde(default)]
+    pub balance: Option<String>,
+}
+
+/// Minimal JSON-RPC client for a Katana node
+pub struct KatanaRpcClient {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl KatanaRpcClient {
+    pub fn new(url: impl Into<String>) -> Result<Self> {
+        let client = reqwest::Client::builder().timeout(RPC_TIMEOUT).build()?;
+        Ok(Self {
+            client,
+            url: url.into(),
+        })
+    }
+
+    /// Client for the RPC port forwarded to the host. It connects from
+    /// `PROBE_ADDRESS`, so its requests don't keep the instance from idling.
+    pub fn for_port(rpc_port: u16) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(RPC_TIMEOUT)
+            .local_address(IpAddr::V4(PROBE_ADDRESS))
+            .build()?;
+        Ok(Self {
+            client,
+            url: format!("http://127.0.0.1:{}", rpc_port),
+        })
+    }
+
+    /// Call `method` and decode its result
+    pub async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let response: Value = self
+            .client
+            .post(&self.url)
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        parse_response(method, response)
+    }
+
+    pub async fn block_number(&self) -> Result<u64> {
+        self.call("starknet_blockNumber", json!([])).await
+    }
+
+    /// Unix time of the latest accepted block
+    pub async fn latest_block_timestamp(&self) -> Result<u64> {
+        #[derive(Deserialize)]
+        struct Block {
+            timestamp: u64,
+        }
+
+        let block: Block = self
+            .call(
+                "starknet_getBlockWithTxHashes",
+                json!({ "block_id": "latest" }),
+            )
+            .await?;
+        Ok(block.timestamp)
+    }
+
+    /// Chain ID as a hex felt, see `decode_short_string`
+    pub async fn chain_id(&self) -> Result<String> {
+        self.call("starknet_chainId", json!([])).await
+    }
+
+    pub async fn pending_transaction_count(&self) -> Result<u64> {
+        self.call(
+            "starknet_getBlockTransactionCount",
+            json!({ "block_id": "pending" }),
+        )
+        .await
+    }
+
+    pub async fn predeployed_accounts(&self) -> Result<Vec<PredeployedAccount>> {
+        self.call("dev_predeployedAccounts", json!([])).await
+    }
+
+    /// Class hash of the contract deployed at `address`
+    pub async fn class_hash_at(&self, address: &str) -> Result<String> {
+        self.call(
+            "starknet_getClassHashAt",
+            json!({ "block_id": "latest", "contract_address": address }),
+        )
+        .await
+    }
+
+    /// Probe the node. Only the block number is required; everything else is
+    /// left empty when the node doesn't serve it, e.g. `dev_*` outside dev mode.
+    pub async fn chain_stats(&self) -> Result<ChainStats> {
+        let block_number = self.block_number().await?;
+
+        let (timestamp, chain_id, pending, accounts) = tokio::join!(
+            self.latest_block_timestamp(),
+            self.chain_id(),
+            self.pending_transaction_count(),
+            self.predeployed_accounts(),
+        );
+
+        Ok(ChainStats {
+            block_number,
+            latest_block_timestamp: timestamp.ok(),
+            chain_id: chain_id.ok(),
+            pending_transactions: pending.ok(),
+            accounts: accounts.unwrap_or_default(),
+        })
+    }
+}
+
+/// Extract the result of a JSON-RPC response, turning an error object into
+/// `HypervisorError::Rpc`
+fn parse_response<T: DeserializeOwned>(method: &str, mut response: Value) -> Result<T> {
+    if let Some(error) = response.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown error");
+        return Err(HypervisorError::Rpc(format!("{}: {}", method, message)));
+    }
+
+    match response.get_mut("result") {
+        Some(result) => Ok(serde_json::from_value(result.take())?),
+        None => Err(HypervisorError::Rpc(format!(
+            "{}: response has no result",
+            method
+        ))),
+    }
+}
+
+/// Header line of the account table Katana prints at boot
+pub const PREFUNDED_ACCOUNTS_HEADER: &str = "PREFUNDED ACCOUNTS";
+
+/// Prefunded accounts from the table Katana prints at boot:
+///
+/// ```text
+/// | Account address |  0x127fd5f1fe78a71f8bcd1fec63e3fe2f0486b6ecd5c86a0466c3a21fa5cfcec
+/// | Private key     |  0xc5b2fcab997346f3ea1c00b002ecf6f382c5f9c9659a3894eb783c5320f912
+/// | Public key      |  0x33246ce85ebdc292e6a5c5b4dd51fab2757be34b8ffda847ca6925edf31cb67
+/// ```
+///
+/// Only the accounts from the last boot in `log` are returned.
+pub fn parse_prefunded_accounts(log: &str) -> Vec<PredeployedAccount> {
+    let mut parser = PrefundedAccountsParser::default();
+    log.lines().for_each(|line| parser.push_line(line));
+    parser.finish()
+}
+
+/// Line-by-line form of `parse_prefunded_accounts`, for logs too large to
+/// hold in memory
+#[derive(Debug, Default)]
+pub struct PrefundedAccountsParser {
+    accounts: Vec<PredeployedAccount>,
+    complete: bool,
+}
+
+impl PrefundedAccountsParser {
+    pub fn push_line(&mut self, line: &str) {
+        if line.contains(PREFUNDED_ACCOUNTS_HEADER) {
+            self.accounts.clear();
+            self.complete = false;
+            return;
+        }
+
+        let mut fields = line.split('|').skip(1);
+        let (Some(label), Some(value)) = (fields.next(), fields.next()) else {
+            // Anything else after the accounts ends the table
+            if !self.accounts.is_empty() && !line.trim().is_empty() {
+                self.complete = true;
+            }
+            return;
+        };
+        let value = value.trim();
+        if !value.starts_with("0x") {
+            return;
+        }
+
+        match label.trim() {
+            "Account address" => self.accounts.push(PredeployedAccount {
+                address: value.to_string(),
+                ..Default::default()
+            }),
+            "Private key" => {
+                if let Some(account) = self.accounts.last_mut() {
+                    account.private_key = Some(value.to_string());
+                }
+            }
+            "Public key" => {
+                if let Some(account) = self.accounts.last_mut() {
+                    account.public_key = Some(value.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether the current table has ended, so further lines can only
+    /// replace it with a later one
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    pub fn finish(self) -> Vec<PredeployedAccount> {
+        self.accounts
+    }
+}
+
+/// Decode a felt holding a Cairo short string, such as the chain ID
+/// `0x4b4154414e41` ("KATANA"). Returns `None` if it isn't printable ASCII.
+pub fn decode_short_string(felt: &str) -> Option<String> {
+    let hex = felt.strip_prefix("0x").unwrap_or(felt);
+    let hex = hex.trim_start_matches('0');
+    if hex.is_empty() {
+        return None;
+    }
+
+    let padded = if hex.len() % 2 == 1 {
+        format!("0{}", hex)
+    } else {
+        hex.to_string()
+    };
+
+    let bytes = (0..padded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&padded[i..i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    if bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+        String::from_utf8(bytes).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_short_string() {
+        assert_eq!(
+            decode_short_string("0x4b4154414e41"),
+            Some("KATANA".to_string())
+        );
+        assert_eq!(
+            decode_short_string("0x534e5f5345504f4c4941"),
+            Some("SN_SEPOLIA".to_string())
+        );
+        // Leading zeros and an odd number of digits
+        assert_eq!(decode_short_string("0x00041"), Some("A".to_string()));
+        assert_eq!(decode_short_string("0x0"), None);
+        assert_eq!(decode_short_string("0x1f"), None);
+        assert_eq!(decode_short_string("0xzz"), None);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let response = json!({ "jsonrpc": "2.0", "id": 1, "result": 42 });
+        assert_eq!(
+            parse_response::<u64>("starknet_blockNumber", response).unwrap(),
+            42
+        );
+
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "error": { "code": -32601, "message": "Method not found" }
+        });
+        let err = parse_response::<u64>("dev_predeployedAccounts", response).unwrap_err();
+        assert!(matches!(err, HypervisorError::Rpc(ref msg) if msg.contains("Method not found")));
+
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": [{
+                "address": "0x127fd",
+                "publicKey": "0x33246",
+                "privateKey": "0xc5b2f",
+                "balance": "0x21e19e0c9bab2400000"
+            }]
+        });
+        let accounts =
+            parse_response::<Vec<PredeployedAccount>>("dev_predeployedAccounts", response).unwrap();
+        assert_eq!(accounts[0].address, "0x127fd");
+        assert_eq!(accounts[0].private_key.as_deref(), Some("0xc5b2f"));
+        assert_eq!(
+            accounts[0].balance.as_deref(),
+            Some("0x21e19e0c9bab2400000")
+        );
+    }
+
+    #[test]
+    fn test_parse_prefunded_accounts() {
+        let log = "\
+[    3.141] katana[212]: starting
+PREFUNDED ACCOUNTS
+==================
+
+| Account address |  0x1111
+| Private key     |  0xaaaa
+| Public key      |  0xbbbb
+
+PREFUNDED ACCOUNTS
+==================
+
+| Account address |  0x2222
+| Private key     |  0xcccc
+| Public key      |  0xdddd
+
+| Account address |  0x3333
+| Private key     |  0xeeee
+| Public key      |  0xffff
+";
+        let accounts = parse_prefunded_accounts(log);
+
+        // Only the last boot counts
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(
+            accounts[0],
+            PredeployedAccount {
+                address: "0x2222".to_string(),
+                public_key: Some("0xdddd".to_string()),
+                private_key: Some("0xcccc".to_string()),
+                balance: None,
+            }
+        );
+        assert_eq!(accounts[1].address, "0x3333");
+        assert!(parse_prefunded_accounts("no accounts here").is_empty());
+    }
+
+    #[test]
+    fn test_prefunded_accounts_parser_complete() {
+        let mut parser = PrefundedAccountsParser::default();
+        for line in [
+            "PREFUNDED ACCOUNTS",
+            "==================",
+            "",
+            "| Account address |  0x1111",
+        ] {
+            parser.push_line(line);
+            assert!(!parser.is_complete());
+        }
+        parser.push_line("");
+        assert!(!parser.is_complete());
+
+        parser.push_line("2025-01-10T12:00:00.000000Z  INFO katana::node: RPC server started.");
+        assert!(parser.is_complete());
+
+        // A later boot starts over
+        parser.push_line("PREFUNDED ACCOUNTS");
+        assert!(!parser.is_complete());
+        assert!(parser.finish().is_empty());
+    }
+}
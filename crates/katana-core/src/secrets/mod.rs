@@ -0,0 +1,310 @@
+// Instance secrets
+//
+// Values such as fork RPC API keys or sequencer private keys are stored in
+// the state database sealed with ChaCha20-Poly1305 under a host key that
+// lives next to it. They are handed to the guest agent over vsock at boot
+// and never written to the instance config, the config drive or the QEMU
+// command line. Settings such as a fork provider URL can reference a secret
+// as `${NAME}`, which the guest expands.
+use crate::{HypervisorError, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+/// Host key file in the daemon state directory
+pub const SECRETS_KEY_FILE: &str = "secrets.key";
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// Largest secret value accepted
+pub const MAX_SECRET_LEN: usize = 64 * 1024;
+
+/// A secret value. `Debug` never prints it, so it can't end up in logs by
+/// accident; use `expose` where the plaintext is really needed.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretValue(String);
+
+impl SecretValue {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for SecretValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[redacted]")
+    }
+}
+
+/// Name and timestamps of a stored secret; the value is write-only
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretMetadata {
+    pub name: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Outcome of handing an instance's secrets to the guest at its last start
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum SecretsDelivery {
+    /// The daemon is waiting for the guest agent to accept them
+    Pending,
+    Delivered,
+    /// The agent never accepted them; the guest is still waiting and Katana
+    /// has not started
+    Failed {
+        error: String,
+    },
+}
+
+impl std::fmt::Display for SecretsDelivery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretsDelivery::Pending => write!(f, "pending"),
+            SecretsDelivery::Delivered => write!(f, "delivered"),
+            SecretsDelivery::Failed { error } => write!(f, "failed: {}", error),
+        }
+    }
+}
+
+/// Host key sealing secrets at rest
+#[derive(Clone)]
+pub struct SecretsKey {
+    cipher: ChaCha20Poly1305,
+}
+
+impl SecretsKey {
+    /// Load the key from `path`, generating it (mode 0600) on first use
+    pub fn load_or_create(path: &Path) -> Result<Self> {
+        if path.exists() {
+            let bytes = fs::read(path)?;
+            if bytes.len() != KEY_LEN {
+                return Err(HypervisorError::Secret(format!(
+                    "{} is {} bytes, expected {}",
+                    path.display(),
+                    bytes.len(),
+                    KEY_LEN
+                )));
+            }
+            return Ok(Self::from_bytes(&bytes));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(&key)?;
+        file.sync_all()?;
+
+        Ok(Self::from_bytes(&key))
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(bytes)),
+        }
+    }
+
+    /// Encrypt `value` as nonce followed by ciphertext. `context` is
+    /// authenticated but not stored, binding the ciphertext to its owner.
+    pub fn seal(&self, context: &str, value: &SecretValue) -> Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: value.expose().as_bytes(),
+                    aad: context.as_bytes(),
+                },
+            )
+            .map_err(|_| HypervisorError::Secret("Failed to encrypt secret".to_string()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// Decrypt a value produced by `seal` with the same `context`
+    pub fn open(&self, context: &str, sealed: &[u8]) -> Result<SecretValue> {
+        if sealed.len() < NONCE_LEN {
+            return Err(HypervisorError::Secret(format!(
+                "Sealed secret for {} is truncated",
+                context
+            )));
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: context.as_bytes(),
+                },
+            )
+            .map_err(|_| {
+                HypervisorError::Secret(format!(
+                    "Failed to decrypt secret for {}; was the host key replaced?",
+                    context
+                ))
+            })?;
+
+        String::from_utf8(plaintext)
+            .map(SecretValue)
+            .map_err(|_| HypervisorError::Secret(format!("Secret for {} is not UTF-8", context)))
+    }
+}
+
+impl std::fmt::Debug for SecretsKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretsKey").finish_non_exhaustive()
+    }
+}
+
+/// Secrets reach Katana as environment variables, so their names follow the
+/// same rules
+pub fn validate_secret(name: &str, value: &SecretValue) -> Result<()> {
+    crate::instance::config_drive::validate_env_name(name)?;
+
+    if value.expose().is_empty() {
+        return Err(HypervisorError::InvalidConfig(format!(
+            "Secret '{}' is empty",
+            name
+        )));
+    }
+    if value.expose().len() > MAX_SECRET_LEN {
+        return Err(HypervisorError::InvalidConfig(format!(
+            "Secret '{}' is larger than {} bytes",
+            name, MAX_SECRET_LEN
+        )));
+    }
+
+    Ok(())
+}
+
+/// Names of the secrets referenced as `${NAME}` in `value`, in order. A `$`
+/// not followed by `{` is taken literally.
+pub fn secret_references(value: &str) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("${") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            return Err(HypervisorError::InvalidConfig(format!(
+                "Unterminated secret reference in '{}'",
+                value
+            )));
+        };
+
+        let name = &after[..end];
+        crate::instance::config_drive::validate_env_name(name).map_err(|_| {
+            HypervisorError::InvalidConfig(format!(
+                "Invalid secret name '{}' referenced in '{}'",
+                name, value
+            ))
+        })?;
+        names.push(name.to_string());
+        rest = &after[end + 1..];
+    }
+
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_load_or_create_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SECRETS_KEY_FILE);
+
+        let key = SecretsKey::load_or_create(&path).unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        // The same key is loaded on the next start
+        let value = SecretValue::new("alchemy-api-key");
+        let sealed = key.seal("instance/ALCHEMY_KEY", &value).unwrap();
+        let reloaded = SecretsKey::load_or_create(&path).unwrap();
+        assert_eq!(
+            reloaded.open("instance/ALCHEMY_KEY", &sealed).unwrap(),
+            value
+        );
+
+        fs::write(&path, b"short").unwrap();
+        assert!(SecretsKey::load_or_create(&path).is_err());
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = SecretsKey::load_or_create(&dir.path().join(SECRETS_KEY_FILE)).unwrap();
+        let value =
+            SecretValue::new("0xc5b2fcab997346f3ea1c00b002ecf6f382c5f9c9659a3894eb783c5320f912");
+
+        let sealed = key.seal("a/KEY", &value).unwrap();
+        assert!(!String::from_utf8_lossy(&sealed).contains(value.expose()));
+        // Fresh nonce per seal
+        assert_ne!(sealed, key.seal("a/KEY", &value).unwrap());
+
+        // Bound to its context and tamper-evident
+        assert!(key.open("b/KEY", &sealed).is_err());
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(key.open("a/KEY", &tampered).is_err());
+        assert!(key.open("a/KEY", &sealed[..4]).is_err());
+    }
+
+    #[test]
+    fn test_secret_value_debug() {
+        let value = SecretValue::new("hunter2");
+        assert_eq!(format!("{:?}", value), "[redacted]");
+        assert_eq!(serde_json::to_string(&value).unwrap(), "\"hunter2\"");
+    }
+
+    #[test]
+    fn test_secret_references() {
+        assert_eq!(
+            secret_references("https://eth.example.com/v2/${FORK_API_KEY}?x=${B}").unwrap(),
+            vec!["FORK_API_KEY", "B"]
+        );
+        assert!(secret_references("https://rpc.example.com/$1")
+            .unwrap()
+            .is_empty());
+        assert!(secret_references("https://rpc.example.com/${KEY").is_err());
+        assert!(secret_references("https://rpc.example.com/${bad-name}").is_err());
+        assert!(secret_references("${}").is_err());
+    }
+
+    #[test]
+    fn test_validate_secret() {
+        assert!(validate_secret("FORK_API_KEY", &SecretValue::new("x")).is_ok());
+        assert!(validate_secret("fork-key", &SecretValue::new("x")).is_err());
+        assert!(validate_secret("KEY", &SecretValue::new("")).is_err());
+        assert!(validate_secret("KEY", &SecretValue::new("x".repeat(MAX_SECRET_LEN + 1))).is_err());
+    }
+}
@@ -1,13 +1,35 @@
 use crate::{
-    instance::{InstanceConfig, InstanceState, InstanceStatus},
+    instance::{
+        Accelerator, InstanceConfig, InstanceState, InstanceStatus, InstanceTemplate, Labels,
+        TemplateConfig,
+    },
+    secrets::{SecretMetadata, SecretValue, SecretsDelivery, SecretsKey},
+    stats::StatsSample,
+    vsock::{FIRST_GUEST_CID, LAST_GUEST_CID},
     HypervisorError, Result,
 };
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 const SCHEMA_SQL: &str = include_str!("schema.sql");
 
+/// Columns added after the initial schema, as (table, column, definition).
+/// Applied to databases created before the column existed.
+const COLUMN_MIGRATIONS: &[(&str, &str, &str)] = &[("instances", "accelerator", "TEXT")];
+
+const INSTANCE_COLUMNS: &str =
+    "id, name, status, config_json, vm_pid, qmp_socket, serial_log, accelerator, created_at, updated_at";
+
+const LABELS_TABLE: &str = "instance_labels";
+const ANNOTATIONS_TABLE: &str = "instance_annotations";
+
+const TEMPLATE_COLUMNS: &str = "name, description, config_json, created_at, updated_at";
+
+const STATS_COLUMNS: &str = "instance_id, timestamp, cpu_percent, rss_bytes, disk_bytes, \
+     net_rx_bytes, net_tx_bytes, block_read_bytes, block_write_bytes";
+
 #[derive(Clone)]
 pub struct StateDatabase {
     conn: Arc<Mutex<Connection>>,
@@ -27,6 +49,7 @@ impl StateDatabase {
 
         // Create schema
         conn.execute_batch(SCHEMA_SQL)?;
+        apply_column_migrations(&conn)?;
 
         Ok(Self {
             conn: Arc::new(Mutex::new(conn)),
@@ -47,6 +70,7 @@ impl StateDatabase {
             .serial_log
             .as_ref()
             .map(|p| p.to_string_lossy().to_string());
+        let accelerator_str = state.accelerator.map(|a| a.as_str());
 
         // Check if instance exists by ID
         let exists: bool = conn.query_row(
@@ -60,7 +84,8 @@ impl StateDatabase {
             conn.execute(
                 "UPDATE instances
                  SET name = ?2, status = ?3, config_json = ?4, vm_pid = ?5, qmp_socket = ?6,
-                     serial_log = ?7, tee_mode = ?8, expected_measurement = ?9, updated_at = ?10
+                     serial_log = ?7, tee_mode = ?8, expected_measurement = ?9, accelerator = ?10,
+                     updated_at = ?11
                  WHERE id = ?1",
                 params![
                     state.id,
@@ -72,6 +97,7 @@ impl StateDatabase {
                     serial_log_str,
                     state.config.tee_mode,
                     state.config.expected_measurement,
+                    accelerator_str,
                     chrono::Utc::now().timestamp(), // Always update timestamp on save
                 ],
             )?;
@@ -79,8 +105,8 @@ impl StateDatabase {
             // Insert new instance
             conn.execute(
                 "INSERT INTO instances
-                 (id, name, status, config_json, vm_pid, qmp_socket, serial_log, tee_mode, expected_measurement, created_at, updated_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                 (id, name, status, config_json, vm_pid, qmp_socket, serial_log, tee_mode, expected_measurement, accelerator, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
                 params![
                     state.id,
                     state.name,
@@ -91,62 +117,36 @@ impl StateDatabase {
                     serial_log_str,
                     state.config.tee_mode,
                     state.config.expected_measurement,
+                    accelerator_str,
                     state.created_at,
                     state.updated_at,
                 ],
             )?;
         }
 
+        save_metadata(&conn, LABELS_TABLE, &state.id, &state.labels)?;
+        save_metadata(&conn, ANNOTATIONS_TABLE, &state.id, &state.annotations)?;
+
         Ok(())
     }
 
     pub fn get_instance(&self, name: &str) -> Result<InstanceState> {
         let conn = self.conn.lock().unwrap();
 
-        let mut stmt = conn.prepare(
-            "SELECT id, name, status, config_json, vm_pid, qmp_socket, serial_log, created_at, updated_at
-             FROM instances
-             WHERE name = ?1"
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM instances WHERE name = ?1",
+            INSTANCE_COLUMNS
+        ))?;
 
-        let state = stmt
-            .query_row([name], |row| {
-                let id: String = row.get(0)?;
-                let name: String = row.get(1)?;
-                let status_str: String = row.get(2)?;
-                let config_json: String = row.get(3)?;
-                let vm_pid: Option<i32> = row.get(4)?;
-                let qmp_socket_str: Option<String> = row.get(5)?;
-                let serial_log_str: Option<String> = row.get(6)?;
-                let created_at: i64 = row.get(7)?;
-                let updated_at: i64 = row.get(8)?;
-
-                let status: InstanceStatus = serde_json::from_str(&status_str)
-                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-                let config: InstanceConfig = serde_json::from_str(&config_json)
-                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-
-                let qmp_socket = qmp_socket_str.map(|s| std::path::PathBuf::from(s));
-                let serial_log = serial_log_str.map(|s| std::path::PathBuf::from(s));
-
-                Ok(InstanceState {
-                    id,
-                    name,
-                    status,
-                    config,
-                    vm_pid,
-                    qmp_socket,
-                    serial_log,
-                    created_at,
-                    updated_at,
-                })
-            })
+        let mut state = stmt
+            .query_row([name], row_to_instance)
             .map_err(|e| match e {
                 rusqlite::Error::QueryReturnedNoRows => {
                     HypervisorError::InstanceNotFound(name.to_string())
                 }
                 e => HypervisorError::Database(e),
             })?;
+        load_related(&conn, &mut state)?;
 
         Ok(state)
     }
@@ -154,50 +154,18 @@ impl StateDatabase {
     pub fn get_instance_by_id(&self, id: &str) -> Result<InstanceState> {
         let conn = self.conn.lock().unwrap();
 
-        let mut stmt = conn.prepare(
-            "SELECT id, name, status, config_json, vm_pid, qmp_socket, serial_log, created_at, updated_at
-             FROM instances
-             WHERE id = ?1"
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM instances WHERE id = ?1",
+            INSTANCE_COLUMNS
+        ))?;
 
-        let state = stmt
-            .query_row([id], |row| {
-                let id: String = row.get(0)?;
-                let name: String = row.get(1)?;
-                let status_str: String = row.get(2)?;
-                let config_json: String = row.get(3)?;
-                let vm_pid: Option<i32> = row.get(4)?;
-                let qmp_socket_str: Option<String> = row.get(5)?;
-                let serial_log_str: Option<String> = row.get(6)?;
-                let created_at: i64 = row.get(7)?;
-                let updated_at: i64 = row.get(8)?;
-
-                let status: InstanceStatus = serde_json::from_str(&status_str)
-                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-                let config: InstanceConfig = serde_json::from_str(&config_json)
-                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-
-                let qmp_socket = qmp_socket_str.map(|s| std::path::PathBuf::from(s));
-                let serial_log = serial_log_str.map(|s| std::path::PathBuf::from(s));
-
-                Ok(InstanceState {
-                    id,
-                    name,
-                    status,
-                    config,
-                    vm_pid,
-                    qmp_socket,
-                    serial_log,
-                    created_at,
-                    updated_at,
-                })
-            })
-            .map_err(|e| match e {
-                rusqlite::Error::QueryReturnedNoRows => {
-                    HypervisorError::InstanceNotFound(id.to_string())
-                }
-                e => HypervisorError::Database(e),
-            })?;
+        let mut state = stmt.query_row([id], row_to_instance).map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                HypervisorError::InstanceNotFound(id.to_string())
+            }
+            e => HypervisorError::Database(e),
+        })?;
+        load_related(&conn, &mut state)?;
 
         Ok(state)
     }
@@ -205,47 +173,18 @@ impl StateDatabase {
     pub fn list_instances(&self) -> Result<Vec<InstanceState>> {
         let conn = self.conn.lock().unwrap();
 
-        let mut stmt = conn.prepare(
-            "SELECT id, name, status, config_json, vm_pid, qmp_socket, serial_log, created_at, updated_at
-             FROM instances
-             ORDER BY created_at DESC"
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM instances ORDER BY created_at DESC",
+            INSTANCE_COLUMNS
+        ))?;
 
-        let instances = stmt.query_map([], |row| {
-            let id: String = row.get(0)?;
-            let name: String = row.get(1)?;
-            let status_str: String = row.get(2)?;
-            let config_json: String = row.get(3)?;
-            let vm_pid: Option<i32> = row.get(4)?;
-            let qmp_socket_str: Option<String> = row.get(5)?;
-            let serial_log_str: Option<String> = row.get(6)?;
-            let created_at: i64 = row.get(7)?;
-            let updated_at: i64 = row.get(8)?;
-
-            let status: InstanceStatus = serde_json::from_str(&status_str)
-                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-            let config: InstanceConfig = serde_json::from_str(&config_json)
-                .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-
-            let qmp_socket = qmp_socket_str.map(|s| std::path::PathBuf::from(s));
-            let serial_log = serial_log_str.map(|s| std::path::PathBuf::from(s));
-
-            Ok(InstanceState {
-                id,
-                name,
-                status,
-                config,
-                vm_pid,
-                qmp_socket,
-                serial_log,
-                created_at,
-                updated_at,
-            })
-        })?;
+        let instances = stmt.query_map([], row_to_instance)?;
 
         let mut result = Vec::new();
         for instance in instances {
-            result.push(instance?);
+            let mut instance = instance?;
+            load_related(&conn, &mut instance)?;
+            result.push(instance);
         }
 
         Ok(result)
@@ -288,6 +227,303 @@ impl StateDatabase {
         Ok(result)
     }
 
+    /// Assign the lowest free guest CID from `first_cid` up to an instance,
+    /// replacing any CID it had. CIDs for which `in_use` returns true are
+    /// held outside this database and skipped.
+    pub fn allocate_vsock_cid(
+        &self,
+        instance_id: &str,
+        first_cid: u32,
+        in_use: impl Fn(u32) -> bool,
+    ) -> Result<u32> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt =
+            conn.prepare("SELECT cid FROM vsock_cids WHERE instance_id != ?1 ORDER BY cid")?;
+        let used = stmt
+            .query_map([instance_id], |row| row.get::<_, u32>(0))?
+            .collect::<rusqlite::Result<Vec<u32>>>()?;
+
+        let cid = (first_cid.max(FIRST_GUEST_CID)..=LAST_GUEST_CID)
+            .find(|cid| used.binary_search(cid).is_err() && !in_use(*cid))
+            .ok_or(HypervisorError::NoVsockCidsAvailable(first_cid))?;
+
+        conn.execute(
+            "DELETE FROM vsock_cids WHERE instance_id = ?1",
+            params![instance_id],
+        )?;
+        conn.execute(
+            "INSERT INTO vsock_cids (cid, instance_id) VALUES (?1, ?2)",
+            params![cid, instance_id],
+        )?;
+
+        Ok(cid)
+    }
+
+    pub fn get_vsock_cid(&self, instance_id: &str) -> Result<Option<u32>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare("SELECT cid FROM vsock_cids WHERE instance_id = ?1")?;
+        let mut rows = stmt.query_map([instance_id], |row| row.get(0))?;
+
+        Ok(rows.next().transpose()?)
+    }
+
+    pub fn insert_stats_sample(&self, sample: &StatsSample) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            &format!(
+                "INSERT INTO stats_samples ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                STATS_COLUMNS
+            ),
+            params![
+                sample.instance_id,
+                sample.timestamp,
+                sample.cpu_percent,
+                sample.rss_bytes.map(|v| v as i64),
+                sample.disk_bytes.map(|v| v as i64),
+                sample.net_rx_bytes.map(|v| v as i64),
+                sample.net_tx_bytes.map(|v| v as i64),
+                sample.block_read_bytes.map(|v| v as i64),
+                sample.block_write_bytes.map(|v| v as i64),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// The latest `limit` samples of an instance taken at or after `since`
+    /// (Unix seconds), oldest first
+    pub fn list_stats_samples(
+        &self,
+        instance_id: &str,
+        since: Option<i64>,
+        limit: usize,
+    ) -> Result<Vec<StatsSample>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM stats_samples
+             WHERE instance_id = ?1 AND timestamp >= ?2
+             ORDER BY timestamp DESC LIMIT ?3",
+            STATS_COLUMNS
+        ))?;
+        let mut samples = stmt
+            .query_map(
+                params![instance_id, since.unwrap_or(i64::MIN), limit as i64],
+                row_to_stats_sample,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        samples.reverse();
+        Ok(samples)
+    }
+
+    /// The most recent sample of every instance that has one, by instance ID
+    pub fn latest_stats_samples(&self) -> Result<HashMap<String, StatsSample>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM stats_samples s
+             WHERE timestamp = (
+                 SELECT MAX(timestamp) FROM stats_samples WHERE instance_id = s.instance_id
+             )",
+            STATS_COLUMNS
+        ))?;
+        let samples = stmt
+            .query_map([], row_to_stats_sample)?
+            .map(|sample| sample.map(|sample| (sample.instance_id.clone(), sample)))
+            .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+
+        Ok(samples)
+    }
+
+    /// Delete samples taken before `before` (Unix seconds), returning how many
+    pub fn prune_stats_samples(&self, before: i64) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+
+        Ok(conn.execute("DELETE FROM stats_samples WHERE timestamp < ?1", [before])?)
+    }
+
+    pub fn create_template(&self, template: &InstanceTemplate) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        let config_json = serde_json::to_string(&template.config)?;
+
+        conn.execute(
+            "INSERT INTO templates (name, description, config_json, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                template.name,
+                template.description,
+                config_json,
+                template.created_at,
+                template.updated_at,
+            ],
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::SqliteFailure(err, _)
+                if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                HypervisorError::TemplateAlreadyExists(template.name.clone())
+            }
+            e => HypervisorError::Database(e),
+        })?;
+
+        Ok(())
+    }
+
+    pub fn get_template(&self, name: &str) -> Result<InstanceTemplate> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM templates WHERE name = ?1",
+            TEMPLATE_COLUMNS
+        ))?;
+
+        stmt.query_row([name], row_to_template)
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    HypervisorError::TemplateNotFound(name.to_string())
+                }
+                e => HypervisorError::Database(e),
+            })
+    }
+
+    pub fn list_templates(&self) -> Result<Vec<InstanceTemplate>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM templates ORDER BY name",
+            TEMPLATE_COLUMNS
+        ))?;
+
+        let templates = stmt
+            .query_map([], row_to_template)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(templates)
+    }
+
+    pub fn delete_template(&self, name: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        let rows_affected = conn.execute("DELETE FROM templates WHERE name = ?1", [name])?;
+
+        if rows_affected == 0 {
+            return Err(HypervisorError::TemplateNotFound(name.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Store a secret sealed with `key`, replacing any previous value
+    pub fn set_secret(
+        &self,
+        instance_id: &str,
+        name: &str,
+        value: &SecretValue,
+        key: &SecretsKey,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        let sealed = key.seal(&secret_context(instance_id, name), value)?;
+        let now = chrono::Utc::now().timestamp();
+
+        conn.execute(
+            "INSERT INTO instance_secrets (instance_id, name, sealed_value, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?4)
+             ON CONFLICT (instance_id, name)
+             DO UPDATE SET sealed_value = excluded.sealed_value, updated_at = excluded.updated_at",
+            params![instance_id, name, sealed, now],
+        )?;
+
+        Ok(())
+    }
+
+    /// Names of an instance's secrets, without their values
+    pub fn list_secrets(&self, instance_id: &str) -> Result<Vec<SecretMetadata>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt = conn.prepare(
+            "SELECT name, created_at, updated_at FROM instance_secrets
+             WHERE instance_id = ?1 ORDER BY name",
+        )?;
+        let secrets = stmt
+            .query_map([instance_id], |row| {
+                Ok(SecretMetadata {
+                    name: row.get(0)?,
+                    created_at: row.get(1)?,
+                    updated_at: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(secrets)
+    }
+
+    /// Decrypted secrets of an instance, for delivery to the guest
+    pub fn get_secrets(
+        &self,
+        instance_id: &str,
+        key: &SecretsKey,
+    ) -> Result<BTreeMap<String, SecretValue>> {
+        let conn = self.conn.lock().unwrap();
+
+        let mut stmt =
+            conn.prepare("SELECT name, sealed_value FROM instance_secrets WHERE instance_id = ?1")?;
+        let sealed = stmt
+            .query_map([instance_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        sealed
+            .into_iter()
+            .map(|(name, sealed)| {
+                let value = key.open(&secret_context(instance_id, &name), &sealed)?;
+                Ok((name, value))
+            })
+            .collect()
+    }
+
+    /// Record how delivering an instance's secrets to the guest went. Kept
+    /// apart from `save_instance`, which may run concurrently with delivery.
+    pub fn set_secrets_delivery(
+        &self,
+        instance_id: &str,
+        delivery: &SecretsDelivery,
+    ) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+
+        conn.execute(
+            "INSERT INTO instance_secrets_delivery (instance_id, state, updated_at)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT (instance_id)
+             DO UPDATE SET state = excluded.state, updated_at = excluded.updated_at",
+            params![
+                instance_id,
+                serde_json::to_string(delivery)?,
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Delete a secret, returning whether it existed
+    pub fn delete_secret(&self, instance_id: &str, name: &str) -> Result<bool> {
+        let conn = self.conn.lock().unwrap();
+
+        let rows_affected = conn.execute(
+            "DELETE FROM instance_secrets WHERE instance_id = ?1 AND name = ?2",
+            [instance_id, name],
+        )?;
+
+        Ok(rows_affected > 0)
+    }
+
     pub fn instance_exists(&self, name: &str) -> Result<bool> {
         let conn = self.conn.lock().unwrap();
         let count: i64 = conn.query_row(
@@ -299,10 +535,165 @@ impl StateDatabase {
     }
 }
 
+/// Authenticated context of a sealed secret, so a value can't be moved to
+/// another instance or name in the database
+fn secret_context(instance_id: &str, name: &str) -> String {
+    format!("{}/{}", instance_id, name)
+}
+
+/// Map a row selected with `INSTANCE_COLUMNS` to an `InstanceState`
+fn row_to_instance(row: &rusqlite::Row) -> rusqlite::Result<InstanceState> {
+    let id: String = row.get(0)?;
+    let name: String = row.get(1)?;
+    let status_str: String = row.get(2)?;
+    let config_json: String = row.get(3)?;
+    let vm_pid: Option<i32> = row.get(4)?;
+    let qmp_socket_str: Option<String> = row.get(5)?;
+    let serial_log_str: Option<String> = row.get(6)?;
+    let accelerator_str: Option<String> = row.get(7)?;
+    let created_at: i64 = row.get(8)?;
+    let updated_at: i64 = row.get(9)?;
+
+    let status: InstanceStatus = serde_json::from_str(&status_str)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+    let config: InstanceConfig = serde_json::from_str(&config_json)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    let qmp_socket = qmp_socket_str.map(std::path::PathBuf::from);
+    let serial_log = serial_log_str.map(std::path::PathBuf::from);
+    let accelerator = accelerator_str.and_then(|a| a.parse::<Accelerator>().ok());
+
+    Ok(InstanceState {
+        id,
+        name,
+        status,
+        config,
+        vm_pid,
+        qmp_socket,
+        serial_log,
+        accelerator,
+        labels: Labels::new(),
+        annotations: Labels::new(),
+        secrets_delivery: None,
+        created_at,
+        updated_at,
+    })
+}
+
+/// Replace the key/value pairs stored for an instance in a metadata table
+fn save_metadata(conn: &Connection, table: &str, instance_id: &str, values: &Labels) -> Result<()> {
+    conn.execute(
+        &format!("DELETE FROM {} WHERE instance_id = ?1", table),
+        [instance_id],
+    )?;
+
+    let mut stmt = conn.prepare(&format!(
+        "INSERT INTO {} (instance_id, key, value) VALUES (?1, ?2, ?3)",
+        table
+    ))?;
+    for (key, value) in values {
+        stmt.execute(params![instance_id, key, value])?;
+    }
+
+    Ok(())
+}
+
+fn load_metadata(conn: &Connection, table: &str, instance_id: &str) -> Result<Labels> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT key, value FROM {} WHERE instance_id = ?1",
+        table
+    ))?;
+
+    let values = stmt
+        .query_map([instance_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Labels>>()?;
+
+    Ok(values)
+}
+
+/// Fill in the parts of an instance stored outside the `instances` row
+fn load_related(conn: &Connection, state: &mut InstanceState) -> Result<()> {
+    state.labels = load_metadata(conn, LABELS_TABLE, &state.id)?;
+    state.annotations = load_metadata(conn, ANNOTATIONS_TABLE, &state.id)?;
+
+    let delivery: Option<String> = conn
+        .query_row(
+            "SELECT state FROM instance_secrets_delivery WHERE instance_id = ?1",
+            [&state.id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    state.secrets_delivery = delivery
+        .map(|json| serde_json::from_str(&json))
+        .transpose()?;
+    Ok(())
+}
+
+/// Map a row selected with `STATS_COLUMNS` to a `StatsSample`
+fn row_to_stats_sample(row: &rusqlite::Row) -> rusqlite::Result<StatsSample> {
+    let bytes = |idx: usize| -> rusqlite::Result<Option<u64>> {
+        Ok(row.get::<_, Option<i64>>(idx)?.map(|v| v as u64))
+    };
+
+    Ok(StatsSample {
+        instance_id: row.get(0)?,
+        timestamp: row.get(1)?,
+        cpu_percent: row.get(2)?,
+        rss_bytes: bytes(3)?,
+        disk_bytes: bytes(4)?,
+        net_rx_bytes: bytes(5)?,
+        net_tx_bytes: bytes(6)?,
+        block_read_bytes: bytes(7)?,
+        block_write_bytes: bytes(8)?,
+    })
+}
+
+/// Map a row selected with `TEMPLATE_COLUMNS` to an `InstanceTemplate`
+fn row_to_template(row: &rusqlite::Row) -> rusqlite::Result<InstanceTemplate> {
+    let config_json: String = row.get(2)?;
+    let config: TemplateConfig = serde_json::from_str(&config_json)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+
+    Ok(InstanceTemplate {
+        name: row.get(0)?,
+        description: row.get(1)?,
+        config,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+/// Add columns missing from databases created with an older schema
+fn apply_column_migrations(conn: &Connection) -> Result<()> {
+    for (table, column, definition) in COLUMN_MIGRATIONS {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|name| name.ok())
+            .any(|name| name == *column);
+
+        if !exists {
+            conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                [],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::StateDatabase;
-    use crate::instance::{InstanceConfig, InstanceState, InstanceStatus};
+    use crate::instance::{
+        Accelerator, InstanceConfig, InstanceState, InstanceStatus, InstanceTemplate,
+        TemplateConfig,
+    };
+    use crate::secrets::{SecretValue, SecretsDelivery, SecretsKey};
+    use crate::stats::StatsSample;
+    use crate::vsock::{FIRST_GUEST_CID, LAST_GUEST_CID};
+    use crate::HypervisorError;
     use tempfile::TempDir;
 
     fn create_test_db() -> (StateDatabase, TempDir) {
@@ -319,9 +710,13 @@ mod tests {
             storage_bytes: 10 * 1024 * 1024 * 1024,
             rpc_port: 5050,
             metrics_port: None,
+            vsock_cid: None,
+            accelerator: Accelerator::Auto,
+            balloon: false,
             tee_mode: false,
             vcpu_type: "host".to_string(),
             expected_measurement: None,
+            sev_snp: None,
             kernel_path: "/tmp/vmlinuz".into(),
             initrd_path: "/tmp/initrd.img".into(),
             ovmf_path: None,
@@ -333,6 +728,14 @@ mod tests {
             accounts: Some(10),
             disable_fee: true,
             extra_args: vec![],
+            fork_provider: None,
+            fork_block: None,
+            messaging: None,
+            chain_spec: None,
+            env: Default::default(),
+            config_drive_args: false,
+            expires_at: None,
+            idle_timeout_secs: None,
         };
 
         // Use unique ID based on name for testing
@@ -394,6 +797,271 @@ mod tests {
         assert!(retrieved.updated_at > retrieved.created_at);
     }
 
+    #[test]
+    fn test_accelerator_roundtrip() {
+        let (db, _temp) = create_test_db();
+        let mut instance = create_test_instance("test1");
+
+        db.save_instance(&instance).unwrap();
+        assert_eq!(db.get_instance("test1").unwrap().accelerator, None);
+
+        instance.accelerator = Some(Accelerator::Tcg);
+        db.save_instance(&instance).unwrap();
+        assert_eq!(
+            db.get_instance("test1").unwrap().accelerator,
+            Some(Accelerator::Tcg)
+        );
+    }
+
+    #[test]
+    fn test_migrates_legacy_schema() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("legacy.db");
+
+        // Instances table as created before the accelerator column existed
+        {
+            let conn = rusqlite::Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE instances (
+                    id TEXT PRIMARY KEY,
+                    name TEXT UNIQUE NOT NULL,
+                    status TEXT NOT NULL,
+                    config_json TEXT NOT NULL,
+                    vm_pid INTEGER,
+                    qmp_socket TEXT,
+                    serial_log TEXT,
+                    tee_mode BOOLEAN NOT NULL,
+                    expected_measurement TEXT,
+                    created_at INTEGER NOT NULL,
+                    updated_at INTEGER NOT NULL
+                );",
+            )
+            .unwrap();
+        }
+
+        let db = StateDatabase::new(&db_path).unwrap();
+        let mut instance = create_test_instance("test1");
+        instance.accelerator = Some(Accelerator::Kvm);
+        db.save_instance(&instance).unwrap();
+
+        let retrieved = db.get_instance("test1").unwrap();
+        assert_eq!(retrieved.accelerator, Some(Accelerator::Kvm));
+
+        // Reopening an already migrated database is a no-op
+        drop(db);
+        StateDatabase::new(&db_path).unwrap();
+    }
+
+    #[test]
+    fn test_allocate_vsock_cid() {
+        let (db, _temp) = create_test_db();
+        let instance1 = create_test_instance("test1");
+        let instance2 = create_test_instance("test2");
+        db.save_instance(&instance1).unwrap();
+        db.save_instance(&instance2).unwrap();
+
+        assert_eq!(db.get_vsock_cid(&instance1.id).unwrap(), None);
+
+        let free = |_| false;
+        let cid1 = db
+            .allocate_vsock_cid(&instance1.id, FIRST_GUEST_CID, free)
+            .unwrap();
+        let cid2 = db
+            .allocate_vsock_cid(&instance2.id, FIRST_GUEST_CID, free)
+            .unwrap();
+        assert_eq!(cid1, 3);
+        assert_eq!(cid2, 4);
+        assert_eq!(db.get_vsock_cid(&instance1.id).unwrap(), Some(3));
+
+        // Deleting an instance frees its CID for reuse
+        db.delete_instance("test1").unwrap();
+        let instance3 = create_test_instance("test3");
+        db.save_instance(&instance3).unwrap();
+        assert_eq!(
+            db.allocate_vsock_cid(&instance3.id, FIRST_GUEST_CID, free)
+                .unwrap(),
+            3
+        );
+
+        // CIDs held elsewhere on the host are skipped, and reallocating
+        // replaces the instance's CID
+        assert_eq!(
+            db.allocate_vsock_cid(&instance3.id, FIRST_GUEST_CID, |cid| cid == 3)
+                .unwrap(),
+            5
+        );
+        assert_eq!(db.get_vsock_cid(&instance3.id).unwrap(), Some(5));
+
+        // The range starts at the configured base
+        assert_eq!(
+            db.allocate_vsock_cid(&instance3.id, 1000, free).unwrap(),
+            1000
+        );
+        assert!(matches!(
+            db.allocate_vsock_cid(&instance3.id, LAST_GUEST_CID, |_| true),
+            Err(HypervisorError::NoVsockCidsAvailable(_))
+        ));
+    }
+
+    #[test]
+    fn test_labels_roundtrip() {
+        let (db, _temp) = create_test_db();
+        let mut instance = create_test_instance("test1");
+        instance
+            .labels
+            .insert("team".to_string(), "bridge".to_string());
+        instance
+            .annotations
+            .insert("owner".to_string(), "Bridge team, ping #bridge".to_string());
+        db.save_instance(&instance).unwrap();
+
+        let retrieved = db.get_instance("test1").unwrap();
+        assert_eq!(retrieved.labels, instance.labels);
+        assert_eq!(retrieved.annotations, instance.annotations);
+
+        // Saving replaces the previous set
+        instance.labels.clear();
+        instance.labels.insert("env".to_string(), "ci".to_string());
+        db.save_instance(&instance).unwrap();
+
+        let listed = db.list_instances().unwrap();
+        assert_eq!(listed[0].labels.len(), 1);
+        assert_eq!(listed[0].labels.get("env").map(String::as_str), Some("ci"));
+
+        // Metadata is removed with the instance
+        db.delete_instance("test1").unwrap();
+        db.save_instance(&create_test_instance("test1")).unwrap();
+        assert!(db.get_instance("test1").unwrap().labels.is_empty());
+    }
+
+    #[test]
+    fn test_secrets() {
+        let (db, temp) = create_test_db();
+        let key = SecretsKey::load_or_create(&temp.path().join("secrets.key")).unwrap();
+        let instance = create_test_instance("test1");
+        db.save_instance(&instance).unwrap();
+
+        db.set_secret(&instance.id, "FORK_API_KEY", &SecretValue::new("old"), &key)
+            .unwrap();
+        db.set_secret(
+            &instance.id,
+            "FORK_API_KEY",
+            &SecretValue::new("new-api-key"),
+            &key,
+        )
+        .unwrap();
+        db.set_secret(
+            &instance.id,
+            "MESSAGING_KEY",
+            &SecretValue::new("0x1"),
+            &key,
+        )
+        .unwrap();
+
+        let names: Vec<_> = db
+            .list_secrets(&instance.id)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, vec!["FORK_API_KEY", "MESSAGING_KEY"]);
+
+        let secrets = db.get_secrets(&instance.id, &key).unwrap();
+        assert_eq!(secrets["FORK_API_KEY"].expose(), "new-api-key");
+
+        // Stored sealed, never in the instance config
+        let conn = db.conn.lock().unwrap();
+        let sealed: Vec<u8> = conn
+            .query_row(
+                "SELECT sealed_value FROM instance_secrets WHERE name = 'FORK_API_KEY'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!String::from_utf8_lossy(&sealed).contains("new-api-key"));
+        drop(conn);
+
+        assert!(db.delete_secret(&instance.id, "MESSAGING_KEY").unwrap());
+        assert!(!db.delete_secret(&instance.id, "MESSAGING_KEY").unwrap());
+
+        // Removed with the instance
+        db.delete_instance("test1").unwrap();
+        assert!(db.list_secrets(&instance.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_secrets_delivery() {
+        let (db, _temp) = create_test_db();
+        let instance = create_test_instance("test1");
+        db.save_instance(&instance).unwrap();
+        assert_eq!(db.get_instance("test1").unwrap().secrets_delivery, None);
+
+        db.set_secrets_delivery(&instance.id, &SecretsDelivery::Pending)
+            .unwrap();
+        let failed = SecretsDelivery::Failed {
+            error: "agent unreachable".to_string(),
+        };
+        db.set_secrets_delivery(&instance.id, &failed).unwrap();
+        assert_eq!(
+            db.get_instance("test1").unwrap().secrets_delivery,
+            Some(failed)
+        );
+
+        // Saving the instance leaves it alone
+        db.save_instance(&instance).unwrap();
+        assert!(db.list_instances().unwrap()[0].secrets_delivery.is_some());
+
+        db.delete_instance("test1").unwrap();
+        db.save_instance(&instance).unwrap();
+        assert_eq!(db.get_instance("test1").unwrap().secrets_delivery, None);
+    }
+
+    #[test]
+    fn test_template_crud() {
+        let (db, _temp) = create_test_db();
+
+        let config = TemplateConfig {
+            vcpus: Some(8),
+            memory_mb: Some(16384),
+            accelerator: Some(Accelerator::Kvm),
+            extra_args: vec!["--invoke-max-steps=1000000".to_string()],
+            ..Default::default()
+        };
+        let template =
+            InstanceTemplate::new("perf".to_string(), Some("Benchmarks".to_string()), config);
+        db.create_template(&template).unwrap();
+
+        let retrieved = db.get_template("perf").unwrap();
+        assert_eq!(retrieved.description.as_deref(), Some("Benchmarks"));
+        assert_eq!(retrieved.config, template.config);
+
+        assert!(matches!(
+            db.create_template(&template),
+            Err(HypervisorError::TemplateAlreadyExists(_))
+        ));
+
+        db.create_template(&InstanceTemplate::new(
+            "small-dev".to_string(),
+            None,
+            TemplateConfig::default(),
+        ))
+        .unwrap();
+        let names: Vec<_> = db
+            .list_templates()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, vec!["perf", "small-dev"]);
+
+        db.delete_template("perf").unwrap();
+        assert!(matches!(
+            db.get_template("perf"),
+            Err(HypervisorError::TemplateNotFound(_))
+        ));
+        assert!(db.delete_template("perf").is_err());
+    }
+
     #[test]
     fn test_list_instances() {
         let (db, _temp) = create_test_db();
@@ -514,6 +1182,51 @@ mod tests {
         assert_eq!(ports.len(), 0);
     }
 
+    #[test]
+    fn test_stats_samples() {
+        let (db, _temp) = create_test_db();
+        let instance = create_test_instance("test1");
+        db.save_instance(&instance).unwrap();
+
+        for timestamp in [100, 110, 120] {
+            db.insert_stats_sample(&StatsSample {
+                instance_id: instance.id.clone(),
+                timestamp,
+                cpu_percent: Some(12.5),
+                rss_bytes: Some(512 * 1024 * 1024),
+                disk_bytes: None,
+                net_rx_bytes: None,
+                net_tx_bytes: None,
+                block_read_bytes: Some(4096),
+                block_write_bytes: Some(8192),
+            })
+            .unwrap();
+        }
+
+        // Latest samples first, returned oldest first
+        let samples = db.list_stats_samples(&instance.id, None, 2).unwrap();
+        let timestamps: Vec<i64> = samples.iter().map(|s| s.timestamp).collect();
+        assert_eq!(timestamps, vec![110, 120]);
+        assert_eq!(samples[0].rss_bytes, Some(512 * 1024 * 1024));
+        assert_eq!(samples[0].disk_bytes, None);
+
+        let samples = db.list_stats_samples(&instance.id, Some(120), 10).unwrap();
+        assert_eq!(samples.len(), 1);
+
+        let latest = db.latest_stats_samples().unwrap();
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[&instance.id].timestamp, 120);
+
+        assert_eq!(db.prune_stats_samples(115).unwrap(), 2);
+
+        // Samples go with the instance
+        db.delete_instance("test1").unwrap();
+        assert!(db
+            .list_stats_samples(&instance.id, None, 10)
+            .unwrap()
+            .is_empty());
+    }
+
     #[test]
     fn test_duplicate_instance_name() {
         let (db, _temp) = create_test_db();
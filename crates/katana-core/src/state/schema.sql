@@ -8,6 +8,7 @@ CREATE TABLE IF NOT EXISTS instances (
     serial_log TEXT,
     tee_mode BOOLEAN NOT NULL,
     expected_measurement TEXT,
+    accelerator TEXT,
     created_at INTEGER NOT NULL,
     updated_at INTEGER NOT NULL
 );
@@ -19,6 +20,53 @@ CREATE TABLE IF NOT EXISTS ports (
     FOREIGN KEY (instance_id) REFERENCES instances(id) ON DELETE CASCADE
 );
 
+CREATE TABLE IF NOT EXISTS vsock_cids (
+    cid INTEGER PRIMARY KEY,
+    instance_id TEXT NOT NULL,
+    FOREIGN KEY (instance_id) REFERENCES instances(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS instance_labels (
+    instance_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (instance_id, key),
+    FOREIGN KEY (instance_id) REFERENCES instances(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS instance_annotations (
+    instance_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (instance_id, key),
+    FOREIGN KEY (instance_id) REFERENCES instances(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS instance_secrets (
+    instance_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    sealed_value BLOB NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (instance_id, name),
+    FOREIGN KEY (instance_id) REFERENCES instances(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS instance_secrets_delivery (
+    instance_id TEXT PRIMARY KEY,
+    state TEXT NOT NULL,
+    updated_at INTEGER NOT NULL,
+    FOREIGN KEY (instance_id) REFERENCES instances(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS templates (
+    name TEXT PRIMARY KEY,
+    description TEXT,
+    config_json TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
 CREATE TABLE IF NOT EXISTS boot_components (
     instance_id TEXT NOT NULL,
     component_type TEXT NOT NULL,
@@ -28,5 +76,21 @@ CREATE TABLE IF NOT EXISTS boot_components (
     FOREIGN KEY (instance_id) REFERENCES instances(id) ON DELETE CASCADE
 );
 
+CREATE TABLE IF NOT EXISTS stats_samples (
+    instance_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    cpu_percent REAL,
+    rss_bytes INTEGER,
+    disk_bytes INTEGER,
+    net_rx_bytes INTEGER,
+    net_tx_bytes INTEGER,
+    block_read_bytes INTEGER,
+    block_write_bytes INTEGER,
+    FOREIGN KEY (instance_id) REFERENCES instances(id) ON DELETE CASCADE
+);
+
 CREATE INDEX IF NOT EXISTS idx_instances_status ON instances(status);
 CREATE INDEX IF NOT EXISTS idx_ports_instance ON ports(instance_id);
+CREATE INDEX IF NOT EXISTS idx_vsock_cids_instance ON vsock_cids(instance_id);
+CREATE INDEX IF NOT EXISTS idx_instance_labels_key ON instance_labels(key, value);
+CREATE INDEX IF NOT EXISTS idx_stats_samples_instance ON stats_samples(instance_id, timestamp);
@@ -0,0 +1,147 @@
+// Per-instance resource usage history
+//
+// CPU and memory come from /proc for the QEMU process, block I/O from QMP
+// and network traffic from the guest agent, as user networking exposes no
+// host-side counters.
+use crate::{HypervisorError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::time::Duration;
+
+/// Clock ticks per second in /proc/<pid>/stat (USER_HZ, 100 on Linux)
+const CLOCK_TICKS_PER_SEC: f64 = 100.0;
+
+/// One point in an instance's resource usage history.
+///
+/// Counters that could not be read when the sample was taken are `None`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsSample {
+    pub instance_id: String,
+    /// Unix time in seconds
+    pub timestamp: i64,
+    /// QEMU CPU usage since the previous sample, 100 being one host core
+    pub cpu_percent: Option<f64>,
+    /// QEMU resident memory
+    pub rss_bytes: Option<u64>,
+    /// Host space taken by the disk image
+    pub disk_bytes: Option<u64>,
+    /// Total bytes received by the guest
+    pub net_rx_bytes: Option<u64>,
+    /// Total bytes sent by the guest
+    pub net_tx_bytes: Option<u64>,
+    /// Total bytes read from block devices
+    pub block_read_bytes: Option<u64>,
+    /// Total bytes written to block devices
+    pub block_write_bytes: Option<u64>,
+}
+
+/// CPU time and memory of a process at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessCounters {
+    /// User plus system time in clock ticks
+    pub cpu_ticks: u64,
+    pub rss_bytes: u64,
+}
+
+impl ProcessCounters {
+    /// Read the counters of a running process from /proc
+    pub fn read(pid: i32) -> Result<Self> {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid))?;
+        let status = fs::read_to_string(format!("/proc/{}/status", pid))?;
+
+        let invalid = |file: &str| {
+            HypervisorError::QemuFailed(format!("Unexpected format of /proc/{}/{}", pid, file))
+        };
+
+        Ok(Self {
+            cpu_ticks: parse_cpu_ticks(&stat).ok_or_else(|| invalid("stat"))?,
+            rss_bytes: parse_rss_bytes(&status).ok_or_else(|| invalid("status"))?,
+        })
+    }
+
+    /// CPU usage between `earlier` and these counters, read `elapsed` apart
+    pub fn cpu_percent_since(&self, earlier: &ProcessCounters, elapsed: Duration) -> Option<f64> {
+        if elapsed.is_zero() || self.cpu_ticks < earlier.cpu_ticks {
+            return None;
+        }
+
+        let cpu_secs = (self.cpu_ticks - earlier.cpu_ticks) as f64 / CLOCK_TICKS_PER_SEC;
+        Some(cpu_secs / elapsed.as_secs_f64() * 100.0)
+    }
+}
+
+/// Host space taken by a (sparse) file such as a qcow2 image
+pub fn allocated_bytes(path: &Path) -> Result<u64> {
+    // st_blocks is always in 512-byte units
+    Ok(fs::metadata(path)?.blocks() * 512)
+}
+
+/// Sum of utime and stime from /proc/<pid>/stat
+fn parse_cpu_ticks(stat: &str) -> Option<u64> {
+    // The command name is in parentheses and may contain spaces, so count
+    // fields from the closing one: state is field 3, utime 14 and stime 15
+    let (_, rest) = stat.rsplit_once(')')?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+/// VmRSS from /proc/<pid>/status
+fn parse_rss_bytes(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_ticks() {
+        let stat = "4242 (qemu-system-x86 (x)) S 1 4242 4242 0 -1 4194624 91377 0 0 0 1500 250 0 0 20 0 5 0 1234 4133888000 108392 18446744073709551615";
+        assert_eq!(parse_cpu_ticks(stat), Some(1750));
+        assert_eq!(parse_cpu_ticks("4242 (qemu) S 1"), None);
+    }
+
+    #[test]
+    fn test_parse_rss_bytes() {
+        let status =
+            "Name:\tqemu-system-x86\nVmPeak:\t 4037000 kB\nVmRSS:\t  433568 kB\nThreads:\t5\n";
+        assert_eq!(parse_rss_bytes(status), Some(433568 * 1024));
+        assert_eq!(parse_rss_bytes("Name:\tkthreadd\n"), None);
+    }
+
+    #[test]
+    fn test_cpu_percent_since() {
+        let earlier = ProcessCounters {
+            cpu_ticks: 1000,
+            rss_bytes: 0,
+        };
+        let later = ProcessCounters {
+            cpu_ticks: 1300,
+            ..earlier
+        };
+
+        // 3 CPU seconds over 2 seconds is one and a half cores
+        assert_eq!(
+            later.cpu_percent_since(&earlier, Duration::from_secs(2)),
+            Some(150.0)
+        );
+        assert_eq!(
+            earlier.cpu_percent_since(&later, Duration::from_secs(2)),
+            None
+        );
+        assert_eq!(later.cpu_percent_since(&earlier, Duration::ZERO), None);
+    }
+
+    #[test]
+    fn test_read_own_process() {
+        let counters = ProcessCounters::read(std::process::id() as i32).unwrap();
+        assert!(counters.rss_bytes > 0);
+    }
+}
@@ -1,9 +1,19 @@
-/// SEV-SNP (Secure Encrypted Virtualization - Secure Nested Paging) configuration
-/// This is already defined in qemu/config.rs and used there.
-/// This module re-exports it for convenience and adds helper methods.
+//! SEV-SNP (Secure Encrypted Virtualization - Secure Nested Paging) configuration
+//! This is already defined in qemu/config.rs and used there.
+//! This module re-exports it for convenience and adds helper methods.
+
+use base64::Engine;
 
 pub use crate::qemu::config::SevSnpConfig;
 
+/// Guest policy bit 17 is reserved and must be set to one
+const POLICY_RESERVED_MBO: u64 = 1 << 17;
+
+/// Expected decoded sizes of the base64-encoded launch parameters
+const HOST_DATA_LEN: usize = 32;
+const ID_BLOCK_LEN: usize = 96;
+const ID_AUTH_LEN: usize = 4096;
+
 impl SevSnpConfig {
     /// Create default SEV-SNP configuration for AMD EPYC processors
     pub fn default_epyc() -> Self {
@@ -11,9 +21,29 @@ impl SevSnpConfig {
             cbitpos: 51,                  // C-bit position for AMD EPYC
             reduced_phys_bits: 1,         // Reserved physical address bits
             vcpu_type: "EPYC-v4".to_string(), // CPU model for SEV-SNP
+            policy: None,
+            host_data: None,
+            id_block: None,
+            id_auth: None,
+            kernel_hashes: false,
         }
     }
 
+    /// Create SEV-SNP configuration using the C-bit position and physical
+    /// address reduction reported by the host CPU.
+    ///
+    /// Falls back to the EPYC defaults when the host does not report SEV support.
+    pub fn for_host() -> Self {
+        let mut config = Self::default_epyc();
+
+        if let Some((cbitpos, reduced_phys_bits)) = probe_host_memory_encryption() {
+            config.cbitpos = cbitpos;
+            config.reduced_phys_bits = reduced_phys_bits;
+        }
+
+        config
+    }
+
     /// Check if SEV-SNP is available on the system
     pub fn is_available() -> bool {
         // Check for /dev/sev-guest device
@@ -40,14 +70,103 @@ impl SevSnpConfig {
             return Err("vcpu_type cannot be empty".to_string());
         }
 
+        // Validate guest policy
+        if let Some(policy) = self.policy {
+            if policy & POLICY_RESERVED_MBO == 0 {
+                return Err(format!(
+                    "Invalid policy: {:#x} (reserved bit 17 must be set)",
+                    policy
+                ));
+            }
+
+            if policy >> 32 != 0 {
+                return Err(format!(
+                    "Invalid policy: {:#x} (bits 32-63 are reserved)",
+                    policy
+                ));
+            }
+        }
+
+        // Validate base64-encoded launch parameters
+        if let Some(ref host_data) = self.host_data {
+            validate_base64_len("host_data", host_data, HOST_DATA_LEN)?;
+        }
+
+        if let Some(ref id_block) = self.id_block {
+            validate_base64_len("id_block", id_block, ID_BLOCK_LEN)?;
+        }
+
+        if let Some(ref id_auth) = self.id_auth {
+            validate_base64_len("id_auth", id_auth, ID_AUTH_LEN)?;
+        }
+
+        // The ID block is verified using the auth structure, so both are needed
+        if self.id_block.is_some() != self.id_auth.is_some() {
+            return Err("id_block and id_auth must be provided together".to_string());
+        }
+
         Ok(())
     }
 }
 
+/// Check that `value` is valid base64 decoding to exactly `expected_len` bytes
+fn validate_base64_len(field: &str, value: &str, expected_len: usize) -> Result<(), String> {
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .map_err(|e| format!("Invalid {}: not valid base64 ({})", field, e))?;
+
+    if decoded.len() != expected_len {
+        return Err(format!(
+            "Invalid {}: expected {} bytes, got {}",
+            field,
+            expected_len,
+            decoded.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Read the C-bit position and physical address bit reduction from
+/// CPUID Fn8000_001F (AMD memory encryption capabilities).
+///
+/// Returns `None` if the leaf is not implemented or SEV is not supported.
+#[cfg(target_arch = "x86_64")]
+fn probe_host_memory_encryption() -> Option<(u8, u8)> {
+    use std::arch::x86_64::__cpuid;
+
+    let max_extended_leaf = __cpuid(0x8000_0000).eax;
+    if max_extended_leaf < 0x8000_001F {
+        return None;
+    }
+
+    let leaf = __cpuid(0x8000_001F);
+
+    // EAX bit 1: SEV supported
+    if leaf.eax & (1 << 1) == 0 {
+        return None;
+    }
+
+    // EBX[5:0]: C-bit position, EBX[11:6]: physical address bit reduction
+    let cbitpos = (leaf.ebx & 0x3f) as u8;
+    let reduced_phys_bits = ((leaf.ebx >> 6) & 0x3f) as u8;
+
+    Some((cbitpos, reduced_phys_bits))
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn probe_host_memory_encryption() -> Option<(u8, u8)> {
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn encode(len: usize) -> String {
+        base64::engine::general_purpose::STANDARD.encode(vec![0u8; len])
+    }
+
     #[test]
     fn test_default_epyc_config() {
         let config = SevSnpConfig::default_epyc();
@@ -76,6 +195,54 @@ mod tests {
         assert!(config.validate().is_err());
     }
 
+    #[test]
+    fn test_validate_policy() {
+        let mut config = SevSnpConfig::default_epyc();
+
+        config.policy = Some(0x30000);
+        assert!(config.validate().is_ok());
+
+        // Reserved bit 17 not set
+        config.policy = Some(0x10000);
+        assert!(config.validate().is_err());
+
+        // Reserved upper bits set
+        config.policy = Some(0x1_0003_0000);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_launch_parameters() {
+        let mut config = SevSnpConfig::default_epyc();
+
+        config.host_data = Some(encode(32));
+        assert!(config.validate().is_ok());
+
+        config.host_data = Some(encode(16));
+        assert!(config.validate().is_err());
+
+        config.host_data = Some("not base64!".to_string());
+        assert!(config.validate().is_err());
+
+        // ID block without auth
+        config.host_data = None;
+        config.id_block = Some(encode(96));
+        assert!(config.validate().is_err());
+
+        config.id_auth = Some(encode(4096));
+        assert!(config.validate().is_ok());
+
+        config.id_auth = Some(encode(96));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_for_host_is_valid() {
+        // Probed values depend on the hardware, but must always validate
+        let config = SevSnpConfig::for_host();
+        assert!(config.validate().is_ok());
+    }
+
     #[test]
     fn test_is_available() {
         // This test will pass or fail depending on hardware
@@ -0,0 +1,353 @@
+// Guest agent control channel over virtio-vsock
+//
+// The in-guest agent listens on `AGENT_PORT` and speaks line-delimited JSON:
+// each request is a single JSON object tagged by `command`, answered by a
+// single `{"ok": bool, "data": ..., "error": ...}` line.
+use crate::{secrets::SecretValue, HypervisorError, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::time::Duration;
+
+/// Host device required to attach vhost-vsock to guests
+pub const VHOST_VSOCK_DEVICE: &str = "/dev/vhost-vsock";
+
+/// Port the guest agent listens on
+pub const AGENT_PORT: u32 = 1024;
+
+/// Lowest CID assignable to a guest (0-2 are reserved for the hypervisor and host)
+pub const FIRST_GUEST_CID: u32 = 3;
+
+/// Highest CID assignable to a guest (`u32::MAX` is `VMADDR_CID_ANY`)
+pub const LAST_GUEST_CID: u32 = u32::MAX - 1;
+
+/// `VHOST_VSOCK_SET_GUEST_CID`, i.e. `_IOW(VHOST_VIRTIO, 0x60, __u64)`
+const VHOST_VSOCK_SET_GUEST_CID: u64 = 0x4008_af60;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Check whether the host can provide vsock devices to guests
+pub fn vhost_vsock_available() -> bool {
+    Path::new(VHOST_VSOCK_DEVICE).exists()
+}
+
+/// Check whether a guest CID is taken on this host.
+///
+/// CIDs are global to the host, so another daemon or libvirt may hold one
+/// this daemon never assigned. The probe claims the CID on a throwaway
+/// vhost-vsock handle, as QEMU does at launch, and releases it on close.
+pub fn guest_cid_in_use(cid: u32) -> Result<bool> {
+    let device = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(VHOST_VSOCK_DEVICE)?;
+
+    let guest_cid = u64::from(cid);
+    // SAFETY: the fd is open for the duration of the call and the argument
+    // is the u64 the ioctl expects
+    let ret = unsafe {
+        libc::ioctl(
+            device.as_raw_fd(),
+            VHOST_VSOCK_SET_GUEST_CID as _,
+            &guest_cid as *const u64,
+        )
+    };
+    if ret == 0 {
+        return Ok(false);
+    }
+
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EADDRINUSE) {
+        Ok(true)
+    } else {
+        Err(err.into())
+    }
+}
+
+/// `guest_cid_in_use` for allocation: without vhost-vsock no CID is held,
+/// and a failed probe counts as free, leaving QEMU to report the error
+pub fn host_cid_in_use(cid: u32) -> bool {
+    vhost_vsock_available() && guest_cid_in_use(cid).unwrap_or(false)
+}
+
+/// Request sent to the guest agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum AgentRequest {
+    /// Check that the agent and Katana are up
+    Health,
+    /// Gracefully stop Katana and power off the guest
+    Shutdown,
+    /// Request an SEV-SNP attestation report
+    Attestation {
+        /// Hex-encoded 64 bytes included in the report
+        report_data: String,
+    },
+    /// Read guest resource metrics
+    Metrics,
+    /// Hand over the instance's secrets, exported to Katana as environment
+    /// variables. The guest waits for these before starting Katana when the
+    /// config drive lists secrets.
+    Secrets {
+        secrets: BTreeMap<String, SecretValue>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct AgentResponse {
+    ok: bool,
+    #[serde(default)]
+    data: serde_json::Value,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentHealth {
+    pub katana_running: bool,
+    #[serde(default)]
+    pub uptime_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentAttestation {
+    /// Hex-encoded attestation report
+    pub report: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentMetrics {
+    pub memory_total_bytes: u64,
+    pub memory_available_bytes: u64,
+    #[serde(default)]
+    pub load_average: Option<f64>,
+    #[serde(default)]
+    pub disk_used_bytes: Option<u64>,
+    /// Bytes received on the guest's network interface
+    #[serde(default)]
+    pub net_rx_bytes: Option<u64>,
+    /// Bytes sent on the guest's network interface
+    #[serde(default)]
+    pub net_tx_bytes: Option<u64>,
+}
+
+/// Host-side client for the guest agent
+#[derive(Debug, Clone)]
+pub struct VsockClient {
+    cid: u32,
+    port: u32,
+    timeout: Duration,
+}
+
+impl VsockClient {
+    pub fn new(cid: u32) -> Self {
+        Self {
+            cid,
+            port: AGENT_PORT,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_port(mut self, port: u32) -> Self {
+        self.port = port;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn cid(&self) -> u32 {
+        self.cid
+    }
+
+    /// Run a health check against the agent
+    pub async fn health(&self) -> Result<AgentHealth> {
+        self.request(AgentRequest::Health).await
+    }
+
+    /// Ask the agent to stop Katana and power off the guest
+    pub async fn shutdown(&self) -> Result<()> {
+        self.request::<serde_json::Value>(AgentRequest::Shutdown)
+            .await
+            .map(|_| ())
+    }
+
+    /// Fetch an attestation report bound to `report_data`
+    pub async fn attestation(&self, report_data: &[u8; 64]) -> Result<AgentAttestation> {
+        self.request(AgentRequest::Attestation {
+            report_data: hex::encode(report_data),
+        })
+        .await
+    }
+
+    /// Read guest metrics
+    pub async fn metrics(&self) -> Result<AgentMetrics> {
+        self.request(AgentRequest::Metrics).await
+    }
+
+    /// Deliver secrets to the agent
+    pub async fn deliver_secrets(&self, secrets: BTreeMap<String, SecretValue>) -> Result<()> {
+        self.request::<serde_json::Value>(AgentRequest::Secrets { secrets })
+            .await
+            .map(|_| ())
+    }
+
+    /// Send a request and decode the response data
+    pub async fn request<T: DeserializeOwned>(&self, request: AgentRequest) -> Result<T> {
+        let client = self.clone();
+
+        let data = tokio::task::spawn_blocking(move || {
+            let mut stream = client.connect()?;
+            exchange(&mut stream, &request)
+        })
+        .await
+        .map_err(|e| HypervisorError::Agent(format!("Agent request task failed: {}", e)))??;
+
+        serde_json::from_value(data)
+            .map_err(|e| HypervisorError::Agent(format!("Invalid agent response: {}", e)))
+    }
+
+    /// Open a blocking AF_VSOCK stream to the agent
+    fn connect(&self) -> Result<std::fs::File> {
+        use nix::sys::socket::{
+            connect, setsockopt, socket, sockopt, AddressFamily, SockFlag, SockType, VsockAddr,
+        };
+        use nix::sys::time::TimeVal;
+        use std::os::fd::AsRawFd;
+
+        let fd = socket(
+            AddressFamily::Vsock,
+            SockType::Stream,
+            SockFlag::SOCK_CLOEXEC,
+            None,
+        )
+        .map_err(|e| HypervisorError::Agent(format!("Failed to create vsock socket: {}", e)))?;
+
+        let timeout = TimeVal::new(
+            self.timeout.as_secs() as _,
+            self.timeout.subsec_micros() as _,
+        );
+        setsockopt(&fd, sockopt::SendTimeout, &timeout)
+            .and_then(|_| setsockopt(&fd, sockopt::ReceiveTimeout, &timeout))
+            .map_err(|e| HypervisorError::Agent(format!("Failed to set vsock timeout: {}", e)))?;
+
+        connect(fd.as_raw_fd(), &VsockAddr::new(self.cid, self.port)).map_err(|e| {
+            HypervisorError::Agent(format!(
+                "Failed to connect to guest agent at vsock {}:{}: {}",
+                self.cid, self.port, e
+            ))
+        })?;
+
+        Ok(std::fs::File::from(fd))
+    }
+}
+
+/// Write one request line and read one response line
+fn exchange<S: std::io::Read + Write>(
+    stream: &mut S,
+    request: &AgentRequest,
+) -> Result<serde_json::Value> {
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    stream.flush()?;
+
+    let mut response_line = String::new();
+    BufReader::new(stream).read_line(&mut response_line)?;
+
+    if response_line.is_empty() {
+        return Err(HypervisorError::Agent(
+            "Agent closed the connection without responding".to_string(),
+        ));
+    }
+
+    let response: AgentResponse = serde_json::from_str(&response_line)
+        .map_err(|e| HypervisorError::Agent(format!("Invalid agent response: {}", e)))?;
+
+    if !response.ok {
+        return Err(HypervisorError::Agent(
+            response
+                .error
+                .unwrap_or_else(|| "Agent request failed".to_string()),
+        ));
+    }
+
+    Ok(response.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+
+    /// Serve a single request on the other end of a socket pair
+    fn fake_agent(response: &'static str) -> (UnixStream, std::thread::JoinHandle<String>) {
+        let (host, mut guest) = UnixStream::pair().unwrap();
+
+        let handle = std::thread::spawn(move || {
+            let mut request = String::new();
+            BufReader::new(&mut guest).read_line(&mut request).unwrap();
+            guest.write_all(response.as_bytes()).unwrap();
+            request
+        });
+
+        (host, handle)
+    }
+
+    #[test]
+    fn test_request_encoding() {
+        assert_eq!(
+            serde_json::to_string(&AgentRequest::Health).unwrap(),
+            r#"{"command":"health"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&AgentRequest::Attestation {
+                report_data: "00".to_string()
+            })
+            .unwrap(),
+            r#"{"command":"attestation","report_data":"00"}"#
+        );
+
+        let secrets = AgentRequest::Secrets {
+            secrets: BTreeMap::from([("API_KEY".to_string(), SecretValue::new("s3cret"))]),
+        };
+        assert_eq!(
+            serde_json::to_string(&secrets).unwrap(),
+            r#"{"command":"secrets","secrets":{"API_KEY":"s3cret"}}"#
+        );
+        assert!(!format!("{:?}", secrets).contains("s3cret"));
+    }
+
+    #[test]
+    fn test_exchange_ok() {
+        let (mut stream, agent) =
+            fake_agent("{\"ok\":true,\"data\":{\"katana_running\":true,\"uptime_secs\":42}}\n");
+
+        let data = exchange(&mut stream, &AgentRequest::Health).unwrap();
+        let health: AgentHealth = serde_json::from_value(data).unwrap();
+
+        assert!(health.katana_running);
+        assert_eq!(health.uptime_secs, Some(42));
+        assert_eq!(agent.join().unwrap(), "{\"command\":\"health\"}\n");
+    }
+
+    #[test]
+    fn test_exchange_error() {
+        let (mut stream, _agent) = fake_agent("{\"ok\":false,\"error\":\"no SEV device\"}\n");
+
+        let err = exchange(&mut stream, &AgentRequest::Metrics).unwrap_err();
+        assert!(err.to_string().contains("no SEV device"));
+    }
+
+    #[test]
+    fn test_exchange_closed() {
+        let (mut stream, _agent) = fake_agent("");
+
+        assert!(exchange(&mut stream, &AgentRequest::Shutdown).is_err());
+    }
+}
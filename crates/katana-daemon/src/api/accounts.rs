@@ -0,0 +1,239 @@
+use crate::{
+    error::ApiError,
+    models::{AccountsResponse, PrefundedAccountInfo},
+    state::DaemonState,
+};
+use axum::{extract::Path, response::Json, Extension};
+use katana_core::{
+    instance::{InstanceState, InstanceStatus},
+    logs::{read_page, LogFilter, LogRotationPolicy},
+    rpc::{
+        decode_short_string, KatanaRpcClient, PredeployedAccount, PrefundedAccountsParser,
+        PREFUNDED_ACCOUNTS_HEADER,
+    },
+};
+use regex::Regex;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+    sync::Arc,
+};
+
+type ApiResult<T> = Result<T, ApiError>;
+
+/// Get the prefunded accounts of an instance
+///
+/// Asks Katana's dev RPC first and falls back to the account table Katana
+/// prints at boot, so keys are also available outside a running dev node.
+pub async fn get_accounts(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<AccountsResponse>> {
+    let instance = state.db.get_instance(&name)?;
+    let rpc_port = instance.config.rpc_port;
+
+    let rpc = match instance.status {
+        InstanceStatus::Running => Some(KatanaRpcClient::for_port(rpc_port)?),
+        _ => None,
+    };
+
+    let from_rpc = match &rpc {
+        Some(rpc) => match rpc.predeployed_accounts().await {
+            // Older nodes leave out the private keys
+            Ok(accounts) if accounts.iter().all(|a| a.private_key.is_some()) => accounts,
+            Ok(_) => Vec::new(),
+            Err(e) => {
+                tracing::debug!(name = %name, error = %e, "Failed to read accounts over RPC");
+                Vec::new()
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let (source, accounts) = if from_rpc.is_empty() {
+        // Rotated logs may need decompressing, so read them off the runtime
+        let rotation = state.log_rotation.clone();
+        let accounts =
+            tokio::task::spawn_blocking(move || accounts_from_logs(&instance, &rotation))
+                .await
+                .map_err(|e| ApiError::Internal(format!("Log reader panicked: {}", e)))?;
+        ("log", accounts)
+    } else {
+        ("rpc", from_rpc)
+    };
+
+    if accounts.is_empty() {
+        return Err(ApiError::NotFound(format!(
+            "No prefunded accounts found for instance '{}'. Is it running in dev mode?",
+            name
+        )));
+    }
+
+    let chain_id = match &rpc {
+        Some(rpc) => rpc.chain_id().await.ok(),
+        None => None,
+    };
+
+    let mut infos = Vec::with_capacity(accounts.len());
+    for account in accounts {
+        let class_hash = match &rpc {
+            Some(rpc) => rpc.class_hash_at(&account.address).await.ok(),
+            None => None,
+        };
+        infos.push(PrefundedAccountInfo {
+            address: account.address,
+            private_key: account.private_key,
+            public_key: account.public_key,
+            balance: account.balance,
+            class_hash,
+        });
+    }
+
+    Ok(Json(AccountsResponse {
+        instance_name: name,
+        rpc_url: format!("http://localhost:{}", rpc_port),
+        chain_name: chain_id.as_deref().and_then(decode_short_string),
+        chain_id,
+        source: source.to_string(),
+        accounts: infos,
+    }))
+}
+
+/// Accounts from Katana's boot output, looking at its own log before the
+/// serial console
+fn accounts_from_logs(
+    instance: &InstanceState,
+    rotation: &LogRotationPolicy,
+) -> Vec<PredeployedAccount> {
+    let logs = [Some(instance.katana_log()), instance.serial_log.clone()];
+
+    logs.into_iter()
+        .flatten()
+        .map(|path| {
+            accounts_from_log(&path, rotation).unwrap_or_else(|e| {
+                tracing::debug!(path = %path.display(), error = %e, "Failed to read accounts from log");
+                Vec::new()
+            })
+        })
+        .find(|accounts| !accounts.is_empty())
+        .unwrap_or_default()
+}
+
+/// The last account table in a log, or else in its rotated copies.
+///
+/// The live log is scanned backwards for the table's header, so only the
+/// table itself is read. Rotated copies may be compressed, so they are read
+/// through line by line, newest first.
+fn accounts_from_log(
+    path: &std::path::Path,
+    rotation: &LogRotationPolicy,
+) -> katana_core::Result<Vec<PredeployedAccount>> {
+    if path.exists() {
+        let filter = LogFilter {
+            grep: Some(Regex::new(PREFUNDED_ACCOUNTS_HEADER).expect("valid regex")),
+            ..Default::default()
+        };
+        let page = read_page(path, &filter, 1, None)?;
+        if !page.lines.is_empty() {
+            let mut file = File::open(path)?;
+            file.seek(SeekFrom::Start(page.start_offset))?;
+            return scan_accounts(BufReader::new(file), true);
+        }
+    }
+
+    for index in 1..=rotation.keep {
+        let Some(reader) = rotation.open_rotated(path, index)? else {
+            break;
+        };
+        let accounts = scan_accounts(reader, false)?;
+        if !accounts.is_empty() {
+            return Ok(accounts);
+        }
+    }
+
+    Ok(Vec::new())
+}
+
+/// Feed lines to a `PrefundedAccountsParser`, stopping at the end of the
+/// first table if `first_only` is set
+fn scan_accounts(
+    mut reader: impl BufRead,
+    first_only: bool,
+) -> katana_core::Result<Vec<PredeployedAccount>> {
+    let mut parser = PrefundedAccountsParser::default();
+    let mut buffer = Vec::new();
+
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            break;
+        }
+        parser.push_line(String::from_utf8_lossy(&buffer).trim_end());
+        if first_only && parser.is_complete() {
+            break;
+        }
+    }
+
+    Ok(parser.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn table(address: &str) -> String {
+        format!(
+            "PREFUNDED ACCOUNTS\n==================\n\n| Account address |  {}\n\
+             | Private key     |  0xaaaa\n| Public key      |  0xbbbb\n\n",
+            address
+        )
+    }
+
+    #[test]
+    fn test_accounts_from_log() {
+        let temp_dir = TempDir::new().unwrap();
+        let log = temp_dir.path().join("serial.log");
+        let rotation = LogRotationPolicy {
+            max_bytes: 1,
+            keep: 2,
+            compress: true,
+        };
+
+        // Only in a rotated copy
+        fs::write(
+            &log,
+            format!("booting\n{}katana started\n", table("0x1111")),
+        )
+        .unwrap();
+        rotation.rotate(&log).unwrap();
+        fs::write(&log, "more output\n").unwrap();
+        rotation.rotate(&log).unwrap();
+        fs::write(&log, "still running\n").unwrap();
+        let accounts = accounts_from_log(&log, &rotation).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].address, "0x1111");
+        assert_eq!(accounts[0].private_key.as_deref(), Some("0xaaaa"));
+
+        // The live log's last boot wins
+        fs::write(
+            &log,
+            format!(
+                "{}restart\n{}katana started\n",
+                table("0x2222"),
+                table("0x3333")
+            ),
+        )
+        .unwrap();
+        let accounts = accounts_from_log(&log, &rotation).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].address, "0x3333");
+
+        assert!(
+            accounts_from_log(&temp_dir.path().join("missing.log"), &rotation)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
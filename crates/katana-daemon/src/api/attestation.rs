@@ -0,0 +1,85 @@
+use crate::{error::ApiError, models::AttestationResponse, state::DaemonState};
+use axum::{
+    extract::{Path, Query},
+    response::Json,
+    Extension,
+};
+use katana_core::{instance::InstanceStatus, vsock::VsockClient};
+use serde::Deserialize;
+use std::sync::Arc;
+
+type ApiResult<T> = Result<T, ApiError>;
+
+#[derive(Debug, Deserialize)]
+pub struct AttestationQuery {
+    /// Hex-encoded 64 bytes bound into the report, e.g. a verifier's nonce
+    pub report_data: String,
+}
+
+/// Get an SEV-SNP attestation report from a TEE instance's guest agent
+/// GET /api/v1/instances/{name}/attestation?report_data=<hex>
+pub async fn get_attestation(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Path(name): Path<String>,
+    Query(params): Query<AttestationQuery>,
+) -> ApiResult<Json<AttestationResponse>> {
+    let report_data = parse_report_data(&params.report_data)?;
+
+    let instance = state.db.get_instance(&name)?;
+    if !instance.config.tee_mode {
+        return Err(ApiError::BadRequest(format!(
+            "Instance '{}' is not running in TEE mode",
+            name
+        )));
+    }
+    if !matches!(instance.status, InstanceStatus::Running) {
+        return Err(ApiError::InvalidState(format!(
+            "Cannot attest instance '{}' while it is {}",
+            name, instance.status
+        )));
+    }
+    let cid = instance.config.vsock_cid.ok_or_else(|| {
+        ApiError::InvalidState(format!("Instance '{}' has no guest agent channel", name))
+    })?;
+
+    let attestation = VsockClient::new(cid)
+        .attestation(&report_data)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to get attestation report: {}", e)))?;
+
+    Ok(Json(AttestationResponse {
+        instance_name: name,
+        report_data: hex::encode(report_data),
+        report: attestation.report,
+    }))
+}
+
+/// Decode `report_data`, with or without a `0x` prefix
+fn parse_report_data(value: &str) -> ApiResult<[u8; 64]> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    let bytes = hex::decode(value)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid report_data: {}", e)))?;
+
+    bytes.try_into().map_err(|bytes: Vec<u8>| {
+        ApiError::BadRequest(format!("report_data must be 64 bytes, got {}", bytes.len()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_report_data() {
+        let nonce = "ab".repeat(64);
+        assert_eq!(parse_report_data(&nonce).unwrap(), [0xab; 64]);
+        assert_eq!(
+            parse_report_data(&format!("0x{}", nonce)).unwrap(),
+            [0xab; 64]
+        );
+
+        assert!(parse_report_data("abcd").is_err());
+        assert!(parse_report_data(&"ab".repeat(65)).is_err());
+        assert!(parse_report_data(&"zz".repeat(64)).is_err());
+    }
+}
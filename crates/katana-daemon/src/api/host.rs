@@ -0,0 +1,18 @@
+use axum::response::Json;
+use katana_core::host::HostCapabilities;
+
+use crate::{
+    error::{ApiError, ApiResult},
+    models::{host_capabilities_to_response, HostInfoResponse},
+};
+
+/// Report host capabilities
+/// GET /api/v1/host
+pub async fn get_host_info() -> ApiResult<Json<HostInfoResponse>> {
+    // Detection shells out to qemu binaries, keep it off the async runtime
+    let caps = tokio::task::spawn_blocking(HostCapabilities::detect)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Host detection failed: {}", e)))?;
+
+    Ok(Json(host_capabilities_to_response(caps)))
+}
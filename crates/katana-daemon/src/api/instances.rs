@@ -1,10 +1,25 @@
 use axum::{
-    extract::{Extension, Path},
+    extract::{Extension, Path, Query},
     http::StatusCode,
     response::Json,
 };
 use byte_unit::Byte;
-use katana_core::instance::{BootComponents, InstanceConfig, InstanceState};
+use katana_core::{
+    instance::{
+        config_drive::{validate_env_name, write_tls},
+        fork_provider_for_guest,
+        labels::{validate_key, validate_label_value, validate_labels},
+        messaging::{DEFAULT_MESSAGING_INTERVAL, DEFAULT_MESSAGING_KEY_SECRET},
+        write_chain_spec, Accelerator, BootComponents, ChainSpecKind, InstanceConfig,
+        InstanceState, InstanceStatus, LabelSelector, Labels, MessagingChain, MessagingConfig,
+        SevSnpSettings, TemplateConfig,
+    },
+    qemu::ManagedVm,
+    tee::SevSnpConfig,
+    vsock::host_cid_in_use,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use tracing::info;
 use uuid::Uuid;
@@ -12,11 +27,21 @@ use uuid::Uuid;
 use crate::{
     error::{ApiError, ApiResult},
     models::{
-        instance_state_to_response, CreateInstanceRequest, InstanceResponse, ListInstancesResponse,
+        instance_state_to_response, sev_snp_request_to_settings, CreateInstanceRequest,
+        InstanceResponse, ListInstancesResponse, MessagingSettings, UpdateInstanceRequest,
+        UpdateLabelsRequest,
     },
     state::DaemonState,
 };
 
+// Defaults for settings left unset by both the request and its template
+const DEFAULT_VCPUS: u32 = 2;
+const DEFAULT_MEMORY: &str = "2G";
+const DEFAULT_STORAGE: &str = "10G";
+const DEFAULT_ACCOUNTS: u16 = 10;
+/// SEV-SNP guests need an EPYC CPU model rather than "host"
+pub(crate) const DEFAULT_TEE_VCPU_TYPE: &str = "EPYC-v4";
+
 /// Create a new instance
 /// POST /api/v1/instances
 pub async fn create_instance(
@@ -37,21 +62,110 @@ pub async fn create_instance(
         )));
     }
 
+    // Layer the request over the template, then fill in defaults
+    let template = match &req.template {
+        Some(name) => state.db.get_template(name)?.config,
+        None => TemplateConfig::default(),
+    };
+    let settings = template.overlay(TemplateConfig {
+        vcpus: req.vcpus,
+        memory_mb: req.memory.as_deref().map(parse_memory_mb).transpose()?,
+        storage_bytes: req
+            .storage
+            .as_deref()
+            .map(parse_storage_bytes)
+            .transpose()?,
+        accelerator: None,
+        tee_mode: req.tee,
+        vcpu_type: req.vcpu_type.clone(),
+        sev_snp: req.sev_snp.clone().map(sev_snp_request_to_settings),
+        chain_id: req.chain_id.clone(),
+        dev_mode: req.dev,
+        block_time: req.block_time,
+        accounts: req.accounts,
+        disable_fee: req.disable_fee,
+        extra_args: req.extra_args.clone(),
+        ttl_secs: req.ttl.as_deref().map(parse_duration_secs).transpose()?,
+        idle_timeout_secs: req
+            .idle_timeout
+            .as_deref()
+            .map(parse_duration_secs)
+            .transpose()?,
+    });
+
+    // Expiry: an explicit time wins over a TTL from the request or template
+    let now = chrono::Utc::now().timestamp();
+    let expires_at = match (&req.expires_at, &req.ttl) {
+        (Some(_), Some(_)) => {
+            return Err(ApiError::BadRequest(
+                "Specify either ttl or expires_at, not both".to_string(),
+            ))
+        }
+        (Some(expires_at), None) => Some(parse_expires_at(expires_at, now)?),
+        (None, _) => settings.ttl_secs.map(|ttl| now.saturating_add(ttl as i64)),
+    };
+
+    let tee = settings.tee_mode.unwrap_or(false);
+    let vcpu_type = resolve_vcpu_type(tee, settings.vcpu_type)?;
+
+    // Validate metadata
+    validate_labels(&req.labels).map_err(ApiError::BadRequest)?;
+    validate_annotation_keys(req.annotations.keys())?;
+
+    // Resolve and validate SEV-SNP launch settings
+    let sev_snp = build_sev_snp_config(tee, &vcpu_type, settings.sev_snp.as_ref())?;
+
+    check_fork(req.fork_provider.as_deref(), req.fork_block)?;
+    let messaging = req
+        .messaging
+        .map(|m| build_messaging_config(&state, &req.name, m))
+        .transpose()?;
+
+    let chain_spec = match (&req.genesis, &req.chain_spec) {
+        (Some(_), Some(_)) => {
+            return Err(ApiError::BadRequest(
+                "Specify either genesis or chain_spec, not both".to_string(),
+            ))
+        }
+        (Some(genesis), None) => Some((ChainSpecKind::Genesis, genesis)),
+        (None, Some(chain_spec)) => Some((ChainSpecKind::Chain, chain_spec)),
+        (None, None) => None,
+    };
+
+    validate_env(&req.env)?;
+    let tls = match (&req.tls_cert, &req.tls_key) {
+        (Some(cert), Some(key)) => Some((cert, key)),
+        (None, None) => None,
+        _ => {
+            return Err(ApiError::BadRequest(
+                "tls_cert and tls_key must be given together".to_string(),
+            ))
+        }
+    };
+
+    // Parse accelerator, falling back to the template's
+    let accelerator = match req.accelerator.as_deref() {
+        Some(value) => parse_accelerator(Some(value), tee)?,
+        None => {
+            let accelerator = template.accelerator.unwrap_or_default();
+            check_accelerator(accelerator, tee)?;
+            accelerator
+        }
+    };
+
     // Generate instance ID
     let instance_id = Uuid::new_v4().to_string();
 
-    // Parse memory size
-    let memory_bytes = Byte::parse_str(&req.memory, true)
-        .map_err(|e| ApiError::BadRequest(format!("Invalid memory size '{}': {}", req.memory, e)))?
-        .as_u64();
-    let memory_mb = memory_bytes / 1024 / 1024;
-
-    // Parse storage size
-    let storage_bytes = Byte::parse_str(&req.storage, true)
-        .map_err(|e| {
-            ApiError::BadRequest(format!("Invalid storage size '{}': {}", req.storage, e))
-        })?
-        .as_u64();
+    // Resource sizes
+    let vcpus = settings.vcpus.unwrap_or(DEFAULT_VCPUS);
+    let memory_mb = match settings.memory_mb {
+        Some(memory_mb) => memory_mb,
+        None => parse_memory_mb(DEFAULT_MEMORY)?,
+    };
+    let storage_bytes = match settings.storage_bytes {
+        Some(storage_bytes) => storage_bytes,
+        None => parse_storage_bytes(DEFAULT_STORAGE)?,
+    };
 
     // Allocate port
     let rpc_port = if let Some(port) = req.port {
@@ -76,50 +190,86 @@ pub async fn create_instance(
     // Get paths
     let paths = state.storage.get_paths(&instance_id);
 
-    // Build extra args
-    let mut extra_args = req.extra_args.clone();
-    if req.tee {
-        extra_args.push("--tee.provider".to_string());
-        extra_args.push("sev-snp".to_string());
+    // Files too large for the kernel cmdline go on the config drive
+    let written = chain_spec
+        .map(|(kind, contents)| write_chain_spec(&paths.config_dir, kind, contents))
+        .transpose()
+        .and_then(|_| {
+            tls.map(|(cert, key)| write_tls(&paths.config_dir, cert, key))
+                .transpose()
+        });
+    if let Err(e) = written {
+        let _ = state.storage.delete_instance_storage(&instance_id);
+        return Err(ApiError::BadRequest(e.to_string()));
     }
 
     // Create instance configuration
     let config = InstanceConfig {
-        vcpus: req.vcpus,
+        vcpus,
         memory_mb,
         storage_bytes,
         rpc_port,
         metrics_port: None,
-        tee_mode: req.tee,
-        vcpu_type: if req.tee {
-            req.vcpu_type.clone()
-        } else {
-            "host".to_string()
-        },
+        vsock_cid: None,
+        accelerator,
+        balloon: req.balloon.unwrap_or(false),
+        tee_mode: tee,
+        vcpu_type,
         expected_measurement: None,
+        sev_snp,
         kernel_path: boot_components.kernel_path.clone(),
         initrd_path: boot_components.initrd_path.clone(),
         ovmf_path: Some(boot_components.ovmf_path.clone()),
         data_dir: paths.disk_image.parent().unwrap().to_path_buf(),
         disk_image: Some(paths.disk_image.clone()),
-        chain_id: req.chain_id,
-        dev_mode: req.dev,
-        block_time: req.block_time,
-        accounts: req.accounts.or(Some(10)),
-        disable_fee: req.disable_fee,
-        extra_args,
+        chain_id: settings.chain_id,
+        dev_mode: settings.dev_mode.unwrap_or(true),
+        block_time: settings.block_time,
+        accounts: settings.accounts.or(Some(DEFAULT_ACCOUNTS)),
+        disable_fee: settings.disable_fee.unwrap_or(false),
+        extra_args: settings.extra_args,
+        fork_provider: req.fork_provider,
+        fork_block: req.fork_block,
+        messaging,
+        chain_spec: chain_spec.map(|(kind, _)| kind),
+        env: req.env,
+        config_drive_args: req.config_drive_args.unwrap_or(false),
+        expires_at,
+        idle_timeout_secs: settings.idle_timeout_secs,
     };
 
     // Create instance state
     let mut instance_state = InstanceState::new(instance_id.clone(), req.name.clone(), config);
     instance_state.serial_log = Some(paths.serial_log.clone());
     instance_state.qmp_socket = Some(paths.qmp_socket.clone());
+    instance_state.labels = req.labels;
+    instance_state.annotations = req.annotations;
 
     // Save to database
-    state.db.save_instance(&instance_state)?;
+    if let Err(e) = state.db.save_instance(&instance_state) {
+        let _ = state.storage.delete_instance_storage(&instance_id);
+        return Err(e.into());
+    }
 
-    // Reserve port
-    state.db.allocate_port(&instance_id, rpc_port, "rpc")?;
+    // Reserve port and assign a guest CID for the vsock control channel
+    let registered = state
+        .db
+        .allocate_port(&instance_id, rpc_port, "rpc")
+        .and_then(|_| {
+            state
+                .db
+                .allocate_vsock_cid(&instance_id, state.vsock_cid_base, host_cid_in_use)
+        })
+        .and_then(|cid| {
+            instance_state.config.vsock_cid = Some(cid);
+            state.db.save_instance(&instance_state)
+        });
+    if let Err(e) = registered {
+        // Deleting the row releases its port and CID
+        let _ = state.db.delete_instance(&req.name);
+        let _ = state.storage.delete_instance_storage(&instance_id);
+        return Err(e.into());
+    }
 
     info!(id = %instance_id, name = %req.name, "Instance created successfully");
 
@@ -129,14 +279,332 @@ pub async fn create_instance(
     ))
 }
 
-/// List all instances
+/// Update a stopped instance's resources and Katana settings
+/// PUT /api/v1/instances/{name}
+pub async fn update_instance(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Path(name): Path<String>,
+    Json(req): Json<UpdateInstanceRequest>,
+) -> ApiResult<Json<InstanceResponse>> {
+    info!(name = %name, "Updating instance via API");
+
+    let mut instance_state = state.db.get_instance(&name)?;
+
+    if !matches!(
+        instance_state.status,
+        InstanceStatus::Created | InstanceStatus::Stopped | InstanceStatus::Failed { .. }
+    ) {
+        return Err(ApiError::InvalidState(format!(
+            "Cannot update instance '{}' from state: {}. Stop it first.",
+            name, instance_state.status
+        )));
+    }
+
+    check_fork(req.fork_provider.as_deref(), req.fork_block)?;
+    validate_env(&req.env)?;
+    let messaging = req
+        .messaging
+        .map(|m| build_messaging_config(&state, &name, m))
+        .transpose()?;
+
+    let config = &mut instance_state.config;
+    config.vcpus = req.vcpus;
+    config.memory_mb = parse_memory_mb(&req.memory)?;
+    config.accelerator = parse_accelerator(req.accelerator.as_deref(), config.tee_mode)?;
+    config.dev_mode = req.dev;
+    config.chain_id = req.chain_id;
+    config.block_time = req.block_time;
+    config.accounts = req.accounts.or(Some(DEFAULT_ACCOUNTS));
+    config.disable_fee = req.disable_fee;
+    config.extra_args = req.extra_args;
+    config.fork_provider = req.fork_provider;
+    config.fork_block = req.fork_block;
+    config.messaging = messaging;
+    config.env = req.env;
+
+    state.db.save_instance(&instance_state)?;
+
+    info!(name = %name, "Instance updated successfully");
+
+    Ok(Json(instance_state_to_response(instance_state)))
+}
+
+/// Add, change or remove labels and annotations
+/// PATCH /api/v1/instances/{name}/labels
+pub async fn update_labels(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Path(name): Path<String>,
+    Json(req): Json<UpdateLabelsRequest>,
+) -> ApiResult<Json<InstanceResponse>> {
+    info!(name = %name, "Updating labels via API");
+
+    let mut instance_state = state.db.get_instance(&name)?;
+
+    for (key, value) in &req.labels {
+        validate_key(key).map_err(ApiError::BadRequest)?;
+        if let Some(value) = value {
+            validate_label_value(value).map_err(ApiError::BadRequest)?;
+        }
+    }
+    validate_annotation_keys(req.annotations.keys())?;
+
+    apply_changes(&mut instance_state.labels, req.labels);
+    apply_changes(&mut instance_state.annotations, req.annotations);
+
+    state.db.save_instance(&instance_state)?;
+
+    Ok(Json(instance_state_to_response(instance_state)))
+}
+
+/// Set keys mapped to a value and remove keys mapped to `None`
+fn apply_changes(target: &mut Labels, changes: BTreeMap<String, Option<String>>) {
+    for (key, value) in changes {
+        match value {
+            Some(value) => target.insert(key, value),
+            None => target.remove(&key),
+        };
+    }
+}
+
+fn validate_annotation_keys<'a>(keys: impl IntoIterator<Item = &'a String>) -> ApiResult<()> {
+    for key in keys {
+        validate_key(key).map_err(ApiError::BadRequest)?;
+    }
+    Ok(())
+}
+
+/// Parse a non-zero duration such as "30m" or "2h 30m" into seconds
+pub(crate) fn parse_duration_secs(value: &str) -> ApiResult<u64> {
+    let duration = humantime::parse_duration(value)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid duration '{}': {}", value, e)))?;
+
+    if duration.as_secs() == 0 {
+        return Err(ApiError::BadRequest(format!(
+            "Invalid duration '{}': must be at least one second",
+            value
+        )));
+    }
+
+    Ok(duration.as_secs())
+}
+
+/// Parse an RFC 3339 expiry time, which must be in the future
+fn parse_expires_at(value: &str, now: i64) -> ApiResult<i64> {
+    let expires_at = chrono::DateTime::parse_from_rfc3339(value)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid expires_at '{}': {}", value, e)))?
+        .timestamp();
+
+    if expires_at <= now {
+        return Err(ApiError::BadRequest(format!(
+            "expires_at '{}' is in the past",
+            value
+        )));
+    }
+
+    Ok(expires_at)
+}
+
+/// Parse a memory size such as "4G" into MiB
+pub(crate) fn parse_memory_mb(memory: &str) -> ApiResult<u64> {
+    let memory_bytes = Byte::parse_str(memory, true)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid memory size '{}': {}", memory, e)))?
+        .as_u64();
+    Ok(memory_bytes / 1024 / 1024)
+}
+
+/// Parse a storage size such as "10G" into bytes
+pub(crate) fn parse_storage_bytes(storage: &str) -> ApiResult<u64> {
+    Ok(Byte::parse_str(storage, true)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid storage size '{}': {}", storage, e)))?
+        .as_u64())
+}
+
+/// The requested vCPU model, or "host" ("EPYC-v4" for TEE instances)
+fn resolve_vcpu_type(tee: bool, vcpu_type: Option<String>) -> ApiResult<String> {
+    match vcpu_type {
+        Some(vcpu_type) if vcpu_type.trim().is_empty() => Err(ApiError::BadRequest(
+            "vcpu_type cannot be empty".to_string(),
+        )),
+        Some(vcpu_type) => Ok(vcpu_type),
+        None if tee => Ok(DEFAULT_TEE_VCPU_TYPE.to_string()),
+        None => Ok("host".to_string()),
+    }
+}
+
+/// Parse the requested accelerator, defaulting to auto
+pub(crate) fn parse_accelerator(value: Option<&str>, tee: bool) -> ApiResult<Accelerator> {
+    let accelerator = match value {
+        Some(value) => value.parse::<Accelerator>().map_err(ApiError::BadRequest)?,
+        None => Accelerator::Auto,
+    };
+
+    check_accelerator(accelerator, tee)?;
+
+    Ok(accelerator)
+}
+
+/// TEE mode needs KVM, so it cannot be combined with forced TCG
+pub(crate) fn check_accelerator(accelerator: Accelerator, tee: bool) -> ApiResult<()> {
+    if tee && accelerator == Accelerator::Tcg {
+        return Err(ApiError::BadRequest(
+            "TEE mode requires KVM acceleration".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// A fork provider must be an http(s) URL, and a fork block needs a provider
+fn check_fork(provider: Option<&str>, block: Option<u64>) -> ApiResult<()> {
+    match provider {
+        Some(provider) => {
+            fork_provider_for_guest(provider).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        }
+        None if block.is_some() => {
+            return Err(ApiError::BadRequest(
+                "fork_block requires fork_provider".to_string(),
+            ))
+        }
+        None => {}
+    }
+
+    Ok(())
+}
+
+/// Validate messaging settings. A sibling L1 instance must exist; its RPC
+/// port is looked up again at every start.
+fn build_messaging_config(
+    state: &DaemonState,
+    name: &str,
+    settings: MessagingSettings,
+) -> ApiResult<MessagingConfig> {
+    let chain = match settings.chain.as_deref() {
+        Some(chain) => chain
+            .parse::<MessagingChain>()
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?,
+        None => MessagingChain::default(),
+    };
+
+    let messaging = MessagingConfig {
+        chain,
+        rpc_url: settings.rpc_url,
+        l1_instance: settings.l1_instance,
+        contract_address: settings.contract_address,
+        sender_address: settings.sender_address,
+        interval_secs: settings.interval_secs.unwrap_or(DEFAULT_MESSAGING_INTERVAL),
+        from_block: settings.from_block.unwrap_or(0),
+        key_secret: settings
+            .key_secret
+            .unwrap_or_else(|| DEFAULT_MESSAGING_KEY_SECRET.to_string()),
+    };
+    messaging
+        .validate()
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    if let Some(l1) = &messaging.l1_instance {
+        if l1 == name {
+            return Err(ApiError::BadRequest(
+                "An instance can't be its own messaging L1".to_string(),
+            ));
+        }
+        if !state.db.instance_exists(l1)? {
+            return Err(ApiError::BadRequest(format!(
+                "Messaging L1 instance '{}' not found",
+                l1
+            )));
+        }
+    }
+
+    Ok(messaging)
+}
+
+/// Environment variable names must be usable from a shell
+fn validate_env(env: &BTreeMap<String, String>) -> ApiResult<()> {
+    for name in env.keys() {
+        validate_env_name(name).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// Build the SEV-SNP configuration for a TEE instance.
+///
+/// Values not provided in the request are probed from the host CPU.
+pub(crate) fn build_sev_snp_config(
+    tee: bool,
+    vcpu_type: &str,
+    sev_snp: Option<&SevSnpSettings>,
+) -> ApiResult<Option<SevSnpConfig>> {
+    if !tee {
+        if sev_snp.is_some() {
+            return Err(ApiError::BadRequest(
+                "SEV-SNP settings require TEE mode".to_string(),
+            ));
+        }
+        return Ok(None);
+    }
+
+    let mut config = SevSnpConfig {
+        vcpu_type: vcpu_type.to_string(),
+        ..SevSnpConfig::for_host()
+    };
+
+    if let Some(opts) = sev_snp {
+        if let Some(cbitpos) = opts.cbitpos {
+            config.cbitpos = cbitpos;
+        }
+        if let Some(reduced_phys_bits) = opts.reduced_phys_bits {
+            config.reduced_phys_bits = reduced_phys_bits;
+        }
+        config.policy = opts.policy;
+        config.host_data = opts.host_data.clone();
+        config.id_block = opts.id_block.clone();
+        config.id_auth = opts.id_auth.clone();
+        config.kernel_hashes = opts.kernel_hashes.unwrap_or(false);
+    }
+
+    config
+        .validate()
+        .map_err(|e| ApiError::BadRequest(format!("Invalid SEV-SNP configuration: {}", e)))?;
+
+    Ok(Some(config))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteQuery {
+    /// Kill the VM and clean up regardless of its state
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListInstancesQuery {
+    /// Label selector, e.g. "team=bridge,env=ci"
+    #[serde(default)]
+    pub selector: Option<String>,
+}
+
+/// List all instances, optionally filtered by label selector
 /// GET /api/v1/instances
 pub async fn list_instances(
     Extension(state): Extension<Arc<DaemonState>>,
+    Query(query): Query<ListInstancesQuery>,
 ) -> ApiResult<Json<ListInstancesResponse>> {
-    info!("Listing instances via API");
+    info!(selector = ?query.selector, "Listing instances via API");
 
-    let instances = state.db.list_instances()?;
+    let selector = query
+        .selector
+        .as_deref()
+        .unwrap_or_default()
+        .parse::<LabelSelector>()
+        .map_err(ApiError::BadRequest)?;
+
+    let instances: Vec<_> = state
+        .db
+        .list_instances()?
+        .into_iter()
+        .filter(|instance| selector.matches(&instance.labels))
+        .collect();
     let total = instances.len();
 
     let response = ListInstancesResponse {
@@ -163,25 +631,29 @@ pub async fn get_instance(
 }
 
 /// Delete instance
-/// DELETE /api/v1/instances/{name}
+/// DELETE /api/v1/instances/{name}?force=true
 pub async fn delete_instance(
     Extension(state): Extension<Arc<DaemonState>>,
     Path(name): Path<String>,
+    Query(params): Query<DeleteQuery>,
 ) -> ApiResult<StatusCode> {
-    info!(name = %name, "Deleting instance via API");
+    info!(name = %name, force = params.force, "Deleting instance via API");
 
     let instance = state.db.get_instance(&name)?;
 
-    // TODO: Add force parameter via query string
-    // For now, don't allow deletion of running instances
-    if matches!(
+    if params.force {
+        // Kill QEMU and remove its runtime files, whatever state it is in
+        ManagedVm::force_cleanup(&instance.id, &state.db)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to clean up VM: {}", e)))?;
+    } else if !matches!(
         instance.status,
-        katana_core::instance::InstanceStatus::Running
-            | katana_core::instance::InstanceStatus::Starting
+        InstanceStatus::Created | InstanceStatus::Stopped | InstanceStatus::Failed { .. }
     ) {
-        return Err(ApiError::BadRequest(format!(
-            "Cannot delete running instance '{}'. Stop it first.",
-            name
+        // Any other state still has a QEMU process holding ports and a CID
+        return Err(ApiError::InvalidState(format!(
+            "Cannot delete instance '{}' while it is {}. Stop it first or use force.",
+            name, instance.status
         )));
     }
 
@@ -195,3 +667,105 @@ pub async fn delete_instance(
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_vcpu_type() {
+        assert_eq!(resolve_vcpu_type(false, None).unwrap(), "host");
+        assert_eq!(resolve_vcpu_type(true, None).unwrap(), "EPYC-v4");
+
+        // Honored with and without TEE
+        assert_eq!(
+            resolve_vcpu_type(false, Some("Skylake-Server".to_string())).unwrap(),
+            "Skylake-Server"
+        );
+        assert_eq!(
+            resolve_vcpu_type(true, Some("EPYC-Milan".to_string())).unwrap(),
+            "EPYC-Milan"
+        );
+
+        assert!(resolve_vcpu_type(false, Some(" ".to_string())).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_paused_instance_without_force() {
+        let dir = TempDir::new().unwrap();
+        let state = Arc::new(DaemonState::for_tests(dir.path()));
+
+        let config = InstanceConfig {
+            data_dir: dir.path().join("instances").join("paused-id"),
+            ..Default::default()
+        };
+        let mut instance =
+            InstanceState::new("paused-id".to_string(), "paused".to_string(), config);
+        instance.status = InstanceStatus::Paused;
+        state.db.save_instance(&instance).unwrap();
+
+        let err = delete_instance(
+            Extension(state.clone()),
+            Path("paused".to_string()),
+            Query(DeleteQuery { force: false }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.into_response().status(), StatusCode::CONFLICT);
+
+        // Nothing was removed
+        assert!(state.db.get_instance("paused").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_force_delete_releases_resources() {
+        let dir = TempDir::new().unwrap();
+        let state = Arc::new(DaemonState::for_tests(dir.path()));
+
+        for (id, status) in [
+            ("starting-id", InstanceStatus::Starting),
+            (
+                "failed-id",
+                InstanceStatus::Failed {
+                    error: "boom".to_string(),
+                },
+            ),
+        ] {
+            let data_dir = state.storage.get_paths(id).instance_dir;
+            std::fs::create_dir_all(&data_dir).unwrap();
+            std::fs::write(data_dir.join("katana-data.qcow2"), b"disk").unwrap();
+
+            let config = InstanceConfig {
+                data_dir: data_dir.clone(),
+                ..Default::default()
+            };
+            let name = id.trim_end_matches("-id").to_string();
+            let mut instance = InstanceState::new(id.to_string(), name.clone(), config);
+            instance.status = status;
+            // Left behind by a QEMU process that is long gone
+            instance.vm_pid = Some(i32::MAX);
+            state.db.save_instance(&instance).unwrap();
+            state.db.allocate_port(id, 5050, "rpc").unwrap();
+            state
+                .db
+                .allocate_vsock_cid(id, state.vsock_cid_base, |_| false)
+                .unwrap();
+
+            let status = delete_instance(
+                Extension(state.clone()),
+                Path(name.clone()),
+                Query(DeleteQuery { force: true }),
+            )
+            .await
+            .unwrap();
+            assert_eq!(status, StatusCode::NO_CONTENT);
+
+            assert!(state.db.get_instance(&name).is_err());
+            assert!(!data_dir.exists());
+            assert!(state.db.get_allocated_ports().unwrap().is_empty());
+            assert_eq!(state.db.get_vsock_cid(id).unwrap(), None);
+        }
+    }
+}
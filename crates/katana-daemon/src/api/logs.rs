@@ -1,15 +1,23 @@
-use crate::{error::ApiError, state::DaemonState};
+use crate::{
+    error::ApiError,
+    models::{log_line_to_record, LogsResponse},
+    state::DaemonState,
+};
 use axum::{
     extract::{Path, Query},
     response::{sse::{Event, KeepAlive}, Json, Sse},
     Extension,
 };
-use futures::stream::Stream;
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use katana_core::instance::InstanceState;
+use katana_core::logs::{level_severity, read_page, LogFilter, LogPage};
+use regex::Regex;
+use serde::Deserialize;
 use serde_json::json;
 use std::convert::Infallible;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
@@ -18,24 +26,161 @@ use std::os::unix::fs::MetadataExt;
 
 type ApiResult<T> = Result<T, ApiError>;
 
+/// Output format for log lines
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Raw lines only
+    #[default]
+    Text,
+    /// Also parse each line into a structured record
+    Json,
+}
+
+/// Which of an instance's logs to read
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogSource {
+    /// Serial console: firmware, kernel and init output
+    Kernel,
+    /// Katana's stdout/stderr from its virtio-serial port
+    Katana,
+    /// Both, kernel lines first
+    #[default]
+    All,
+}
+
+impl LogSource {
+    fn as_str(self) -> &'static str {
+        match self {
+            LogSource::Kernel => "kernel",
+            LogSource::Katana => "katana",
+            LogSource::All => "all",
+        }
+    }
+}
+
+/// Log files to read for `source`, skipping missing ones when reading both
+fn log_paths(instance: &InstanceState, source: LogSource) -> ApiResult<Vec<(LogSource, PathBuf)>> {
+    let kernel = || {
+        instance
+            .serial_log
+            .clone()
+            .ok_or_else(|| ApiError::NotFound("Instance has no serial log".to_string()))
+    };
+
+    let paths = match source {
+        LogSource::Kernel => vec![(LogSource::Kernel, kernel()?)],
+        LogSource::Katana => vec![(LogSource::Katana, instance.katana_log())],
+        LogSource::All => {
+            let kernel = instance
+                .serial_log
+                .clone()
+                .map(|path| (LogSource::Kernel, path));
+            let paths: Vec<_> = kernel
+                .into_iter()
+                .chain([(LogSource::Katana, instance.katana_log())])
+                .filter(|(_, path)| path.exists())
+                .collect();
+            if paths.is_empty() {
+                return Err(ApiError::NotFound(
+                    "No log files found. Instance may not have been started yet.".to_string(),
+                ));
+            }
+            return Ok(paths);
+        }
+    };
+
+    if let Some((_, path)) = paths.iter().find(|(_, path)| !path.exists()) {
+        return Err(ApiError::NotFound(format!(
+            "Log file not found: {}. Instance may not have been started yet.",
+            path.display()
+        )));
+    }
+
+    Ok(paths)
+}
+
 #[derive(Debug, Deserialize)]
 pub struct LogsQuery {
+    /// Maximum number of lines to return
     #[serde(default = "default_tail")]
     pub tail: usize,
+    #[serde(default)]
+    pub format: LogFormat,
+    #[serde(default)]
+    pub source: LogSource,
+    /// Only lines at or after this time (RFC 3339, or a duration such as "10m" ago)
+    pub since: Option<String>,
+    /// Only lines at or before this time (RFC 3339, or a duration ago)
+    pub until: Option<String>,
+    /// Only lines matching this regex
+    pub grep: Option<String>,
+    /// Only lines at this level or more severe
+    pub level: Option<String>,
+    /// Only lines before this byte offset, for paging backwards. Requires a
+    /// single source
+    pub before: Option<u64>,
 }
 
 fn default_tail() -> usize {
     100
 }
 
-#[derive(Debug, Serialize)]
-pub struct LogsResponse {
-    pub instance_name: String,
-    pub lines: Vec<String>,
-    pub total_lines: usize,
+/// Build a line filter from query parameters
+fn log_filter(
+    since: Option<&str>,
+    until: Option<&str>,
+    grep: Option<&str>,
+    level: Option<&str>,
+) -> ApiResult<LogFilter> {
+    let grep = grep
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| ApiError::BadRequest(format!("Invalid grep pattern: {}", e)))?;
+
+    let min_level = level
+        .map(|level| {
+            level_severity(level).ok_or_else(|| {
+                ApiError::BadRequest(format!(
+                    "Invalid level '{}': expected trace, debug, info, warn or error",
+                    level
+                ))
+            })
+        })
+        .transpose()?;
+
+    Ok(LogFilter {
+        since: since.map(parse_time).transpose()?,
+        until: until.map(parse_time).transpose()?,
+        grep,
+        min_level,
+    })
+}
+
+/// Parse an RFC 3339 time, or a duration meaning that long ago
+pub(crate) fn parse_time(value: &str) -> ApiResult<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let ago = humantime::parse_duration(value).map_err(|_| {
+        ApiError::BadRequest(format!(
+            "Invalid time '{}': expected RFC 3339 or a duration such as \"10m\"",
+            value
+        ))
+    })?;
+    let ago = chrono::Duration::from_std(ago)
+        .map_err(|_| ApiError::BadRequest(format!("Duration '{}' is too long", value)))?;
+
+    Ok(Utc::now() - ago)
 }
 
 /// Get logs from an instance
+///
+/// Lines are read backwards from the end of the log (or from `before`), so
+/// large logs are not loaded into memory. With `source=all` the latest
+/// Katana lines take precedence and the kernel log fills up the rest.
 pub async fn get_logs(
     Extension(state): Extension<Arc<DaemonState>>,
     Path(name): Path<String>,
@@ -44,44 +189,72 @@ pub async fn get_logs(
     // Load instance from database
     let instance_state = state.db.get_instance(&name)?;
 
-    let log_path = instance_state
-        .serial_log
-        .ok_or_else(|| ApiError::NotFound("Instance has no serial log".to_string()))?;
-
-    if !log_path.exists() {
-        return Err(ApiError::NotFound(format!(
-            "Log file not found: {}. Instance may not have been started yet.",
-            log_path.display()
-        )));
+    if params.source == LogSource::All && params.before.is_some() {
+        return Err(ApiError::BadRequest(
+            "Paging with 'before' requires a single log source (kernel or katana)".to_string(),
+        ));
     }
 
-    // Read log file
-    let file = File::open(&log_path).map_err(|e| {
-        ApiError::Internal(format!("Failed to open log file: {}", e))
-    })?;
-
-    let reader = BufReader::new(file);
-    let all_lines: Vec<String> = reader
-        .lines()
-        .collect::<Result<_, _>>()
-        .map_err(|e| ApiError::Internal(format!("Failed to read log file: {}", e)))?;
-
-    let total_lines = all_lines.len();
+    let mut paths = log_paths(&instance_state, params.source)?;
+
+    let filter = log_filter(
+        params.since.as_deref(),
+        params.until.as_deref(),
+        params.grep.as_deref(),
+        params.level.as_deref(),
+    )?;
+
+    // Scanning a large log for matches can take a while
+    let tail = params.tail;
+    let before = params.before;
+    let pages = tokio::task::spawn_blocking(move || {
+        let mut pages: Vec<(LogSource, LogPage)> = Vec::new();
+        let mut remaining = tail;
+        // Katana first, so its latest lines are kept
+        paths.reverse();
+        for (source, path) in paths {
+            let page = read_page(&path, &filter, remaining, before)?;
+            remaining -= page.lines.len();
+            pages.push((source, page));
+        }
+        pages.reverse();
+        katana_core::Result::Ok(pages)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("Log reader panicked: {}", e)))?
+    .map_err(|e| ApiError::Internal(format!("Failed to read log file: {}", e)))?;
+
+    let single = match pages.as_slice() {
+        [(_, page)] if params.source != LogSource::All => Some(page),
+        _ => None,
+    };
 
-    // Get last N lines
-    let start_idx = if all_lines.len() > params.tail {
-        all_lines.len() - params.tail
-    } else {
-        0
+    let mut response = LogsResponse {
+        instance_name: name,
+        lines_scanned: pages.iter().map(|(_, page)| page.lines_scanned).sum(),
+        lines: Vec::new(),
+        records: None,
+        start_offset: single.map(|page| page.start_offset),
+        end_offset: single.map(|page| page.end_offset),
+        has_more: pages.iter().any(|(_, page)| page.has_more),
     };
 
-    let lines = all_lines[start_idx..].to_vec();
+    let mut records = Vec::new();
+    for (source, page) in pages {
+        if params.format == LogFormat::Json {
+            records.extend(
+                page.lines
+                    .iter()
+                    .map(|line| log_line_to_record(line, source.as_str())),
+            );
+        }
+        response.lines.extend(page.lines);
+    }
+    if params.format == LogFormat::Json {
+        response.records = Some(records);
+    }
 
-    Ok(Json(LogsResponse {
-        instance_name: name,
-        lines,
-        total_lines,
-    }))
+    Ok(Json(response))
 }
 
 fn default_stream_tail() -> usize {
@@ -92,31 +265,61 @@ fn default_stream_tail() -> usize {
 pub struct StreamLogsQuery {
     #[serde(default = "default_stream_tail")]
     pub tail: usize,
+    #[serde(default)]
+    pub format: LogFormat,
+    #[serde(default)]
+    pub source: LogSource,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub grep: Option<String>,
+    pub level: Option<String>,
+    /// Stream from this byte offset (e.g. a previous `end_offset`) instead
+    /// of sending the last `tail` lines. Requires a single source
+    pub offset: Option<u64>,
 }
 
 /// Stream logs via Server-Sent Events
+///
+/// With `source=all` both logs are streamed and each event says which one
+/// it came from.
 pub async fn stream_logs(
     Extension(state): Extension<Arc<DaemonState>>,
     Path(name): Path<String>,
     Query(params): Query<StreamLogsQuery>,
-) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+) -> Result<Sse<BoxStream<'static, Result<Event, Infallible>>>, ApiError> {
     // Load instance from database
     let instance_state = state.db.get_instance(&name)?;
 
-    let log_path = instance_state
-        .serial_log
-        .ok_or_else(|| ApiError::NotFound("Instance has no serial log".to_string()))?;
-
-    // Verify log file exists
-    if !log_path.exists() {
-        return Err(ApiError::NotFound(format!(
-            "Log file not found: {}. Instance may not have been started yet.",
-            log_path.display()
-        )));
+    if params.source == LogSource::All && params.offset.is_some() {
+        return Err(ApiError::BadRequest(
+            "Streaming from an 'offset' requires a single log source (kernel or katana)"
+                .to_string(),
+        ));
     }
 
-    // Create SSE stream
-    let stream = create_log_stream(log_path, params.tail, name);
+    let paths = log_paths(&instance_state, params.source)?;
+
+    let filter = log_filter(
+        params.since.as_deref(),
+        params.until.as_deref(),
+        params.grep.as_deref(),
+        params.level.as_deref(),
+    )?;
+
+    // Create one SSE stream per log and interleave them as lines arrive
+    let streams = paths.into_iter().map(|(source, path)| {
+        create_log_stream(
+            path,
+            source,
+            params.tail,
+            params.offset,
+            filter.clone(),
+            params.format,
+            name.clone(),
+        )
+        .boxed()
+    });
+    let stream = stream::select_all(streams).boxed();
 
     Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
 }
@@ -124,7 +327,8 @@ pub async fn stream_logs(
 // Helper function to create the actual stream
 //
 // This function creates a Server-Sent Events (SSE) stream that:
-// 1. Sends the last N lines from the log file
+// 1. Sends the last N matching lines from the log file, unless resuming from
+//    a byte offset
 // 2. Watches for new content and streams it in real-time
 // 3. Detects and handles log file rotation (logrotate-compatible)
 // 4. Sends periodic heartbeat events during idle periods
@@ -137,14 +341,23 @@ pub async fn stream_logs(
 // See inline comments in the rotation detection section for details and limitations.
 fn create_log_stream(
     log_path: PathBuf,
+    source: LogSource,
     tail: usize,
+    offset: Option<u64>,
+    filter: LogFilter,
+    format: LogFormat,
     instance_name: String,
 ) -> impl Stream<Item = Result<Event, Infallible>> {
     async_stream::stream! {
         // Send init event
         if let Ok(event) = Event::default()
             .event("init")
-            .json_data(json!({"type": "init", "instance": instance_name, "tail": tail}))
+            .json_data(json!({
+                "type": "init",
+                "instance": instance_name,
+                "source": source.as_str(),
+                "tail": tail,
+            }))
         {
             yield Ok(event);
         }
@@ -163,91 +376,28 @@ fn create_log_stream(
             }
         };
 
-        // Send last N lines (efficiently for large files)
-        {
-            // Get file size
-            let file_size = match file.seek(SeekFrom::End(0)) {
-                Ok(size) => size,
-                Err(_) => 0,
-            };
-
-            // For small files (< 1MB), use simple approach
-            if file_size < 1_000_000 {
-                let _ = file.seek(SeekFrom::Start(0));
-                let reader = BufReader::new(&file);
-                let lines: Vec<String> = reader
-                    .lines()
-                    .filter_map(|l| l.ok())
-                    .collect();
-
-                let start = lines.len().saturating_sub(tail);
-                for line in &lines[start..] {
-                    if let Ok(event) = Event::default()
-                        .event("log")
-                        .json_data(json!({"line": line}))
-                    {
-                        yield Ok(event);
-                    }
-                }
-            } else {
-                // For large files, read backwards to find start position
-                let mut newline_count = 0;
-                let mut pos = file_size;
-                let chunk_size = 8192u64;
-                let mut start_pos = 0u64;
-
-                while pos > 0 && newline_count < tail {
-                    let read_size = chunk_size.min(pos);
-                    pos = pos.saturating_sub(read_size);
-
-                    if file.seek(SeekFrom::Start(pos)).is_ok() {
-                        let mut buffer = vec![0u8; read_size as usize];
-                        if file.read_exact(&mut buffer).is_ok() {
-                            // Count newlines in this chunk (scanning backwards)
-                            for (i, &byte) in buffer.iter().enumerate().rev() {
-                                if byte == b'\n' {
-                                    newline_count += 1;
-                                    if newline_count >= tail {
-                                        start_pos = pos + i as u64 + 1;
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-
-                    if newline_count >= tail {
-                        break;
-                    }
-                }
-
-                // Read from start_pos to end
-                if file.seek(SeekFrom::Start(start_pos)).is_ok() {
-                    let reader = BufReader::new(&file);
-                    for line in reader.lines().filter_map(|l| l.ok()) {
-                        if let Ok(event) = Event::default()
-                            .event("log")
-                            .json_data(json!({"line": line}))
-                        {
+        // Send the last N matching lines, or resume from a byte offset
+        let mut last_pos = match offset {
+            Some(offset) => offset.min(file.metadata().map(|m| m.len()).unwrap_or_default()),
+            None => match read_tail_page(log_path.clone(), filter.clone(), tail).await {
+                Ok(page) => {
+                    for line in &page.lines {
+                        if let Ok(event) = log_event(line, source, format) {
                             yield Ok(event);
                         }
                     }
+                    page.end_offset
                 }
-            }
-        }
-
-        // Track current file position (seek to end)
-        let mut last_pos = match file.seek(SeekFrom::End(0)) {
-            Ok(pos) => pos,
-            Err(e) => {
-                if let Ok(event) = Event::default()
-                    .event("error")
-                    .json_data(json!({"error": format!("Failed to seek file: {}", e)}))
-                {
-                    yield Ok(event);
+                Err(e) => {
+                    if let Ok(event) = Event::default()
+                        .event("error")
+                        .json_data(json!({"error": format!("Failed to read log file: {}", e)}))
+                    {
+                        yield Ok(event);
+                    }
+                    return;
                 }
-                return;
-            }
+            },
         };
 
         // Track file inode for rotation detection
@@ -418,15 +568,22 @@ fn create_log_stream(
 
             // Read new lines using the same file handle
             if let Ok(current_size) = file.seek(SeekFrom::End(0)) {
-                if current_size > last_pos {
-                    if file.seek(SeekFrom::Start(last_pos)).is_ok() {
-                        let reader = BufReader::new(&file);
-
-                        for line in reader.lines().filter_map(|l| l.ok()) {
-                            if let Ok(event) = Event::default()
-                                .event("log")
-                                .json_data(json!({"line": line}))
-                            {
+                if current_size > last_pos
+                    && file.seek(SeekFrom::Start(last_pos)).is_ok() {
+                        let mut reader = BufReader::new(&file);
+                        let mut buf = Vec::new();
+
+                        // The serial console carries non-UTF-8 bytes during
+                        // boot, so decode lossily rather than stop there
+                        while matches!(reader.read_until(b'\n', &mut buf), Ok(n) if n > 0) {
+                            let line = String::from_utf8_lossy(&buf)
+                                .trim_end_matches(['\n', '\r'])
+                                .to_string();
+                            buf.clear();
+                            if !filter.matches(&line) {
+                                continue;
+                            }
+                            if let Ok(event) = log_event(&line, source, format) {
                                 yield Ok(event);
                                 last_event_time = std::time::Instant::now();
                             }
@@ -434,8 +591,54 @@ fn create_log_stream(
 
                         last_pos = current_size;
                     }
-                }
             }
         }
     }
 }
+
+/// Read the last `tail` matching lines off the runtime, as a selective
+/// filter can scan a large log end to end
+async fn read_tail_page(path: PathBuf, filter: LogFilter, tail: usize) -> Result<LogPage, String> {
+    tokio::task::spawn_blocking(move || read_page(&path, &filter, tail, None))
+        .await
+        .map_err(|e| format!("Log reader panicked: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+/// Build a `log` event, with the parsed record for `LogFormat::Json`
+fn log_event(line: &str, source: LogSource, format: LogFormat) -> Result<Event, axum::Error> {
+    let source = source.as_str();
+    let data = match format {
+        LogFormat::Text => json!({"line": line, "source": source}),
+        LogFormat::Json => {
+            json!({"line": line, "source": source, "record": log_line_to_record(line, source)})
+        }
+    };
+    Event::default().event("log").json_data(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use katana_core::instance::InstanceConfig;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_log_paths_all_without_serial_log() {
+        let dir = TempDir::new().unwrap();
+        let config = InstanceConfig {
+            data_dir: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let instance = InstanceState::new("id".to_string(), "name".to_string(), config);
+        std::fs::write(instance.katana_log(), "started\n").unwrap();
+
+        let paths = log_paths(&instance, LogSource::All).unwrap();
+        assert_eq!(paths, vec![(LogSource::Katana, instance.katana_log())]);
+
+        assert!(matches!(
+            log_paths(&instance, LogSource::Kernel),
+            Err(ApiError::NotFound(_))
+        ));
+    }
+}
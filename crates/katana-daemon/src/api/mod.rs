@@ -1,9 +1,19 @@
+pub mod accounts;
+pub mod attestation;
+pub mod host;
 pub mod instances;
 pub mod logs;
 pub mod operations;
+pub mod secrets;
 pub mod stats;
+pub mod templates;
 
+pub use accounts::*;
+pub use attestation::*;
+pub use host::*;
 pub use instances::*;
 pub use logs::*;
 pub use operations::*;
+pub use secrets::*;
 pub use stats::*;
+pub use templates::*;
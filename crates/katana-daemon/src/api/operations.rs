@@ -1,13 +1,30 @@
 use axum::{
-    extract::{Extension, Path},
+    extract::{Extension, Path, Query},
     response::Json,
 };
 use katana_core::{
-    instance::InstanceStatus,
-    qemu::{config::{QemuConfig, SevSnpConfig}, ManagedVm},
+    host::kvm_accessible,
+    instance::{
+        config_drive::write_katana_config,
+        messaging::{remove_messaging_config, write_messaging_config},
+        Accelerator, InstanceState, InstanceStatus,
+    },
+    qemu::{config::QemuConfig, ManagedVm},
+    secrets::SecretsDelivery,
+    vsock::{
+        guest_cid_in_use, host_cid_in_use, vhost_vsock_available, VsockClient, VHOST_VSOCK_DEVICE,
+    },
 };
+use serde::Deserialize;
 use std::sync::Arc;
-use tracing::info;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How long the guest agent has to come up and accept secrets after boot
+const SECRETS_DELIVERY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Time between secret delivery attempts while the guest boots
+const SECRETS_DELIVERY_RETRY: Duration = Duration::from_secs(2);
 
 use crate::{
     error::{ApiError, ApiResult},
@@ -56,47 +73,78 @@ pub async fn start_instance(
         )));
     }
 
-    // Build katana arguments
-    let katana_args = instance_state.config.build_katana_args();
-    let kernel_cmdline = QemuConfig::build_kernel_cmdline(&katana_args);
+    // CIDs are global to the host; if another VM took this one since it was
+    // assigned, QEMU would fail with EADDRINUSE, so move to a free one
+    if let Some(cid) = instance_state
+        .config
+        .vsock_cid
+        .filter(|_| vhost_vsock_available())
+    {
+        if guest_cid_in_use(cid).unwrap_or(false) {
+            let new_cid = state.db.allocate_vsock_cid(
+                &instance_state.id,
+                state.vsock_cid_base,
+                host_cid_in_use,
+            )?;
+            warn!(name = %name, cid, new_cid, "Vsock CID is taken on the host, reassigning");
+            instance_state.config.vsock_cid = Some(new_cid);
+            state.db.save_instance(&instance_state)?;
+        }
+    }
 
-    // Build SEV-SNP config if TEE mode is enabled
-    let sev_snp_config = if instance_state.config.tee_mode {
-        Some(SevSnpConfig {
-            cbitpos: 51,
-            reduced_phys_bits: 1,
-            vcpu_type: instance_state.config.vcpu_type.clone(),
-        })
-    } else {
-        None
-    };
+    // Attach the vsock control channel only if the host supports it
+    let vsock_cid = instance_state
+        .config
+        .vsock_cid
+        .filter(|_| vhost_vsock_available());
+    if instance_state.config.vsock_cid.is_some() && vsock_cid.is_none() {
+        warn!(name = %name, "{} not found, starting without vsock", VHOST_VSOCK_DEVICE);
+    }
 
-    // Build QEMU configuration
-    let qemu_config = QemuConfig {
-        memory_mb: instance_state.config.memory_mb,
-        vcpus: instance_state.config.vcpus,
-        cpu_type: instance_state.config.vcpu_type.clone(),
-        kernel_path: instance_state.config.kernel_path.clone(),
-        initrd_path: instance_state.config.initrd_path.clone(),
-        bios_path: instance_state.config.ovmf_path.clone(),
-        kernel_cmdline,
-        rpc_port: instance_state.config.rpc_port,
-        disk_image: instance_state.config.disk_image.clone(),
-        qmp_socket: instance_state.qmp_socket.clone().unwrap(),
-        serial_log: instance_state.serial_log.clone().unwrap(),
-        pid_file: std::path::PathBuf::from(format!(
-            "/tmp/katana-hypervisor-{}.pid",
-            instance_state.id
-        )),
-        sev_snp: sev_snp_config,
-        enable_kvm: true,
-    };
+    // Secrets only travel over vsock, so the guest would wait for them forever
+    let secret_names: Vec<String> = state
+        .db
+        .list_secrets(&instance_state.id)?
+        .into_iter()
+        .map(|s| s.name)
+        .collect();
+    if !secret_names.is_empty() && vsock_cid.is_none() {
+        return Err(ApiError::BadRequest(format!(
+            "Instance '{}' has secrets, which are delivered over vsock, but vsock is not available",
+            name
+        )));
+    }
+
+    // Secrets referenced as ${NAME} in settings are expanded by the guest
+    for reference in instance_state.config.secret_references() {
+        if !secret_names.contains(&reference) {
+            return Err(ApiError::BadRequest(format!(
+                "Instance '{}' references secret '{}', which is not set",
+                name, reference
+            )));
+        }
+    }
+
+    write_config_drive(&state, &instance_state, &secret_names)?;
+
+    // Pick KVM or TCG depending on the configured accelerator and the host
+    let accelerator = instance_state
+        .config
+        .resolve_accelerator(kvm_accessible())
+        .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    if accelerator == Accelerator::Tcg && instance_state.config.accelerator == Accelerator::Auto {
+        warn!(name = %name, "/dev/kvm is not accessible, falling back to TCG emulation");
+    }
+
+    let qemu_config = qemu_config(&instance_state, vsock_cid, accelerator);
 
     info!(
         name = %name,
         vcpus = %qemu_config.vcpus,
         memory_mb = %qemu_config.memory_mb,
         rpc_port = %qemu_config.rpc_port,
+        accelerator = %accelerator,
         "Launching VM"
     );
 
@@ -120,14 +168,30 @@ pub async fn start_instance(
         "Instance started successfully"
     );
 
+    if let Some(cid) = vsock_cid.filter(|_| !secret_names.is_empty()) {
+        spawn_secrets_delivery(state.clone(), &mut instance_state, cid);
+    }
+
     Ok(Json(instance_state_to_response(instance_state)))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct StopQuery {
+    /// Seconds to wait for the guest to power off before sending SIGTERM
+    #[serde(default = "default_grace_period")]
+    pub grace_period: u64,
+}
+
+fn default_grace_period() -> u64 {
+    30
+}
+
 /// Stop an instance
-/// POST /api/v1/instances/{name}/stop
+/// POST /api/v1/instances/{name}/stop?grace_period=30
 pub async fn stop_instance(
     Extension(state): Extension<Arc<DaemonState>>,
     Path(name): Path<String>,
+    Query(params): Query<StopQuery>,
 ) -> ApiResult<Json<InstanceResponse>> {
     info!(name = %name, "Stopping instance via API");
 
@@ -150,7 +214,7 @@ pub async fn stop_instance(
             // Valid states for stopping
         }
         _ => {
-            return Err(ApiError::BadRequest(format!(
+            return Err(ApiError::InvalidState(format!(
                 "Cannot stop instance '{}' from state: {}",
                 name, instance_state.status
             )));
@@ -160,15 +224,49 @@ pub async fn stop_instance(
     // Get PID for logging
     let pid = instance_state.vm_pid;
 
-    info!(name = %name, pid = ?pid, "Stopping VM");
+    info!(name = %name, pid = ?pid, grace_period = params.grace_period, "Stopping VM");
 
     // Stop VM using ManagedVm (automatically handles state tracking)
-    let mut managed_vm = ManagedVm::from_instance(&instance_state.id, &state.db).await
+    let mut managed_vm = ManagedVm::from_instance(&instance_state.id, &state.db)
+        .await
         .map_err(|e| ApiError::Internal(format!("Failed to load VM instance: {}", e)))?;
 
-    managed_vm.stop(30).map_err(|e| {
-        ApiError::Internal(format!("Failed to stop VM: {}", e))
-    })?;
+    let mut grace_period = Duration::from_secs(params.grace_period);
+    let mut stopped = false;
+
+    // Ask the guest agent to stop Katana cleanly and power off, giving it
+    // half the grace period; ACPI powerdown is the fallback
+    if let (Some(cid), InstanceStatus::Running) =
+        (instance_state.config.vsock_cid, &instance_state.status)
+    {
+        if !grace_period.is_zero() {
+            match VsockClient::new(cid).shutdown().await {
+                Ok(()) => {
+                    info!(name = %name, "Guest agent is shutting down");
+                    let agent_wait = grace_period / 2;
+                    stopped = managed_vm
+                        .wait_for_poweroff(agent_wait)
+                        .await
+                        .map_err(|e| ApiError::Internal(format!("Failed to stop VM: {}", e)))?;
+                    grace_period -= agent_wait;
+                }
+                Err(e) => {
+                    warn!(
+                        name = %name,
+                        error = %e,
+                        "Guest agent shutdown failed, using ACPI powerdown"
+                    );
+                }
+            }
+        }
+    }
+
+    if !stopped {
+        managed_vm
+            .stop(grace_period)
+            .await
+            .map_err(|e| ApiError::Internal(format!("Failed to stop VM: {}", e)))?;
+    }
 
     // Reload instance state from database (updated by ManagedVm)
     instance_state = state.db.get_instance(&name)?;
@@ -205,7 +303,7 @@ pub async fn pause_instance(
 
     // Validate state transition
     if !instance_state.status.can_pause() {
-        return Err(ApiError::BadRequest(format!(
+        return Err(ApiError::InvalidState(format!(
             "Cannot pause instance '{}' from state: {}",
             name, instance_state.status
         )));
@@ -254,7 +352,7 @@ pub async fn resume_instance(
 
     // Validate state transition
     if !instance_state.status.can_resume_from_pause() && !instance_state.status.can_wake() {
-        return Err(ApiError::BadRequest(format!(
+        return Err(ApiError::InvalidState(format!(
             "Cannot resume instance '{}' from state: {}",
             name, instance_state.status
         )));
@@ -285,6 +383,49 @@ pub async fn resume_instance(
     Ok(Json(instance_state_to_response(instance_state)))
 }
 
+/// Wake a suspended instance
+/// POST /api/v1/instances/{name}/wake
+pub async fn wake_instance(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<InstanceResponse>> {
+    info!(name = %name, "Waking instance via API");
+
+    // Load instance from database
+    let mut instance_state = state.db.get_instance(&name)?;
+
+    // Check if already running (idempotent)
+    if matches!(instance_state.status, InstanceStatus::Running) {
+        info!(name = %name, "Instance already running");
+        return Ok(Json(instance_state_to_response(instance_state)));
+    }
+
+    // Validate state transition
+    if !instance_state.status.can_wake() {
+        return Err(ApiError::InvalidState(format!(
+            "Cannot wake instance '{}' from state: {}",
+            name, instance_state.status
+        )));
+    }
+
+    // Wake VM using ManagedVm (automatically handles state tracking)
+    let managed_vm = ManagedVm::from_instance(&instance_state.id, &state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to load VM instance: {}", e)))?;
+
+    managed_vm
+        .wake()
+        .await
+        .map_err(|e| ApiError::Internal(format!("Failed to wake VM: {}", e)))?;
+
+    // Reload instance state from database (updated by ManagedVm)
+    instance_state = state.db.get_instance(&name)?;
+
+    info!(name = %name, "Instance woken successfully");
+
+    Ok(Json(instance_state_to_response(instance_state)))
+}
+
 /// Suspend an instance to RAM
 /// POST /api/v1/instances/{name}/suspend
 pub async fn suspend_instance(
@@ -312,7 +453,7 @@ pub async fn suspend_instance(
 
     // Validate state transition
     if !instance_state.status.can_suspend() {
-        return Err(ApiError::BadRequest(format!(
+        return Err(ApiError::InvalidState(format!(
             "Cannot suspend instance '{}' from state: {}",
             name, instance_state.status
         )));
@@ -358,7 +499,7 @@ pub async fn reset_instance(
 
     // Validate state transition
     if !instance_state.status.can_reset() {
-        return Err(ApiError::BadRequest(format!(
+        return Err(ApiError::InvalidState(format!(
             "Cannot reset instance '{}' from state: {}",
             name, instance_state.status
         )));
@@ -388,5 +529,271 @@ pub async fn reset_instance(
 
     info!(name = %name, "Instance reset successfully");
 
+    // The guest lost its secrets with its memory and waits for them again
+    if let Some(cid) = instance_state
+        .config
+        .vsock_cid
+        .filter(|_| vhost_vsock_available())
+    {
+        if !state.db.list_secrets(&instance_state.id)?.is_empty() {
+            spawn_secrets_delivery(state.clone(), &mut instance_state, cid);
+        }
+    }
+
     Ok(Json(instance_state_to_response(instance_state)))
 }
+
+/// Write the config drive for a start. Messaging settings get the L1
+/// resolved to an address the guest can reach; their key comes from a secret.
+fn write_config_drive(
+    state: &DaemonState,
+    instance_state: &InstanceState,
+    secret_names: &[String],
+) -> ApiResult<()> {
+    let name = &instance_state.name;
+
+    if let Some(messaging) = &instance_state.config.messaging {
+        if !secret_names.contains(&messaging.key_secret) {
+            return Err(ApiError::BadRequest(format!(
+                "Instance '{}' uses L1 messaging but its key secret '{}' is not set",
+                name, messaging.key_secret
+            )));
+        }
+
+        let l1_rpc_port = match &messaging.l1_instance {
+            Some(l1) => {
+                let l1_state = state.db.get_instance(l1).map_err(|_| {
+                    ApiError::BadRequest(format!("Messaging L1 instance '{}' not found", l1))
+                })?;
+                if !matches!(l1_state.status, InstanceStatus::Running) {
+                    warn!(name = %name, l1 = %l1, "Messaging L1 instance is not running");
+                }
+                Some(l1_state.config.rpc_port)
+            }
+            None => None,
+        };
+
+        let rpc_url = messaging
+            .guest_rpc_url(l1_rpc_port)
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+        write_messaging_config(&instance_state.config_dir(), messaging, &rpc_url)
+            .map_err(|e| ApiError::Internal(format!("Failed to write config drive: {}", e)))?;
+    } else {
+        remove_messaging_config(&instance_state.config_dir())
+            .map_err(|e| ApiError::Internal(format!("Failed to write config drive: {}", e)))?;
+    }
+
+    // Katana arguments go on the config drive, and on the kernel cmdline
+    // unless the instance reads them from the drive only
+    write_katana_config(
+        &instance_state.config_dir(),
+        &instance_state.config.build_katana_args(),
+        &instance_state.config.env,
+        secret_names,
+    )
+    .map_err(|e| ApiError::Internal(format!("Failed to write config drive: {}", e)))
+}
+
+/// QEMU configuration for launching an instance
+fn qemu_config(
+    instance_state: &InstanceState,
+    vsock_cid: Option<u32>,
+    accelerator: Accelerator,
+) -> QemuConfig {
+    QemuConfig {
+        memory_mb: instance_state.config.memory_mb,
+        vcpus: instance_state.config.vcpus,
+        cpu_type: instance_state.config.vcpu_type.clone(),
+        kernel_path: instance_state.config.kernel_path.clone(),
+        initrd_path: instance_state.config.initrd_path.clone(),
+        bios_path: instance_state.config.ovmf_path.clone(),
+        kernel_cmdline: instance_state.config.kernel_cmdline(),
+        rpc_port: instance_state.config.rpc_port,
+        vsock_cid,
+        disk_image: instance_state.config.disk_image.clone(),
+        config_drive: Some(instance_state.config_dir()),
+        qmp_socket: instance_state.qmp_socket.clone().unwrap(),
+        serial_log: instance_state.serial_log.clone().unwrap(),
+        katana_log: instance_state.katana_log(),
+        // Same location ManagedVm and force cleanup look for it
+        pid_file: instance_state.config.data_dir.join("qemu.pid"),
+        sev_snp: instance_state.config.sev_snp_config(),
+        enable_kvm: accelerator == Accelerator::Kvm,
+        balloon: instance_state.config.balloon,
+    }
+}
+
+/// Hand the instance's secrets to the guest agent in the background,
+/// retrying while the guest boots. Only secret names are ever logged.
+///
+/// The outcome is recorded as the instance's `secrets_delivery`, starting
+/// out as pending on `instance`.
+fn spawn_secrets_delivery(state: Arc<DaemonState>, instance: &mut InstanceState, cid: u32) {
+    let name = instance.name.clone();
+    let instance_id = instance.id.clone();
+
+    if let Err(e) = state
+        .db
+        .set_secrets_delivery(&instance_id, &SecretsDelivery::Pending)
+    {
+        warn!(name = %name, error = %e, "Failed to record secrets delivery");
+    }
+    instance.secrets_delivery = Some(SecretsDelivery::Pending);
+
+    tokio::spawn(async move {
+        let client = VsockClient::new(cid);
+        let deadline = tokio::time::Instant::now() + SECRETS_DELIVERY_TIMEOUT;
+
+        let outcome = loop {
+            // Decrypt on every attempt so plaintext isn't held while waiting
+            let secrets = match state.db.get_secrets(&instance_id, &state.secrets_key) {
+                Ok(secrets) => secrets,
+                Err(e) => {
+                    warn!(name = %name, error = %e, "Failed to load secrets for delivery");
+                    break SecretsDelivery::Failed {
+                        error: format!("Failed to load secrets: {}", e),
+                    };
+                }
+            };
+            let count = secrets.len();
+
+            match client.deliver_secrets(secrets).await {
+                Ok(()) => {
+                    info!(name = %name, count, "Delivered secrets to guest");
+                    break SecretsDelivery::Delivered;
+                }
+                Err(e) if tokio::time::Instant::now() >= deadline => {
+                    warn!(name = %name, error = %e, "Gave up delivering secrets to guest");
+                    break SecretsDelivery::Failed {
+                        error: format!(
+                            "Guest agent did not accept secrets within {}s: {}",
+                            SECRETS_DELIVERY_TIMEOUT.as_secs(),
+                            e
+                        ),
+                    };
+                }
+                Err(_) => tokio::time::sleep(SECRETS_DELIVERY_RETRY).await,
+            }
+        };
+
+        if let Err(e) = state.db.set_secrets_delivery(&instance_id, &outcome) {
+            warn!(name = %name, error = %e, "Failed to record secrets delivery");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use katana_core::{
+        instance::{
+            messaging::{MessagingChain, MessagingConfig, MESSAGING_FILE},
+            InstanceConfig,
+        },
+        secrets::SecretValue,
+        vsock::FIRST_GUEST_CID,
+    };
+    use tempfile::TempDir;
+
+    fn test_state(dir: &TempDir) -> DaemonState {
+        DaemonState::for_tests(dir.path())
+    }
+
+    #[test]
+    fn test_secret_values_stay_on_the_host() {
+        let dir = TempDir::new().unwrap();
+        let state = test_state(&dir);
+
+        let data_dir = dir.path().join("instances").join("l2");
+        let config = InstanceConfig {
+            data_dir: data_dir.clone(),
+            fork_provider: Some("https://eth.example.com/v2/${FORK_API_KEY}".to_string()),
+            messaging: Some(MessagingConfig {
+                chain: MessagingChain::Ethereum,
+                rpc_url: Some("https://l1.example.com/${L1_API_KEY}".to_string()),
+                l1_instance: None,
+                contract_address: "0x5FbDB2315678afecb367f032d93F642f64180aa3".to_string(),
+                sender_address: "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266".to_string(),
+                interval_secs: 2,
+                from_block: 0,
+                key_secret: "MESSAGING_KEY".to_string(),
+            }),
+            ..Default::default()
+        };
+        let mut instance = InstanceState::new("l2-id".to_string(), "l2".to_string(), config);
+        instance.qmp_socket = Some(data_dir.join("qmp.sock"));
+        instance.serial_log = Some(data_dir.join("serial.log"));
+        state.db.save_instance(&instance).unwrap();
+
+        let secrets = [
+            ("FORK_API_KEY", "fork-api-key-value"),
+            ("L1_API_KEY", "l1-api-key-value"),
+            ("MESSAGING_KEY", "0xc5b2fcab997346f3ea1c00b002ecf6f3"),
+        ];
+        for (name, value) in secrets {
+            state
+                .db
+                .set_secret(
+                    &instance.id,
+                    name,
+                    &SecretValue::new(value),
+                    &state.secrets_key,
+                )
+                .unwrap();
+        }
+        let names: Vec<String> = secrets.iter().map(|(name, _)| name.to_string()).collect();
+
+        write_config_drive(&state, &instance, &names).unwrap();
+        let instance = state.db.get_instance("l2").unwrap();
+
+        let response =
+            serde_json::to_string(&instance_state_to_response(instance.clone())).unwrap();
+        let config_json = serde_json::to_string(&instance.config).unwrap();
+        let qemu_args = qemu_config(&instance, Some(FIRST_GUEST_CID), Accelerator::Tcg)
+            .to_qemu_args()
+            .join(" ");
+        let config_drive: String = std::fs::read_dir(instance.config_dir())
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+
+        for (name, value) in secrets {
+            for (what, text) in [
+                ("InstanceResponse", &response),
+                ("config_json", &config_json),
+                ("QEMU args", &qemu_args),
+                ("config drive", &config_drive),
+            ] {
+                assert!(
+                    !text.contains(value),
+                    "{} contains the value of {}",
+                    what,
+                    name
+                );
+            }
+        }
+
+        // References reach the guest as they are, for it to expand
+        assert!(qemu_args.contains("--fork.provider=https://eth.example.com/v2/${FORK_API_KEY}"));
+        assert!(config_drive.contains("https://l1.example.com/${L1_API_KEY}"));
+        assert!(config_drive.contains("\"MESSAGING_KEY\""));
+    }
+
+    #[test]
+    fn test_config_drive_drops_stale_messaging() {
+        let dir = TempDir::new().unwrap();
+        let state = test_state(&dir);
+
+        let config = InstanceConfig {
+            data_dir: dir.path().join("instances").join("l2"),
+            ..Default::default()
+        };
+        let instance = InstanceState::new("l2-id".to_string(), "l2".to_string(), config);
+        let messaging_file = instance.config_dir().join(MESSAGING_FILE);
+        std::fs::create_dir_all(instance.config_dir()).unwrap();
+        std::fs::write(&messaging_file, "{}").unwrap();
+
+        write_config_drive(&state, &instance, &[]).unwrap();
+        assert!(!messaging_file.exists());
+    }
+}
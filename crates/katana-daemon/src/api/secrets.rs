@@ -0,0 +1,82 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::Json,
+};
+use katana_core::secrets::{validate_secret, SecretValue};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::{
+    error::{ApiError, ApiResult},
+    models::{secret_metadata_to_info, ListSecretsResponse, SecretInfo, SetSecretRequest},
+    state::DaemonState,
+};
+
+/// List the names of an instance's secrets
+/// GET /api/v1/instances/{name}/secrets
+pub async fn list_secrets(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<ListSecretsResponse>> {
+    let instance = state.db.get_instance(&name)?;
+
+    let secrets = state
+        .db
+        .list_secrets(&instance.id)?
+        .into_iter()
+        .map(secret_metadata_to_info)
+        .collect();
+
+    Ok(Json(ListSecretsResponse {
+        instance_name: name,
+        secrets,
+    }))
+}
+
+/// Create or replace a secret. A running instance gets the new value on
+/// its next start.
+/// PUT /api/v1/instances/{name}/secrets/{secret}
+pub async fn set_secret(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Path((name, secret)): Path<(String, String)>,
+    Json(request): Json<SetSecretRequest>,
+) -> ApiResult<Json<SecretInfo>> {
+    info!(name = %name, secret = %secret, "Setting secret via API");
+
+    let instance = state.db.get_instance(&name)?;
+    let value = SecretValue::new(request.value);
+    validate_secret(&secret, &value).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+    state
+        .db
+        .set_secret(&instance.id, &secret, &value, &state.secrets_key)?;
+
+    let metadata = state
+        .db
+        .list_secrets(&instance.id)?
+        .into_iter()
+        .find(|s| s.name == secret)
+        .ok_or_else(|| ApiError::Internal(format!("Secret '{}' was not stored", secret)))?;
+
+    Ok(Json(secret_metadata_to_info(metadata)))
+}
+
+/// Delete a secret
+/// DELETE /api/v1/instances/{name}/secrets/{secret}
+pub async fn delete_secret(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Path((name, secret)): Path<(String, String)>,
+) -> ApiResult<StatusCode> {
+    info!(name = %name, secret = %secret, "Deleting secret via API");
+
+    let instance = state.db.get_instance(&name)?;
+    if !state.db.delete_secret(&instance.id, &secret)? {
+        return Err(ApiError::NotFound(format!(
+            "Secret '{}' not found for instance '{}'",
+            secret, name
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
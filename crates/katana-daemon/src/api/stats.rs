@@ -1,54 +1,41 @@
-use crate::{error::ApiError, state::DaemonState};
-use axum::{extract::Path, response::Json, Extension};
-use katana_core::{instance::InstanceStatus, qemu::{ManagedVm, QmpClient}};
-use serde::Serialize;
+use crate::{
+    api::{instances::parse_duration_secs, logs::parse_time},
+    error::ApiError,
+    models::{
+        chain_stats_to_info, instance_status_name, stats_sample_to_info, BlockDeviceInfo,
+        ChainInfo, ConfigInfo, CpuInfo, InstanceUsage, NetworkInfo, NetworkIoInfo, ResourcesInfo,
+        StatsHistoryResponse, StatsResponse, StatsSnapshot, StatusInfo,
+    },
+    state::DaemonState,
+};
+use axum::{
+    extract::{Path, Query},
+    response::{
+        sse::{Event, KeepAlive},
+        Json, Sse,
+    },
+    Extension,
+};
+use futures::stream::Stream;
+use katana_core::{
+    instance::{InstanceState, InstanceStatus, LabelSelector},
+    qemu::{
+        qmp::{StatValue, StatsResult, StatsTarget},
+        ManagedVm, QmpClient,
+    },
+    rpc::KatanaRpcClient,
+    vsock::VsockClient,
+};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
 use std::sync::Arc;
+use std::time::Duration;
 
-type ApiResult<T> = Result<T, ApiError>;
-
-#[derive(Debug, Serialize)]
-pub struct StatsResponse {
-    pub instance_name: String,
-    pub status: StatusInfo,
-    pub config: ConfigInfo,
-    pub resources: ResourcesInfo,
-    pub network: NetworkInfo,
-}
-
-#[derive(Debug, Serialize)]
-pub struct StatusInfo {
-    pub state: String,
-    pub running: bool,
-    pub pid: Option<i32>,
-    pub uptime: String,
-}
-
-#[derive(Debug, Serialize)]
-pub struct ConfigInfo {
-    pub vcpus: u32,
-    pub memory_mb: u64,
-    pub rpc_port: u16,
-    pub tee_mode: Option<String>,
-}
+/// How long each guest agent gets to report health in the stats stream
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(1);
 
-#[derive(Debug, Serialize)]
-pub struct ResourcesInfo {
-    pub cpu_count: usize,
-    pub cpus: Vec<CpuInfo>,
-    pub memory_mb: u64,
-}
-
-#[derive(Debug, Serialize)]
-pub struct CpuInfo {
-    pub cpu_index: u64,
-    pub thread_id: u64,
-}
-
-#[derive(Debug, Serialize)]
-pub struct NetworkInfo {
-    pub rpc_url: String,
-    pub health_url: String,
-}
+type ApiResult<T> = Result<T, ApiError>;
 
 /// Get stats for an instance
 pub async fn get_stats(
@@ -101,6 +88,63 @@ pub async fn get_stats(
         ApiError::Internal(format!("Failed to query memory: {}", e))
     })?;
 
+    // Optional counters; a missing device or an older QEMU leaves them empty
+    let block_devices = match qmp_client.query_blockstats().await {
+        Ok(devices) => devices
+            .into_iter()
+            .map(|device| BlockDeviceInfo {
+                device: match device.node_name {
+                    Some(node_name) if device.device.is_empty() => node_name,
+                    _ => device.device,
+                },
+                read_bytes: device.stats.rd_bytes,
+                write_bytes: device.stats.wr_bytes,
+                read_operations: device.stats.rd_operations,
+                write_operations: device.stats.wr_operations,
+            })
+            .collect(),
+        Err(e) => {
+            tracing::debug!(name = %name, error = %e, "Failed to query block stats");
+            Vec::new()
+        }
+    };
+    let balloon_bytes = qmp_client.query_balloon().await.ok().map(|b| b.actual);
+    let vm_stats = qmp_client
+        .query_stats(StatsTarget::Vm)
+        .await
+        .map(|results| sum_scalar_stats(&results))
+        .unwrap_or_default();
+    let vcpu_stats = qmp_client
+        .query_stats(StatsTarget::Vcpu)
+        .await
+        .map(|results| sum_scalar_stats(&results))
+        .unwrap_or_default();
+
+    // QEMU has no counters for user networking, so ask the guest agent
+    let network_io = match instance_state.config.vsock_cid {
+        Some(cid) => VsockClient::new(cid)
+            .metrics()
+            .await
+            .ok()
+            .and_then(|metrics| {
+                Some(NetworkIoInfo {
+                    rx_bytes: metrics.net_rx_bytes?,
+                    tx_bytes: metrics.net_tx_bytes?,
+                })
+            }),
+        None => None,
+    };
+
+    // Ask Katana itself whether the chain is progressing, through the
+    // forwarded RPC port
+    let (chain, chain_error) = match probe_chain(instance_state.config.rpc_port).await {
+        Ok(chain) => (Some(chain), None),
+        Err(e) => {
+            tracing::debug!(name = %name, error = %e, "Katana RPC probe failed");
+            (None, Some(e.to_string()))
+        }
+    };
+
     // Get process uptime
     let uptime = if let Some(pid) = pid {
         get_process_uptime(pid).unwrap_or_else(|_| "unknown".to_string())
@@ -139,16 +183,190 @@ pub async fn get_stats(
                 })
                 .collect(),
             memory_mb: memory.base_memory / 1024 / 1024,
+            balloon_bytes,
+            block_devices,
+            network_io,
+            vm_stats,
+            vcpu_stats,
         },
         network: NetworkInfo {
             rpc_url: format!("http://localhost:{}", instance_state.config.rpc_port),
             health_url: format!("http://localhost:{}/", instance_state.config.rpc_port),
         },
+        chain,
+        chain_error,
     };
 
     Ok(Json(response))
 }
 
+async fn probe_chain(rpc_port: u16) -> katana_core::Result<ChainInfo> {
+    let stats = KatanaRpcClient::for_port(rpc_port)?.chain_stats().await?;
+    Ok(chain_stats_to_info(stats))
+}
+
+/// Add up scalar counters by name, e.g. across all vCPUs
+fn sum_scalar_stats(results: &[StatsResult]) -> BTreeMap<String, u64> {
+    let mut totals = BTreeMap::new();
+    for stat in results.iter().flat_map(|result| &result.stats) {
+        if let StatValue::Scalar(value) = stat.value {
+            *totals.entry(stat.name.clone()).or_default() += value;
+        }
+    }
+    totals
+}
+
+fn default_history_limit() -> usize {
+    1000
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatsHistoryQuery {
+    /// Only samples at or after this time (RFC 3339, or a duration such as "1h" ago)
+    pub since: Option<String>,
+    /// Maximum number of samples, counted from the latest
+    #[serde(default = "default_history_limit")]
+    pub limit: usize,
+}
+
+/// Get the recorded resource usage of an instance
+///
+/// Samples are taken by the daemon while the instance runs, so stopped
+/// periods show up as gaps.
+pub async fn get_stats_history(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Path(name): Path<String>,
+    Query(params): Query<StatsHistoryQuery>,
+) -> ApiResult<Json<StatsHistoryResponse>> {
+    let instance_state = state.db.get_instance(&name)?;
+
+    let since = params
+        .since
+        .as_deref()
+        .map(parse_time)
+        .transpose()?
+        .map(|since| since.timestamp());
+
+    let samples = state
+        .db
+        .list_stats_samples(&instance_state.id, since, params.limit)?;
+
+    Ok(Json(StatsHistoryResponse {
+        instance_name: name,
+        interval_secs: state.stats_interval.as_secs(),
+        samples: samples.into_iter().map(stats_sample_to_info).collect(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StreamStatsQuery {
+    /// Time between snapshots, e.g. "5s"; defaults to the sampling interval
+    pub interval: Option<String>,
+    /// Label selector, e.g. "team=bridge,env=ci"
+    pub selector: Option<String>,
+}
+
+/// Stream the latest resource usage of all instances via Server-Sent Events
+///
+/// Usage comes from the background sampler, so it changes at most once per
+/// sampling interval. Health is asked of each guest agent for every
+/// snapshot rather than probing the RPC port, which would keep idle
+/// instances from being stopped.
+pub async fn stream_stats(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Query(params): Query<StreamStatsQuery>,
+) -> ApiResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let interval = match params.interval.as_deref() {
+        Some(interval) => Duration::from_secs(parse_duration_secs(interval)?),
+        None => state.stats_interval,
+    };
+    let selector = params
+        .selector
+        .as_deref()
+        .unwrap_or_default()
+        .parse::<LabelSelector>()
+        .map_err(ApiError::BadRequest)?;
+
+    let stream = async_stream::stream! {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+
+            let event = match stats_snapshot(&state, &selector).await {
+                Ok(snapshot) => Event::default().event("stats").json_data(snapshot),
+                Err(e) => Event::default()
+                    .event("error")
+                    .json_data(serde_json::json!({"error": e.to_string()})),
+            };
+            if let Ok(event) = event {
+                yield Ok(event);
+            }
+        }
+    };
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn stats_snapshot(
+    state: &DaemonState,
+    selector: &LabelSelector,
+) -> katana_core::Result<StatsSnapshot> {
+    let instances: Vec<_> = state
+        .db
+        .list_instances()?
+        .into_iter()
+        .filter(|instance| selector.matches(&instance.labels))
+        .collect();
+    let mut samples = state.db.latest_stats_samples()?;
+
+    let health = futures::future::join_all(instances.iter().map(instance_health)).await;
+
+    let instances = instances
+        .into_iter()
+        .zip(health)
+        .map(|(instance, health)| {
+            let running = matches!(instance.status, InstanceStatus::Running);
+            InstanceUsage {
+                sample: samples
+                    .remove(&instance.id)
+                    .filter(|_| running)
+                    .map(stats_sample_to_info),
+                status: instance_status_name(&instance.status),
+                vcpus: instance.config.vcpus,
+                memory_mb: instance.config.memory_mb,
+                rpc_port: instance.config.rpc_port,
+                health: health.to_string(),
+                name: instance.name,
+            }
+        })
+        .collect();
+
+    Ok(StatsSnapshot {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        instances,
+    })
+}
+
+/// Health of a running instance as reported by its guest agent
+async fn instance_health(instance: &InstanceState) -> &'static str {
+    let Some(cid) = instance.config.vsock_cid else {
+        return "unknown";
+    };
+    if !matches!(instance.status, InstanceStatus::Running) {
+        return "unknown";
+    }
+
+    match VsockClient::new(cid)
+        .with_timeout(HEALTH_TIMEOUT)
+        .health()
+        .await
+    {
+        Ok(health) if health.katana_running => "healthy",
+        Ok(_) => "unhealthy",
+        Err(_) => "unknown",
+    }
+}
+
 fn get_process_uptime(pid: i32) -> Result<String, std::io::Error> {
     // Read process start time from /proc/<pid>/stat
     let stat_path = format!("/proc/{}/stat", pid);
@@ -0,0 +1,128 @@
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::Json,
+};
+use katana_core::instance::{Accelerator, InstanceTemplate, TemplateConfig};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::{
+    api::instances::{
+        build_sev_snp_config, check_accelerator, parse_duration_secs, parse_memory_mb,
+        parse_storage_bytes, DEFAULT_TEE_VCPU_TYPE,
+    },
+    error::{ApiError, ApiResult},
+    models::{
+        sev_snp_request_to_settings, template_to_response, CreateTemplateRequest,
+        ListTemplatesResponse, TemplateResponse,
+    },
+    state::DaemonState,
+};
+
+/// Create a template
+/// POST /api/v1/templates
+pub async fn create_template(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Json(req): Json<CreateTemplateRequest>,
+) -> ApiResult<(StatusCode, Json<TemplateResponse>)> {
+    info!(name = %req.name, "Creating template via API");
+
+    if req.name.is_empty() {
+        return Err(ApiError::BadRequest(
+            "Template name cannot be empty".to_string(),
+        ));
+    }
+
+    let accelerator = req
+        .accelerator
+        .as_deref()
+        .map(|value| value.parse::<Accelerator>().map_err(ApiError::BadRequest))
+        .transpose()?;
+    if let Some(accelerator) = accelerator {
+        check_accelerator(accelerator, req.tee.unwrap_or(false))?;
+    }
+
+    // Check SEV-SNP settings now rather than at every create from the template
+    let sev_snp = req.sev_snp.map(sev_snp_request_to_settings);
+    if let Some(settings) = &sev_snp {
+        if req.tee == Some(false) {
+            return Err(ApiError::BadRequest(
+                "SEV-SNP settings require TEE mode".to_string(),
+            ));
+        }
+        let vcpu_type = req.vcpu_type.as_deref().unwrap_or(DEFAULT_TEE_VCPU_TYPE);
+        build_sev_snp_config(true, vcpu_type, Some(settings))?;
+    }
+
+    let config = TemplateConfig {
+        vcpus: req.vcpus,
+        memory_mb: req.memory.as_deref().map(parse_memory_mb).transpose()?,
+        storage_bytes: req
+            .storage
+            .as_deref()
+            .map(parse_storage_bytes)
+            .transpose()?,
+        accelerator,
+        tee_mode: req.tee,
+        vcpu_type: req.vcpu_type,
+        sev_snp,
+        chain_id: req.chain_id,
+        dev_mode: req.dev,
+        block_time: req.block_time,
+        accounts: req.accounts,
+        disable_fee: req.disable_fee,
+        extra_args: req.extra_args,
+        ttl_secs: req.ttl.as_deref().map(parse_duration_secs).transpose()?,
+        idle_timeout_secs: req
+            .idle_timeout
+            .as_deref()
+            .map(parse_duration_secs)
+            .transpose()?,
+    };
+
+    let template = InstanceTemplate::new(req.name, req.description, config);
+    state.db.create_template(&template)?;
+
+    info!(name = %template.name, "Template created successfully");
+
+    Ok((StatusCode::CREATED, Json(template_to_response(template))))
+}
+
+/// List all templates
+/// GET /api/v1/templates
+pub async fn list_templates(
+    Extension(state): Extension<Arc<DaemonState>>,
+) -> ApiResult<Json<ListTemplatesResponse>> {
+    let templates = state.db.list_templates()?;
+    let total = templates.len();
+
+    Ok(Json(ListTemplatesResponse {
+        templates: templates.into_iter().map(template_to_response).collect(),
+        total,
+    }))
+}
+
+/// Get template by name
+/// GET /api/v1/templates/{name}
+pub async fn get_template(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<TemplateResponse>> {
+    let template = state.db.get_template(&name)?;
+    Ok(Json(template_to_response(template)))
+}
+
+/// Delete template
+/// DELETE /api/v1/templates/{name}
+pub async fn delete_template(
+    Extension(state): Extension<Arc<DaemonState>>,
+    Path(name): Path<String>,
+) -> ApiResult<StatusCode> {
+    info!(name = %name, "Deleting template via API");
+
+    // Instances created from the template keep their own copy of the settings
+    state.db.delete_template(&name)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -10,6 +10,8 @@ pub enum ApiError {
     NotFound(String),
     Conflict(String),
     BadRequest(String),
+    /// The operation is not valid for the instance's current state
+    InvalidState(String),
     Internal(String),
 }
 
@@ -19,6 +21,7 @@ impl IntoResponse for ApiError {
             ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", msg),
             ApiError::Conflict(msg) => (StatusCode::CONFLICT, "CONFLICT", msg),
             ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "BAD_REQUEST", msg),
+            ApiError::InvalidState(msg) => (StatusCode::CONFLICT, "INVALID_STATE", msg),
             ApiError::Internal(msg) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR", msg)
             }
@@ -44,7 +47,13 @@ impl From<HypervisorError> for ApiError {
             HypervisorError::InstanceAlreadyExists(name) => {
                 ApiError::Conflict(format!("Instance '{}' already exists", name))
             }
-            HypervisorError::InvalidStateTransition { from, to } => ApiError::BadRequest(
+            HypervisorError::TemplateNotFound(name) => {
+                ApiError::NotFound(format!("Template '{}' not found", name))
+            }
+            HypervisorError::TemplateAlreadyExists(name) => {
+                ApiError::Conflict(format!("Template '{}' already exists", name))
+            }
+            HypervisorError::InvalidStateTransition { from, to } => ApiError::InvalidState(
                 format!("Invalid state transition from {:?} to {:?}", from, to),
             ),
             HypervisorError::PortUnavailable(port) => {
@@ -53,6 +62,9 @@ impl From<HypervisorError> for ApiError {
             HypervisorError::NoPortsAvailable => {
                 ApiError::Conflict("No ports available".to_string())
             }
+            HypervisorError::NoVsockCidsAvailable(first) => {
+                ApiError::Conflict(format!("No vsock guest CIDs available from {}", first))
+            }
             HypervisorError::StorageQuotaExceeded { used, limit } => ApiError::BadRequest(
                 format!("Storage quota exceeded: used {}, limit {}", used, limit),
             ),
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::state::DaemonState;
+
+/// How often logs are checked against the rotation policy
+const ROTATION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Background task that rotates instance serial and Katana logs.
+///
+/// Stream readers detect the truncation and reopen the log, see
+/// `create_log_stream`.
+pub async fn run(state: Arc<DaemonState>) {
+    let mut interval = tokio::time::interval(ROTATION_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        // Rotation copies and gzips whole logs, so keep it off the runtime
+        let state = state.clone();
+        if let Err(e) = tokio::task::spawn_blocking(move || rotate_logs(&state)).await {
+            error!(error = %e, "Log rotation task panicked");
+        }
+    }
+}
+
+/// Rotate every instance's logs that exceed the policy
+fn rotate_logs(state: &DaemonState) {
+    let instances = match state.db.list_instances() {
+        Ok(instances) => instances,
+        Err(e) => {
+            error!(error = %e, "Failed to list instances for log rotation");
+            return;
+        }
+    };
+
+    for instance in instances {
+        let logs = instance
+            .serial_log
+            .clone()
+            .into_iter()
+            .chain([instance.katana_log()]);
+
+        for log in logs {
+            match state.log_rotation.rotate_if_needed(&log) {
+                Ok(true) => info!(name = %instance.name, log = %log.display(), "Rotated log"),
+                Ok(false) => {}
+                Err(e) => {
+                    error!(name = %instance.name, log = %log.display(), error = %e, "Failed to rotate log")
+                }
+            }
+        }
+    }
+}
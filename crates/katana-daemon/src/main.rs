@@ -1,8 +1,8 @@
 use anyhow::{Context, Result};
 use axum::{
-    extract::Extension,
+    extract::{DefaultBodyLimit, Extension},
     response::{IntoResponse, Json},
-    routing::{get, post},
+    routing::{get, patch, post, put},
     Router,
 };
 use hyper::body::Incoming;
@@ -20,11 +20,17 @@ use tracing::{info, Level};
 
 mod api;
 mod error;
+mod log_rotation;
 mod models;
+mod reaper;
 mod state;
+mod stats_sampler;
 
 use state::DaemonState;
 
+/// Largest request body accepted, e.g. for a custom genesis
+const MAX_REQUEST_BODY: usize = 64 * 1024 * 1024;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize tracing
@@ -50,6 +56,15 @@ async fn main() -> Result<()> {
 
     info!("Initializing daemon state");
 
+    // Enforce instance TTLs and idle timeouts in the background
+    tokio::spawn(reaper::Reaper::new(state.clone()).run());
+
+    // Keep serial logs within the configured size and retention
+    tokio::spawn(log_rotation::run(state.clone()));
+
+    // Record resource usage history of running instances
+    tokio::spawn(stats_sampler::StatsSampler::new(state.clone()).run());
+
     // Bind UNIX socket
     let listener = UnixListener::bind(&socket_path).context("Failed to bind UNIX socket")?;
 
@@ -97,6 +112,8 @@ fn build_router(state: Arc<DaemonState>) -> Router {
         .route("/health", get(health_check))
         .route("/version", get(get_version))
         .nest("/api/v1", api_routes())
+        // Custom genesis files with predeployed classes run to several MB
+        .layer(DefaultBodyLimit::max(MAX_REQUEST_BODY))
         .layer(layer)
 }
 
@@ -109,7 +126,15 @@ fn api_routes() -> Router {
         )
         .route(
             "/instances/:name",
-            get(api::get_instance).delete(api::delete_instance),
+            get(api::get_instance)
+                .put(api::update_instance)
+                .delete(api::delete_instance),
+        )
+        .route("/instances/:name/labels", patch(api::update_labels))
+        .route("/instances/:name/secrets", get(api::list_secrets))
+        .route(
+            "/instances/:name/secrets/:secret",
+            put(api::set_secret).delete(api::delete_secret),
         )
         // Instance operations
         .route("/instances/:name/start", post(api::start_instance))
@@ -117,11 +142,30 @@ fn api_routes() -> Router {
         .route("/instances/:name/pause", post(api::pause_instance))
         .route("/instances/:name/resume", post(api::resume_instance))
         .route("/instances/:name/suspend", post(api::suspend_instance))
+        .route("/instances/:name/wake", post(api::wake_instance))
         .route("/instances/:name/reset", post(api::reset_instance))
         // Monitoring
         .route("/instances/:name/logs", get(api::get_logs))
         .route("/instances/:name/logs/stream", get(api::stream_logs))
         .route("/instances/:name/stats", get(api::get_stats))
+        .route(
+            "/instances/:name/stats/history",
+            get(api::get_stats_history),
+        )
+        .route("/instances/:name/accounts", get(api::get_accounts))
+        .route("/instances/:name/attestation", get(api::get_attestation))
+        .route("/stats/stream", get(api::stream_stats))
+        // Templates
+        .route(
+            "/templates",
+            get(api::list_templates).post(api::create_template),
+        )
+        .route(
+            "/templates/:name",
+            get(api::get_template).delete(api::delete_template),
+        )
+        // Host
+        .route("/host", get(api::get_host_info))
 }
 
 // Health check endpoint
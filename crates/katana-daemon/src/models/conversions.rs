@@ -1,10 +1,22 @@
 use chrono::DateTime;
-use katana_core::instance::{InstanceState, InstanceStatus};
-use katana_models::{EndpointsResponse, InstanceConfigResponse, InstanceResponse};
+use katana_core::host::{HostCapabilities, ToolStatus};
+use katana_core::instance::config_drive::TLS_DIR;
+use katana_core::instance::{
+    InstanceState, InstanceStatus, InstanceTemplate, MessagingConfig, SevSnpSettings,
+};
+use katana_core::logs::parse_line;
+use katana_core::rpc::{decode_short_string, ChainStats};
+use katana_core::secrets::{SecretMetadata, SecretsDelivery};
+use katana_core::stats::StatsSample;
+use katana_models::{
+    ChainAccountInfo, ChainInfo, EndpointsResponse, HostInfoResponse, InstanceConfigResponse,
+    InstanceResponse, KvmInfo, LogRecord, MessagingSettings, NbdInfo, QemuInfo, SecretInfo,
+    SecretsDeliveryInfo, SevInfo, SevSnpRequest, StatsSampleInfo, TemplateResponse, ToolInfo,
+};
 
-/// Convert InstanceState from core to InstanceResponse for API
-pub fn instance_state_to_response(state: InstanceState) -> InstanceResponse {
-    let status_str = match &state.status {
+/// Status name as shown by the API, e.g. "Running"
+pub fn instance_status_name(status: &InstanceStatus) -> String {
+    match status {
         InstanceStatus::Created => "Created",
         InstanceStatus::Starting => "Starting",
         InstanceStatus::Running => "Running",
@@ -17,7 +29,13 @@ pub fn instance_state_to_response(state: InstanceState) -> InstanceResponse {
         InstanceStatus::Stopped => "Stopped",
         InstanceStatus::Failed { error: _ } => "Failed",
     }
-    .to_string();
+    .to_string()
+}
+
+/// Convert InstanceState from core to InstanceResponse for API
+pub fn instance_state_to_response(state: InstanceState) -> InstanceResponse {
+    let status_str = instance_status_name(&state.status);
+    let tls = state.config_dir().join(TLS_DIR).exists();
 
     let endpoints = if matches!(state.status, InstanceStatus::Running) {
         Some(EndpointsResponse {
@@ -42,6 +60,29 @@ pub fn instance_state_to_response(state: InstanceState) -> InstanceResponse {
             rpc_port: state.config.rpc_port,
             metrics_port: state.config.metrics_port,
             tee_mode: state.config.tee_mode,
+            vsock_cid: state.config.vsock_cid,
+            accelerator: state.config.accelerator.to_string(),
+            balloon: state.config.balloon,
+            vcpu_type: state.config.vcpu_type,
+            chain_id: state.config.chain_id,
+            dev: state.config.dev_mode,
+            block_time: state.config.block_time,
+            accounts: state.config.accounts,
+            disable_fee: state.config.disable_fee,
+            extra_args: state.config.extra_args,
+            fork_provider: state.config.fork_provider,
+            fork_block: state.config.fork_block,
+            messaging: state.config.messaging.map(messaging_to_settings),
+            chain_spec: state.config.chain_spec.map(|kind| kind.to_string()),
+            tls,
+            env: state.config.env,
+            config_drive_args: state.config.config_drive_args,
+            expires_at: state
+                .config
+                .expires_at
+                .and_then(|ts| DateTime::from_timestamp(ts, 0))
+                .map(|dt| dt.to_rfc3339()),
+            idle_timeout_secs: state.config.idle_timeout_secs,
         },
         created_at: DateTime::from_timestamp(state.created_at, 0)
             .unwrap_or_default()
@@ -50,5 +91,208 @@ pub fn instance_state_to_response(state: InstanceState) -> InstanceResponse {
             .unwrap_or_default()
             .to_rfc3339(),
         endpoints,
+        accelerator: state.accelerator.map(|a| a.to_string()),
+        labels: state.labels,
+        annotations: state.annotations,
+        secrets_delivery: state.secrets_delivery.map(secrets_delivery_to_info),
+    }
+}
+
+/// Convert a secrets delivery outcome to SecretsDeliveryInfo for API
+pub fn secrets_delivery_to_info(delivery: SecretsDelivery) -> SecretsDeliveryInfo {
+    let (state, error) = match delivery {
+        SecretsDelivery::Pending => ("pending", None),
+        SecretsDelivery::Delivered => ("delivered", None),
+        SecretsDelivery::Failed { error } => ("failed", Some(error)),
+    };
+
+    SecretsDeliveryInfo {
+        state: state.to_string(),
+        error,
+    }
+}
+
+/// Convert core messaging settings to MessagingSettings for API
+pub fn messaging_to_settings(messaging: MessagingConfig) -> MessagingSettings {
+    MessagingSettings {
+        chain: Some(messaging.chain.to_string()),
+        rpc_url: messaging.rpc_url,
+        l1_instance: messaging.l1_instance,
+        contract_address: messaging.contract_address,
+        sender_address: messaging.sender_address,
+        interval_secs: Some(messaging.interval_secs),
+        from_block: Some(messaging.from_block),
+        key_secret: Some(messaging.key_secret),
+    }
+}
+
+/// Convert InstanceTemplate from core to TemplateResponse for API
+pub fn template_to_response(template: InstanceTemplate) -> TemplateResponse {
+    let config = template.config;
+
+    TemplateResponse {
+        name: template.name,
+        description: template.description,
+        vcpus: config.vcpus,
+        memory_mb: config.memory_mb,
+        storage_bytes: config.storage_bytes,
+        dev: config.dev_mode,
+        tee: config.tee_mode,
+        vcpu_type: config.vcpu_type,
+        sev_snp: config.sev_snp.map(sev_snp_settings_to_request),
+        accelerator: config.accelerator.map(|a| a.to_string()),
+        chain_id: config.chain_id,
+        block_time: config.block_time,
+        accounts: config.accounts,
+        disable_fee: config.disable_fee,
+        extra_args: config.extra_args,
+        ttl_secs: config.ttl_secs,
+        idle_timeout_secs: config.idle_timeout_secs,
+        created_at: DateTime::from_timestamp(template.created_at, 0)
+            .unwrap_or_default()
+            .to_rfc3339(),
+        updated_at: DateTime::from_timestamp(template.updated_at, 0)
+            .unwrap_or_default()
+            .to_rfc3339(),
+    }
+}
+
+/// Convert SEV-SNP launch settings from the API
+pub fn sev_snp_request_to_settings(request: SevSnpRequest) -> SevSnpSettings {
+    SevSnpSettings {
+        cbitpos: request.cbitpos,
+        reduced_phys_bits: request.reduced_phys_bits,
+        policy: request.policy,
+        host_data: request.host_data,
+        id_block: request.id_block,
+        id_auth: request.id_auth,
+        kernel_hashes: request.kernel_hashes,
+    }
+}
+
+/// Convert a template's SEV-SNP launch settings for the API
+pub fn sev_snp_settings_to_request(settings: SevSnpSettings) -> SevSnpRequest {
+    SevSnpRequest {
+        cbitpos: settings.cbitpos,
+        reduced_phys_bits: settings.reduced_phys_bits,
+        policy: settings.policy,
+        host_data: settings.host_data,
+        id_block: settings.id_block,
+        id_auth: settings.id_auth,
+        kernel_hashes: settings.kernel_hashes,
+    }
+}
+
+/// Convert a stored secret's metadata to SecretInfo for API
+pub fn secret_metadata_to_info(secret: SecretMetadata) -> SecretInfo {
+    SecretInfo {
+        name: secret.name,
+        created_at: DateTime::from_timestamp(secret.created_at, 0)
+            .unwrap_or_default()
+            .to_rfc3339(),
+        updated_at: DateTime::from_timestamp(secret.updated_at, 0)
+            .unwrap_or_default()
+            .to_rfc3339(),
+    }
+}
+
+/// Parse a log line into a record, keeping unparsed lines as the message
+pub fn log_line_to_record(line: &str, source: &str) -> LogRecord {
+    let source = Some(source.to_string());
+    match parse_line(line) {
+        Some(record) => LogRecord {
+            source,
+            timestamp: Some(record.timestamp),
+            level: Some(record.level),
+            target: Some(record.target),
+            message: record.message,
+        },
+        None => LogRecord {
+            source,
+            timestamp: None,
+            level: None,
+            target: None,
+            message: line.to_string(),
+        },
+    }
+}
+
+/// Convert a stored resource usage sample for the API
+pub fn stats_sample_to_info(sample: StatsSample) -> StatsSampleInfo {
+    StatsSampleInfo {
+        timestamp: DateTime::from_timestamp(sample.timestamp, 0)
+            .unwrap_or_default()
+            .to_rfc3339(),
+        cpu_percent: sample.cpu_percent,
+        rss_bytes: sample.rss_bytes,
+        disk_bytes: sample.disk_bytes,
+        net_rx_bytes: sample.net_rx_bytes,
+        net_tx_bytes: sample.net_tx_bytes,
+        block_read_bytes: sample.block_read_bytes,
+        block_write_bytes: sample.block_write_bytes,
+    }
+}
+
+/// Convert the Katana RPC probe result for the API
+pub fn chain_stats_to_info(stats: ChainStats) -> ChainInfo {
+    ChainInfo {
+        block_number: stats.block_number,
+        latest_block_timestamp: stats
+            .latest_block_timestamp
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp as i64, 0))
+            .map(|timestamp| timestamp.to_rfc3339()),
+        chain_name: stats.chain_id.as_deref().and_then(decode_short_string),
+        chain_id: stats.chain_id,
+        pending_transactions: stats.pending_transactions,
+        accounts: stats
+            .accounts
+            .into_iter()
+            .map(|account| ChainAccountInfo {
+                address: account.address,
+                balance: account.balance,
+            })
+            .collect(),
+    }
+}
+
+/// Convert HostCapabilities from core to HostInfoResponse for API
+pub fn host_capabilities_to_response(caps: HostCapabilities) -> HostInfoResponse {
+    let tool = |status: ToolStatus| ToolInfo {
+        available: status.available(),
+        path: status.path.map(|p| p.display().to_string()),
+        version: status.version,
+    };
+
+    let q35_supported = caps.qemu.supports_q35();
+    let snp_supported = caps.sev.snp_supported();
+
+    HostInfoResponse {
+        kvm: KvmInfo {
+            present: caps.kvm.present,
+            accessible: caps.kvm.accessible,
+            error: caps.kvm.error,
+        },
+        qemu: QemuInfo {
+            binary: tool(caps.qemu.binary),
+            machine_types: caps.qemu.machine_types,
+            q35_supported,
+        },
+        qemu_img: tool(caps.qemu_img),
+        qemu_nbd: tool(caps.qemu_nbd),
+        nbd: NbdInfo {
+            module_loaded: caps.nbd.module_loaded,
+            devices: caps.nbd.devices,
+        },
+        sev: SevInfo {
+            device: caps.sev.device,
+            sev: caps.sev.sev,
+            sev_es: caps.sev.sev_es,
+            sev_snp: caps.sev.sev_snp,
+            snp_supported,
+        },
+        vhost_vsock: caps.vhost_vsock,
+        cpu_count: caps.cpu_count,
+        memory_total_bytes: caps.memory_total_bytes,
+        memory_available_bytes: caps.memory_available_bytes,
     }
 }
@@ -0,0 +1,319 @@
+use anyhow::Result;
+use katana_core::{
+    instance::{InstanceState, InstanceStatus},
+    port::activity::port_has_recent_connections,
+    qemu::ManagedVm,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::state::DaemonState;
+
+/// How often instances are checked for expiry and idleness
+const REAP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Grace period given to the guest when the reaper stops it
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Background task that enforces instance lifecycle policies.
+///
+/// Expired instances are stopped and deleted. Running instances with an idle
+/// timeout are stopped once their RPC port has seen no connections for that
+/// long.
+pub struct Reaper {
+    state: Arc<DaemonState>,
+    /// Last time each running instance was seen with RPC connections
+    last_active: HashMap<String, Instant>,
+}
+
+impl Reaper {
+    pub fn new(state: Arc<DaemonState>) -> Self {
+        Self {
+            state,
+            last_active: HashMap::new(),
+        }
+    }
+
+    /// Run forever, checking instances every `REAP_INTERVAL`
+    pub async fn run(mut self) {
+        let mut interval = tokio::time::interval(REAP_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.reap().await {
+                error!(error = %e, "Failed to reap instances");
+            }
+        }
+    }
+
+    async fn reap(&mut self) -> Result<()> {
+        let instances = self.state.db.list_instances()?;
+        let actions = self.plan(
+            instances,
+            chrono::Utc::now().timestamp(),
+            Instant::now(),
+            port_has_recent_connections,
+        );
+
+        for (instance, action) in actions {
+            let result = match action {
+                Action::Delete => self.delete_expired(&instance).await,
+                Action::Stop => self.stop_idle(&instance).await,
+            };
+
+            if let Err(e) = result {
+                error!(name = %instance.name, error = %e, "Failed to apply lifecycle policy");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decide which instances to delete or stop, updating idle timers.
+    ///
+    /// `now` is the Unix time checked against expiry, `at` the clock idle
+    /// timers run on, and `has_connections` reports RPC port activity.
+    fn plan(
+        &mut self,
+        instances: Vec<InstanceState>,
+        now: i64,
+        at: Instant,
+        has_connections: impl Fn(u16) -> bool,
+    ) -> Vec<(InstanceState, Action)> {
+        // Forget instances that are gone or no longer running
+        self.last_active.retain(|id, _| {
+            instances
+                .iter()
+                .any(|i| &i.id == id && i.status == InstanceStatus::Running)
+        });
+
+        let mut actions = Vec::new();
+        for instance in instances {
+            if instance.config.is_expired(now) {
+                actions.push((instance, Action::Delete));
+            } else if self.is_idle(&instance, at, &has_connections) {
+                actions.push((instance, Action::Stop));
+            }
+        }
+
+        actions
+    }
+
+    /// Whether a running instance has gone its idle timeout without RPC connections
+    fn is_idle(
+        &mut self,
+        instance: &InstanceState,
+        at: Instant,
+        has_connections: &impl Fn(u16) -> bool,
+    ) -> bool {
+        let Some(timeout) = instance.config.idle_timeout_secs else {
+            return false;
+        };
+
+        if instance.status != InstanceStatus::Running {
+            return false;
+        }
+
+        // Instances are considered active when first seen, so a freshly
+        // started instance gets a full timeout before being stopped
+        let last_active = self.last_active.entry(instance.id.clone()).or_insert(at);
+        if has_connections(instance.config.rpc_port) {
+            *last_active = at;
+            return false;
+        }
+
+        at.duration_since(*last_active) >= Duration::from_secs(timeout)
+    }
+
+    async fn delete_expired(&mut self, instance: &InstanceState) -> Result<()> {
+        info!(name = %instance.name, "Instance expired, deleting");
+
+        if is_active(&instance.status) {
+            let stopped = match ManagedVm::from_instance(&instance.id, &self.state.db).await {
+                Ok(mut vm) => vm.stop(STOP_GRACE_PERIOD).await,
+                Err(e) => Err(e),
+            };
+
+            // The instance is going away anyway, so don't let a wedged VM keep it alive
+            if let Err(e) = stopped {
+                warn!(name = %instance.name, error = %e, "Graceful stop failed, forcing cleanup");
+                ManagedVm::force_cleanup(&instance.id, &self.state.db).await?;
+            }
+        }
+
+        self.state.storage.delete_instance_storage(&instance.id)?;
+
+        // Cascades to ports
+        self.state.db.delete_instance(&instance.name)?;
+        self.last_active.remove(&instance.id);
+
+        info!(name = %instance.name, "Expired instance deleted");
+        Ok(())
+    }
+
+    async fn stop_idle(&mut self, instance: &InstanceState) -> Result<()> {
+        info!(
+            name = %instance.name,
+            idle_timeout_secs = instance.config.idle_timeout_secs.unwrap_or_default(),
+            "Instance idle, stopping"
+        );
+
+        ManagedVm::from_instance(&instance.id, &self.state.db)
+            .await?
+            .stop(STOP_GRACE_PERIOD)
+            .await?;
+        self.last_active.remove(&instance.id);
+
+        info!(name = %instance.name, "Idle instance stopped");
+        Ok(())
+    }
+}
+
+/// What the reaper does with an instance on a given check
+#[derive(Debug, PartialEq)]
+enum Action {
+    /// Expired: stop if needed, then delete it with its storage
+    Delete,
+    /// Idle past its timeout: stop it
+    Stop,
+}
+
+/// Whether the instance has a QEMU process that must be stopped first
+fn is_active(status: &InstanceStatus) -> bool {
+    !matches!(
+        status,
+        InstanceStatus::Created | InstanceStatus::Stopped | InstanceStatus::Failed { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use katana_core::instance::InstanceConfig;
+    use tempfile::TempDir;
+
+    /// Save an instance with a data directory holding a file
+    fn save_instance(state: &DaemonState, id: &str, config: InstanceConfig) -> InstanceState {
+        let data_dir = state.storage.get_paths(id).instance_dir;
+        std::fs::create_dir_all(&data_dir).unwrap();
+        std::fs::write(data_dir.join("katana-data.qcow2"), b"disk").unwrap();
+
+        let config = InstanceConfig { data_dir, ..config };
+        let instance = InstanceState::new(id.to_string(), format!("{id}-name"), config);
+        state.db.save_instance(&instance).unwrap();
+        instance
+    }
+
+    fn idle_after(secs: u64) -> InstanceConfig {
+        InstanceConfig {
+            idle_timeout_secs: Some(secs),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expired_created_instance_deleted_with_storage() {
+        let dir = TempDir::new().unwrap();
+        let state = Arc::new(DaemonState::for_tests(dir.path()));
+
+        let expired = InstanceConfig {
+            expires_at: Some(1_000),
+            ..Default::default()
+        };
+        let expired = save_instance(&state, "expired", expired);
+        state.db.allocate_port("expired", 5050, "rpc").unwrap();
+        let kept = save_instance(&state, "kept", InstanceConfig::default());
+
+        Reaper::new(state.clone()).reap().await.unwrap();
+
+        assert!(state.db.get_instance(&expired.name).is_err());
+        assert!(!expired.config.data_dir.exists());
+        assert!(state.db.get_allocated_ports().unwrap().is_empty());
+
+        assert!(state.db.get_instance(&kept.name).is_ok());
+        assert!(kept.config.data_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_expired_instance_force_cleaned_when_stop_fails() {
+        let dir = TempDir::new().unwrap();
+        let state = Arc::new(DaemonState::for_tests(dir.path()));
+
+        let config = InstanceConfig {
+            expires_at: Some(1_000),
+            ..Default::default()
+        };
+        let mut instance = save_instance(&state, "wedged", config);
+        // No QEMU behind this PID, so attaching for a graceful stop fails
+        instance.status = InstanceStatus::Running;
+        instance.vm_pid = Some(i32::MAX);
+        state.db.save_instance(&instance).unwrap();
+        state
+            .db
+            .allocate_vsock_cid("wedged", state.vsock_cid_base, |_| false)
+            .unwrap();
+
+        Reaper::new(state.clone()).reap().await.unwrap();
+
+        assert!(state.db.get_instance(&instance.name).is_err());
+        assert!(!instance.config.data_dir.exists());
+        assert_eq!(state.db.get_vsock_cid("wedged").unwrap(), None);
+    }
+
+    #[test]
+    fn test_idle_timer_resets_on_activity() {
+        let dir = TempDir::new().unwrap();
+        let state = Arc::new(DaemonState::for_tests(dir.path()));
+        let mut instance = save_instance(&state, "idle", idle_after(60));
+        instance.status = InstanceStatus::Running;
+
+        let mut reaper = Reaper::new(state);
+        let start = Instant::now();
+        let mut check = |secs: u64, busy: bool| {
+            reaper
+                .plan(
+                    vec![instance.clone()],
+                    0,
+                    start + Duration::from_secs(secs),
+                    |_| busy,
+                )
+                .into_iter()
+                .map(|(_, action)| action)
+                .collect::<Vec<_>>()
+        };
+
+        // First sighting starts the timer
+        assert!(check(0, false).is_empty());
+        assert!(check(50, true).is_empty());
+        // Measured from the last connection, not the first sighting
+        assert!(check(100, false).is_empty());
+        assert_eq!(check(110, false), vec![Action::Stop]);
+    }
+
+    #[test]
+    fn test_idle_timer_forgotten_once_not_running() {
+        let dir = TempDir::new().unwrap();
+        let state = Arc::new(DaemonState::for_tests(dir.path()));
+        let mut instance = save_instance(&state, "restarted", idle_after(60));
+        let start = Instant::now();
+        let mut reaper = Reaper::new(state);
+
+        instance.status = InstanceStatus::Running;
+        assert!(reaper
+            .plan(vec![instance.clone()], 0, start, |_| false)
+            .is_empty());
+        assert!(reaper.last_active.contains_key("restarted"));
+
+        // Stopped by hand, then started again much later
+        instance.status = InstanceStatus::Stopped;
+        let later = start + Duration::from_secs(120);
+        assert!(reaper
+            .plan(vec![instance.clone()], 0, later, |_| false)
+            .is_empty());
+        assert!(reaper.last_active.is_empty());
+
+        instance.status = InstanceStatus::Running;
+        assert!(reaper.plan(vec![instance], 0, later, |_| false).is_empty());
+    }
+}
@@ -1,16 +1,37 @@
 use anyhow::{Context, Result};
+use byte_unit::Byte;
 use katana_core::{
     instance::StorageManager,
+    logs::LogRotationPolicy,
     port::PortAllocator,
+    secrets::{SecretsKey, SECRETS_KEY_FILE},
     state::StateDatabase,
+    vsock::FIRST_GUEST_CID,
 };
 use std::path::PathBuf;
+use std::time::Duration;
+
+/// Default time between resource usage samples
+const DEFAULT_STATS_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Default time resource usage samples are kept
+const DEFAULT_STATS_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
 
 /// Daemon state shared across request handlers
 pub struct DaemonState {
     pub db: StateDatabase,
     pub storage: StorageManager,
     pub port_allocator: PortAllocator,
+    pub log_rotation: LogRotationPolicy,
+    /// Host key sealing instance secrets in the database
+    pub secrets_key: SecretsKey,
+    /// Time between resource usage samples
+    pub stats_interval: Duration,
+    /// How long resource usage samples are kept
+    pub stats_retention: Duration,
+    /// Lowest vsock guest CID handed to instances. CIDs are global to the
+    /// host, so daemons sharing one should use disjoint ranges.
+    pub vsock_cid_base: u32,
 }
 
 impl DaemonState {
@@ -43,12 +64,110 @@ impl DaemonState {
 
         let storage = StorageManager::new(instances_dir);
 
+        let secrets_key = SecretsKey::load_or_create(&state_dir.join(SECRETS_KEY_FILE))
+            .context("Failed to load secrets key")?;
+
         let port_allocator = PortAllocator::new(db.clone());
 
+        let log_rotation = log_rotation_from_env()?;
+        tracing::info!(
+            "Serial log rotation: max {} bytes, keep {}, compress {}",
+            log_rotation.max_bytes,
+            log_rotation.keep,
+            log_rotation.compress
+        );
+
+        let stats_interval = duration_from_env("KATANA_STATS_INTERVAL", DEFAULT_STATS_INTERVAL)?;
+        let stats_retention = duration_from_env("KATANA_STATS_RETENTION", DEFAULT_STATS_RETENTION)?;
+        tracing::info!(
+            "Stats sampling: every {}, kept for {}",
+            humantime::format_duration(stats_interval),
+            humantime::format_duration(stats_retention)
+        );
+
+        let vsock_cid_base = vsock_cid_base_from_env()?;
+        tracing::info!("Vsock guest CIDs from {}", vsock_cid_base);
+
         Ok(Self {
             db,
             storage,
             port_allocator,
+            log_rotation,
+            secrets_key,
+            stats_interval,
+            stats_retention,
+            vsock_cid_base,
         })
     }
+
+    /// State rooted in a scratch directory, for handler tests
+    #[cfg(test)]
+    pub(crate) fn for_tests(dir: &std::path::Path) -> Self {
+        let db = StateDatabase::new(&dir.join("state.db")).unwrap();
+        Self {
+            storage: StorageManager::new(dir.join("instances")),
+            port_allocator: PortAllocator::new(db.clone()),
+            log_rotation: LogRotationPolicy::default(),
+            secrets_key: SecretsKey::load_or_create(&dir.join(SECRETS_KEY_FILE)).unwrap(),
+            stats_interval: DEFAULT_STATS_INTERVAL,
+            stats_retention: DEFAULT_STATS_RETENTION,
+            vsock_cid_base: FIRST_GUEST_CID,
+            db,
+        }
+    }
+}
+
+/// Read the serial log policy from `KATANA_LOG_MAX_SIZE` (e.g. "10MiB"),
+/// `KATANA_LOG_KEEP` and `KATANA_LOG_COMPRESS`, falling back to the defaults
+fn log_rotation_from_env() -> Result<LogRotationPolicy> {
+    let mut policy = LogRotationPolicy::default();
+
+    if let Ok(value) = std::env::var("KATANA_LOG_MAX_SIZE") {
+        policy.max_bytes = Byte::parse_str(&value, true)
+            .with_context(|| format!("Invalid KATANA_LOG_MAX_SIZE '{}'", value))?
+            .as_u64();
+    }
+    if let Ok(value) = std::env::var("KATANA_LOG_KEEP") {
+        policy.keep = value
+            .parse()
+            .with_context(|| format!("Invalid KATANA_LOG_KEEP '{}'", value))?;
+    }
+    if let Ok(value) = std::env::var("KATANA_LOG_COMPRESS") {
+        policy.compress = value
+            .parse()
+            .with_context(|| format!("Invalid KATANA_LOG_COMPRESS '{}'", value))?;
+    }
+
+    Ok(policy)
+}
+
+/// Read the lowest guest CID from `KATANA_VSOCK_CID_BASE`
+fn vsock_cid_base_from_env() -> Result<u32> {
+    let Ok(value) = std::env::var("KATANA_VSOCK_CID_BASE") else {
+        return Ok(FIRST_GUEST_CID);
+    };
+
+    let base: u32 = value
+        .parse()
+        .with_context(|| format!("Invalid KATANA_VSOCK_CID_BASE '{}'", value))?;
+    anyhow::ensure!(
+        base >= FIRST_GUEST_CID,
+        "KATANA_VSOCK_CID_BASE must be at least {}",
+        FIRST_GUEST_CID
+    );
+
+    Ok(base)
+}
+
+/// Read a non-zero duration such as "30s" from an environment variable
+fn duration_from_env(name: &str, default: Duration) -> Result<Duration> {
+    let Ok(value) = std::env::var(name) else {
+        return Ok(default);
+    };
+
+    let duration = humantime::parse_duration(&value)
+        .with_context(|| format!("Invalid {} '{}'", name, value))?;
+    anyhow::ensure!(!duration.is_zero(), "{} must be greater than zero", name);
+
+    Ok(duration)
 }
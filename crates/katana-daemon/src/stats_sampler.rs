@@ -0,0 +1,146 @@
+use anyhow::Result;
+use katana_core::{
+    instance::{InstanceState, InstanceStatus},
+    qemu::QmpClient,
+    stats::{allocated_bytes, ProcessCounters, StatsSample},
+    vsock::VsockClient,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{debug, error};
+
+use crate::state::DaemonState;
+
+/// Background task that records resource usage of running instances.
+///
+/// Samples are stored in the state database and kept for the configured
+/// retention period, see `GET /api/v1/instances/{name}/stats/history`.
+pub struct StatsSampler {
+    state: Arc<DaemonState>,
+    /// Process counters from each running instance's previous sample, used
+    /// to work out CPU usage in between
+    previous: HashMap<String, (ProcessCounters, Instant)>,
+}
+
+impl StatsSampler {
+    pub fn new(state: Arc<DaemonState>) -> Self {
+        Self {
+            state,
+            previous: HashMap::new(),
+        }
+    }
+
+    /// Run forever, sampling every `stats_interval`
+    pub async fn run(mut self) {
+        let mut interval = tokio::time::interval(self.state.stats_interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.sample_all().await {
+                error!(error = %e, "Failed to sample instance stats");
+            }
+        }
+    }
+
+    async fn sample_all(&mut self) -> Result<()> {
+        let instances = self.state.db.list_instances()?;
+
+        // Forget instances that are gone or no longer running
+        self.previous.retain(|id, _| {
+            instances
+                .iter()
+                .any(|i| &i.id == id && i.status == InstanceStatus::Running)
+        });
+
+        for instance in &instances {
+            let Some(pid) = instance.vm_pid else {
+                continue;
+            };
+            if instance.status != InstanceStatus::Running {
+                continue;
+            }
+
+            let sample = self.sample(instance, pid).await;
+            if let Err(e) = self.state.db.insert_stats_sample(&sample) {
+                error!(name = %instance.name, error = %e, "Failed to save stats sample");
+            }
+        }
+
+        let retention = chrono::Duration::from_std(self.state.stats_retention)?;
+        let cutoff = (chrono::Utc::now() - retention).timestamp();
+        self.state.db.prune_stats_samples(cutoff)?;
+
+        Ok(())
+    }
+
+    /// Take one sample. Counters that can't be read are left out rather
+    /// than failing the whole sample.
+    async fn sample(&mut self, instance: &InstanceState, pid: i32) -> StatsSample {
+        let now = Instant::now();
+
+        let counters = ProcessCounters::read(pid)
+            .inspect_err(
+                |e| debug!(name = %instance.name, error = %e, "Failed to read process counters"),
+            )
+            .ok();
+        let cpu_percent = counters.and_then(|counters| {
+            let (previous, at) = self.previous.get(&instance.id)?;
+            counters.cpu_percent_since(previous, now.duration_since(*at))
+        });
+        if let Some(counters) = counters {
+            self.previous.insert(instance.id.clone(), (counters, now));
+        }
+
+        let disk_bytes = instance
+            .config
+            .disk_image
+            .as_deref()
+            .and_then(|disk| allocated_bytes(disk).ok());
+
+        let (block_read_bytes, block_write_bytes) = match &instance.qmp_socket {
+            Some(socket) => block_io(socket)
+                .await
+                .inspect_err(
+                    |e| debug!(name = %instance.name, error = %e, "Failed to query block stats"),
+                )
+                .map_or((None, None), |(read, write)| (Some(read), Some(write))),
+            None => (None, None),
+        };
+
+        // User networking has no host-side counters, so ask the guest agent
+        let (net_rx_bytes, net_tx_bytes) = match instance.config.vsock_cid {
+            Some(cid) => match VsockClient::new(cid).metrics().await {
+                Ok(metrics) => (metrics.net_rx_bytes, metrics.net_tx_bytes),
+                Err(e) => {
+                    debug!(name = %instance.name, error = %e, "Failed to read guest metrics");
+                    (None, None)
+                }
+            },
+            None => (None, None),
+        };
+
+        StatsSample {
+            instance_id: instance.id.clone(),
+            timestamp: chrono::Utc::now().timestamp(),
+            cpu_percent,
+            rss_bytes: counters.map(|counters| counters.rss_bytes),
+            disk_bytes,
+            net_rx_bytes,
+            net_tx_bytes,
+            block_read_bytes,
+            block_write_bytes,
+        }
+    }
+}
+
+/// Total bytes read and written across the VM's block devices
+async fn block_io(qmp_socket: &Path) -> katana_core::Result<(u64, u64)> {
+    let mut qmp = QmpClient::new();
+    qmp.connect(qmp_socket).await?;
+
+    let stats = qmp.query_blockstats().await?;
+    Ok(stats.iter().fold((0, 0), |(read, write), device| {
+        (read + device.stats.rd_bytes, write + device.stats.wr_bytes)
+    }))
+}
@@ -1,24 +1,120 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 // ============================================================================
 // Request Types
 // ============================================================================
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Create an instance.
+///
+/// Unset settings are taken from `template` if given, otherwise from the
+/// daemon defaults (2 vCPUs, 2G memory, 10G storage, dev mode).
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct CreateInstanceRequest {
     pub name: String,
-    pub vcpus: u32,
-    pub memory: String,  // e.g., "4G", "2048M"
-    pub storage: String, // e.g., "10G", "5120M"
+    /// Template providing defaults for unset settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub vcpus: Option<u32>,
+    #[serde(default)]
+    pub memory: Option<String>, // e.g., "4G", "2048M"
+    #[serde(default)]
+    pub storage: Option<String>, // e.g., "10G", "5120M"
     #[serde(default)]
     pub port: Option<u16>,
+    #[serde(default)]
+    pub dev: Option<bool>,
+    #[serde(default)]
+    pub tee: Option<bool>,
+    /// vCPU model, defaults to "host" or "EPYC-v4" in TEE mode
+    #[serde(default)]
+    pub vcpu_type: Option<String>,
+    #[serde(default)]
+    pub chain_id: Option<String>,
+    #[serde(default)]
+    pub block_time: Option<u64>,
+    #[serde(default)]
+    pub accounts: Option<u16>,
+    #[serde(default)]
+    pub disable_fee: Option<bool>,
+    /// Appended after the template's extra args
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    /// Starknet RPC URL to fork state from. `localhost` means the host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_provider: Option<String>,
+    /// Block to fork at, defaults to the provider's latest. Requires `fork_provider`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_block: Option<u64>,
+    /// L1 messaging with a settlement chain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub messaging: Option<MessagingSettings>,
+    /// Contents of a Katana genesis JSON file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genesis: Option<String>,
+    /// Contents of a Katana chain spec (`chain.toml`); conflicts with `genesis`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_spec: Option<String>,
+    /// Environment variables for Katana, delivered on the config drive
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Pass Katana's args on the config drive only, not on the kernel cmdline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_drive_args: Option<bool>,
+    /// PEM certificate chain put on the config drive; requires `tls_key`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<String>,
+    /// PEM private key put on the config drive; requires `tls_cert`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<String>,
+    /// Hardware acceleration: "kvm", "tcg" or "auto" (default)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accelerator: Option<String>,
+    /// Attach a memory balloon device (not for TEE instances)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balloon: Option<bool>,
+    /// SEV-SNP launch settings, only valid together with `tee`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sev_snp: Option<SevSnpRequest>,
+    /// Identifying metadata, matched by label selectors
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// Non-identifying metadata with free-form values
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+    /// Delete the instance after this long, e.g. "2h"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<String>,
+    /// Delete the instance at this RFC 3339 time; conflicts with `ttl`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// Stop the instance after this long without RPC connections, e.g. "30m"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout: Option<String>,
+}
+
+/// Add, change or remove labels and annotations.
+///
+/// Keys mapped to `null` are removed; keys not mentioned are left unchanged.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UpdateLabelsRequest {
+    #[serde(default)]
+    pub labels: BTreeMap<String, Option<String>>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, Option<String>>,
+}
+
+/// Replace the settings of a stopped instance.
+///
+/// Storage, port and TEE settings are fixed at creation and cannot be updated.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdateInstanceRequest {
+    pub vcpus: u32,
+    pub memory: String, // e.g., "4G", "2048M"
     #[serde(default = "default_dev")]
     pub dev: bool,
     #[serde(default)]
-    pub tee: bool,
-    #[serde(default = "default_vcpu_type")]
-    pub vcpu_type: String,
-    #[serde(default)]
     pub chain_id: Option<String>,
     #[serde(default)]
     pub block_time: Option<u64>,
@@ -28,14 +124,127 @@ pub struct CreateInstanceRequest {
     pub disable_fee: bool,
     #[serde(default)]
     pub extra_args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_block: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub messaging: Option<MessagingSettings>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accelerator: Option<String>,
+}
+
+/// L1 messaging settings. The settlement chain is either `rpc_url` or a
+/// sibling `l1_instance`; the sender's private key is an instance secret.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct MessagingSettings {
+    /// "ethereum" (default) or "starknet"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain: Option<String>,
+    /// Settlement chain RPC URL. `localhost` means the host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpc_url: Option<String>,
+    /// Instance serving the settlement chain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_instance: Option<String>,
+    /// Settlement contract address
+    pub contract_address: String,
+    /// Account sending messages to the settlement chain
+    pub sender_address: String,
+    /// Seconds between polls of the settlement chain [default: 2]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_secs: Option<u64>,
+    /// Settlement chain block to start from [default: 0]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_block: Option<u64>,
+    /// Secret holding the sender's private key [default: MESSAGING_KEY]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_secret: Option<String>,
+}
+
+/// SEV-SNP launch settings. Unset fields use values probed from the host.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SevSnpRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cbitpos: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reduced_phys_bits: Option<u8>,
+    /// Guest policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<u64>,
+    /// Base64-encoded 32-byte host data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_data: Option<String>,
+    /// Base64-encoded ID block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_block: Option<String>,
+    /// Base64-encoded ID authentication information structure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_auth: Option<String>,
+    /// Include kernel, initrd and cmdline hashes in the launch measurement
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kernel_hashes: Option<bool>,
+}
+
+/// Set an instance secret. The value is write-only: no response includes it.
+#[derive(Deserialize, Serialize)]
+pub struct SetSecretRequest {
+    pub value: String,
+}
+
+impl std::fmt::Debug for SetSecretRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SetSecretRequest")
+            .field("value", &"[redacted]")
+            .finish()
+    }
 }
 
 fn default_dev() -> bool {
     true
 }
 
-fn default_vcpu_type() -> String {
-    "host".to_string()
+/// Create a named template of instance settings.
+///
+/// Every setting is optional; unset settings are left to the create request.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CreateTemplateRequest {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcpus: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dev: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tee: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcpu_type: Option<String>,
+    /// SEV-SNP launch settings for TEE instances
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sev_snp: Option<SevSnpRequest>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accelerator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_time: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accounts: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_fee: Option<bool>,
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout: Option<String>,
 }
 
 // ============================================================================
@@ -52,6 +261,17 @@ pub struct InstanceResponse {
     pub updated_at: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub endpoints: Option<EndpointsResponse>,
+    /// Accelerator the VM was last launched with ("kvm" or "tcg")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accelerator: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+    /// Whether the guest got its secrets at the last start, for instances
+    /// with secrets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets_delivery: Option<SecretsDeliveryInfo>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -63,6 +283,51 @@ pub struct InstanceConfigResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metrics_port: Option<u16>,
     pub tee_mode: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vsock_cid: Option<u32>,
+    /// Configured accelerator ("kvm", "tcg" or "auto")
+    #[serde(default)]
+    pub accelerator: String,
+    /// A memory balloon device is attached
+    #[serde(default)]
+    pub balloon: bool,
+    #[serde(default)]
+    pub vcpu_type: String,
+    // Katana settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<String>,
+    #[serde(default)]
+    pub dev: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_time: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accounts: Option<u16>,
+    #[serde(default)]
+    pub disable_fee: bool,
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_block: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub messaging: Option<MessagingSettings>,
+    /// Custom chain definition on the config drive: "genesis" or "chain"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_spec: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Katana's args are only on the config drive
+    #[serde(default)]
+    pub config_drive_args: bool,
+    /// A TLS certificate and key are on the config drive
+    #[serde(default)]
+    pub tls: bool,
+    /// When the instance will be deleted (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_secs: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -78,6 +343,55 @@ pub struct ListInstancesResponse {
     pub total: usize,
 }
 
+// ============================================================================
+// Response Types - Templates
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateResponse {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcpus: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dev: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tee: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcpu_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sev_snp: Option<SevSnpRequest>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accelerator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_time: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accounts: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disable_fee: Option<bool>,
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_secs: Option<u64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListTemplatesResponse {
+    pub templates: Vec<TemplateResponse>,
+    pub total: usize,
+}
+
 // ============================================================================
 // Response Types - Logs
 // ============================================================================
@@ -86,7 +400,38 @@ pub struct ListInstancesResponse {
 pub struct LogsResponse {
     pub instance_name: String,
     pub lines: Vec<String>,
-    pub total_lines: usize,
+    /// Lines examined to find `lines`, including ones filtered out. This
+    /// replaces `total_lines`, as the log is no longer read in full
+    pub lines_scanned: usize,
+    /// One record per line, present when requested with `format=json`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub records: Option<Vec<LogRecord>>,
+    /// Byte offset of the earliest line examined; pass as `before` for the
+    /// previous page. Not set when reading more than one log source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_offset: Option<u64>,
+    /// Byte offset just past the last line examined; pass as `offset` to
+    /// stream from there. Not set when reading more than one log source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_offset: Option<u64>,
+    /// Whether earlier lines may match
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+/// A log line, with fields filled in when it is Katana tracing output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRecord {
+    /// Log the line was read from: "kernel" (serial console) or "katana"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub message: String,
 }
 
 // ============================================================================
@@ -100,6 +445,12 @@ pub struct StatsResponse {
     pub config: ConfigInfo,
     pub resources: ResourcesInfo,
     pub network: NetworkInfo,
+    /// Chain state read from the Katana RPC, absent when the node doesn't answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain: Option<ChainInfo>,
+    /// Why the Katana RPC probe failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_error: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -123,6 +474,38 @@ pub struct ResourcesInfo {
     pub cpu_count: usize,
     pub cpus: Vec<CpuInfo>,
     pub memory_mb: u64,
+    /// Memory the balloon leaves the guest, absent without a balloon device
+    /// (SEV-SNP guests have none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balloon_bytes: Option<u64>,
+    /// I/O totals since boot per block device
+    #[serde(default)]
+    pub block_devices: Vec<BlockDeviceInfo>,
+    /// Guest network traffic since boot, reported by the guest agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_io: Option<NetworkIoInfo>,
+    /// KVM counters for the whole VM, empty under TCG
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vm_stats: BTreeMap<String, u64>,
+    /// KVM counters summed over all vCPUs, empty under TCG
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vcpu_stats: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlockDeviceInfo {
+    /// Device or node name
+    pub device: String,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub read_operations: u64,
+    pub write_operations: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkIoInfo {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -131,12 +514,209 @@ pub struct CpuInfo {
     pub thread_id: u64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainInfo {
+    pub block_number: u64,
+    /// Time of the latest block (RFC 3339)
+    pub latest_block_timestamp: Option<String>,
+    /// Chain ID as a hex felt
+    pub chain_id: Option<String>,
+    /// Chain ID decoded as a short string, e.g. "KATANA"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_name: Option<String>,
+    pub pending_transactions: Option<u64>,
+    /// Prefunded accounts, empty outside dev mode
+    #[serde(default)]
+    pub accounts: Vec<ChainAccountInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainAccountInfo {
+    pub address: String,
+    /// Fee token balance as a hex felt
+    pub balance: Option<String>,
+}
+
+/// Prefunded accounts of a dev mode instance
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountsResponse {
+    pub instance_name: String,
+    pub rpc_url: String,
+    /// Chain ID as a hex felt, if the node answered
+    pub chain_id: Option<String>,
+    /// Chain ID decoded as a short string, e.g. "KATANA"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_name: Option<String>,
+    /// Where the accounts were read from: "rpc" or "log"
+    pub source: String,
+    pub accounts: Vec<PrefundedAccountInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrefundedAccountInfo {
+    pub address: String,
+    pub private_key: Option<String>,
+    pub public_key: Option<String>,
+    /// Fee token balance as a hex felt
+    pub balance: Option<String>,
+    /// Account contract class, needed by starkli account files
+    pub class_hash: Option<String>,
+}
+
+/// SEV-SNP attestation report from a TEE instance's guest agent
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttestationResponse {
+    pub instance_name: String,
+    /// Hex-encoded 64 bytes bound into the report
+    pub report_data: String,
+    /// Hex-encoded attestation report
+    pub report: String,
+}
+
+/// Names of an instance's secrets
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListSecretsResponse {
+    pub instance_name: String,
+    pub secrets: Vec<SecretInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecretInfo {
+    pub name: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecretsDeliveryInfo {
+    /// "pending", "delivered" or "failed"
+    pub state: String,
+    /// Why delivery failed; Katana has not started in the guest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NetworkInfo {
     pub rpc_url: String,
     pub health_url: String,
 }
 
+/// Resource usage of an instance over time
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsHistoryResponse {
+    pub instance_name: String,
+    /// Time between samples while the instance runs
+    pub interval_secs: u64,
+    /// Oldest first
+    pub samples: Vec<StatsSampleInfo>,
+}
+
+/// Latest resource usage of every instance, sent as `stats` events by
+/// `GET /api/v1/stats/stream`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    /// RFC 3339
+    pub timestamp: String,
+    pub instances: Vec<InstanceUsage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstanceUsage {
+    pub name: String,
+    pub status: String,
+    pub vcpus: u32,
+    pub memory_mb: u64,
+    pub rpc_port: u16,
+    /// "healthy", "unhealthy" (Katana is not running) or "unknown" (not
+    /// running, or no guest agent)
+    pub health: String,
+    /// Latest sample, only while the instance runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<StatsSampleInfo>,
+}
+
+/// One resource usage sample. Byte counters for network and block I/O are
+/// totals since the VM started; fields are missing if they couldn't be read.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsSampleInfo {
+    /// RFC 3339
+    pub timestamp: String,
+    /// QEMU CPU usage, 100 being one host core
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_percent: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rss_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub net_rx_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub net_tx_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_read_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_write_bytes: Option<u64>,
+}
+
+// ============================================================================
+// Response Types - Host
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HostInfoResponse {
+    pub kvm: KvmInfo,
+    pub qemu: QemuInfo,
+    pub qemu_img: ToolInfo,
+    pub qemu_nbd: ToolInfo,
+    pub nbd: NbdInfo,
+    pub sev: SevInfo,
+    #[serde(default)]
+    pub vhost_vsock: bool,
+    pub cpu_count: usize,
+    pub memory_total_bytes: u64,
+    pub memory_available_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KvmInfo {
+    pub present: bool,
+    pub accessible: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QemuInfo {
+    pub binary: ToolInfo,
+    pub machine_types: Vec<String>,
+    pub q35_supported: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ToolInfo {
+    pub available: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NbdInfo {
+    pub module_loaded: bool,
+    pub devices: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SevInfo {
+    pub device: bool,
+    pub sev: bool,
+    pub sev_es: bool,
+    pub sev_snp: bool,
+    pub snp_supported: bool,
+}
+
 // ============================================================================
 // Error Types
 // ============================================================================